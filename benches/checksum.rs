@@ -0,0 +1,32 @@
+// Checksum throughput per implementation and payload size: cargo bench --bench checksum
+use raw_socket_labs::net::checksum::Implementation;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 6] = [20, 64, 576, 1500, 9000, 65536];
+
+fn main() {
+    println!("detected: {:?}", Implementation::detect());
+    let data: Vec<u8> = (0..*SIZES.last().unwrap()).map(|idx| (idx * 31 + 7) as u8).collect();
+    for &size in SIZES.iter() {
+        for imp in Implementation::ALL.iter().filter(|imp| imp.is_available()) {
+            let data = &data[..size];
+            // about 200 ms per row
+            let mut iterations = 0u64;
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(200) {
+                for _ in 0..1000 {
+                    black_box(imp.sum(black_box(data)));
+                }
+                iterations += 1000;
+            }
+            let elapsed = start.elapsed().as_secs_f64();
+            println!(
+                "{:>6} bytes {:>9}: {:>8.2} GB/s {:>8.1} ns/op",
+                size, format!("{:?}", imp),
+                (size as f64 * iterations as f64) / elapsed / 1e9,
+                elapsed * 1e9 / iterations as f64,
+            );
+        }
+    }
+}
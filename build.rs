@@ -0,0 +1,100 @@
+// Generates the embedded IEEE OUI registry for the `oui` feature.
+//
+// By default the bundled subset in `data/ieee` is used; set `OUI_REGISTRY_DIR`
+// to a directory holding local copies of the IEEE `oui.csv` (MA-L), `mam.csv`
+// (MA-M) and `oui36.csv` (MA-S) files to regenerate the full table.
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// file name, registry name and assignment length in hex digits;
+// longest assignments go first, lookup order follows this table
+const REGISTRIES: [(&str, &str, usize); 3] = [
+    ("oui36.csv", "MA-S", 9),
+    ("mam.csv", "MA-M", 7),
+    ("oui.csv", "MA-L", 6),
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=OUI_REGISTRY_DIR");
+    if env::var_os("CARGO_FEATURE_OUI").is_none() {
+        return
+    }
+    let dir = match env::var_os("OUI_REGISTRY_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("data/ieee"),
+    };
+    let mut names = String::new();
+    let mut name_ends = Vec::new();
+    let mut name_ids = HashMap::new();
+    let mut assignments = Vec::new();
+    for (file_name, registry, digits) in REGISTRIES {
+        let path = dir.join(file_name);
+        println!("cargo:rerun-if-changed={}", path.display());
+        let mut table = Vec::new();
+        // a registry file may be absent when only part of the listings is needed
+        if let Ok(content) = fs::read_to_string(&path) {
+            for (line_idx, line) in content.lines().enumerate().skip(1) {
+                let fields = split_csv_line(line);
+                if fields.len() < 3 || fields[0] != registry {
+                    continue
+                }
+                let prefix = match u64::from_str_radix(&fields[1], 16) {
+                    Ok(prefix) if fields[1].len() == digits => prefix,
+                    _ => panic!("{}:{}: bad assignment {:?}", path.display(), line_idx + 1, fields[1]),
+                };
+                let name = fields[2].trim();
+                let id = *name_ids.entry(name.to_string()).or_insert_with(|| {
+                    names.push_str(name);
+                    name_ends.push(names.len());
+                    name_ends.len() - 1
+                });
+                table.push((prefix, id));
+            }
+        }
+        table.sort_unstable();
+        table.dedup_by_key(|(prefix, _)| *prefix);
+        assignments.push(table);
+    }
+    let mut out = String::new();
+    writeln!(out, "// @generated by build.rs from {}", dir.display()).unwrap();
+    writeln!(out, "static REGISTRY: Registry = Registry {{").unwrap();
+    writeln!(out, "    names: {:?},", names).unwrap();
+    writeln!(out, "    name_ends: &{:?},", name_ends).unwrap();
+    writeln!(out, "    assignments: [").unwrap();
+    for table in &assignments {
+        write!(out, "        &[").unwrap();
+        for (prefix, id) in table {
+            write!(out, "({:#x}, {}), ", prefix, id).unwrap();
+        }
+        writeln!(out, "],").unwrap();
+    }
+    writeln!(out, "    ],").unwrap();
+    writeln!(out, "}};").unwrap();
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("oui_registry.rs");
+    fs::write(out_path, out).unwrap();
+}
+
+// IEEE listings quote fields containing commas, and double quotes inside them
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cur.push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(core::mem::take(&mut cur)),
+            ch => cur.push(ch),
+        }
+    }
+    fields.push(cur);
+    fields
+}
@@ -0,0 +1,27 @@
+use raw_socket_labs::raw_socket_sys::*;
+use raw_socket_labs::iface::*;
+use std::time::{Duration, Instant};
+
+fn main() {
+    let mut socket = RawSocketDesc::new("eth0").unwrap();
+    socket.bind_interface().unwrap();
+    let mtu = socket.interface_mtu().unwrap();
+    let mut buf = vec![0u8; mtu + 14];
+    let mut cache = ArpCache::new(Duration::from_secs(60));
+    let mut last_print = Instant::now();
+    loop {
+        match cache.receive(&mut socket, &mut buf) {
+            Ok(Some(operation)) => println!("ARP {:?}, {} entries", operation, cache.len()),
+            Ok(None) => {},
+            Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => {},
+            Err(err) => panic!("{}", err),
+        }
+        if last_print.elapsed() > Duration::from_secs(5) {
+            cache.expire(Instant::now());
+            for (ip, mac) in cache.iter() {
+                println!("{} at {}", ip, mac);
+            }
+            last_print = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use raw_socket_labs::raw_socket_sys::*;
+use raw_socket_labs::iface::*;
+use raw_socket_labs::link::*;
+use std::time::Instant;
+
+fn main() {
+    let mut bridge = Bridge::new();
+    for name in ["eth0", "eth1"] {
+        let mut socket = RawSocketDesc::new(name).unwrap();
+        socket.bind_interface().unwrap();
+        bridge.add_port(socket);
+    }
+    // drop LLDP so neighbours keep seeing the real link
+    bridge.add_hook(|port, frame| {
+        if EthernetFrame::new(frame).ethertype() == EthernetProtocol::Lldp {
+            println!("drop LLDP from port {}", port);
+            return BridgeVerdict::Drop
+        }
+        BridgeVerdict::Forward
+    });
+    let mut buf = vec![0u8; 65536];
+    let mut last_expire = Instant::now();
+    loop {
+        bridge.poll(&mut buf, Instant::now()).unwrap();
+        if last_expire.elapsed().as_secs() >= 10 {
+            bridge.mac_table_mut().expire(Instant::now());
+            for port in 0..2 {
+                println!("port {}: {:?}", port, bridge.counters(port));
+            }
+            last_expire = Instant::now();
+        }
+    }
+}
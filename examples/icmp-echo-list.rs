@@ -43,8 +43,8 @@ fn main() {
                     }
                 }
                 */
-                if packet.next_header() == IpProtocol::Icmpv6 {
-                    let icmp = IcmpPacket::new(packet.payload());
+                if let Ok((IpProtocol::Icmpv6, payload)) = packet.upper_layer() {
+                    let icmp = IcmpPacket::new(payload);
                     println!("Icmpv6 {:?}, Code {}, Sum {}", icmp.packet_type(), icmp.code(), icmp.checksum());
                     if icmp.packet_type() == IcmpType::EchoRequest {
                         let echo = EchoRequest::new(icmp.payload());
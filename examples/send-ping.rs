@@ -3,7 +3,14 @@ use raw_socket_labs::link::*;
 use raw_socket_labs::net::*;
 
 fn main() {
-    let mut socket = RawSocketDesc::new("eth0").unwrap();
+    // link-local peer, the zone picks the interface to send on
+    let peer: IpScopedAddress = "fe80::d19d:d7:769c:3a86%eth0".parse().unwrap();
+    let ifname = match peer.zone() {
+        Some(IpZone::Index(index)) => interface_name(*index).unwrap(),
+        Some(IpZone::Name(name)) => name.clone(),
+        None => "eth0".to_string(),
+    };
+    let mut socket = RawSocketDesc::new(&ifname).unwrap();
     socket.bind_interface().unwrap();
     let mtu = socket.interface_mtu().unwrap();
     println!("mtu value: {}", mtu);
@@ -19,7 +26,7 @@ fn main() {
     let mut ip = IpPacket::new(eth_frame.payload_mut());
     ip.set_version(6);
     ip.set_src_addr(virtual_dev_ip_addr);
-    ip.set_dst_addr("fe80::d19d:d7:769c:3a86".parse().unwrap());
+    ip.set_dst_addr(peer.address());
     ip.set_next_header(IpProtocol::Icmpv6);
     ip.set_hop_limit(128);
     
@@ -35,11 +42,14 @@ fn main() {
         0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 
         0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e, 0x2f, 0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37,
     ]);
-    icmp.set_checksum(0xa688);
     ip.set_payload_len(64);
+    let (src_addr, dst_addr) = (ip.src_addr(), ip.dst_addr());
+    IcmpPacket::new(&mut ip.payload_mut()[..64]).fill_checksum(src_addr, dst_addr);
+    // Ethernet header, IPv6 header and 64 bytes of ICMPv6
+    let frame_len = 14 + 40 + 64;
     loop {
-        let _ = socket.send(&buf[..160]); // 这个160是分片系统计算的
-        println!("{:x?}", &buf[..160]);
+        let _ = socket.send(&buf[..frame_len]);
+        println!("{:x?}", &buf[..frame_len]);
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
 }
@@ -0,0 +1,18 @@
+use raw_socket_labs::raw_socket_sys::*;
+use raw_socket_labs::link::*;
+
+fn main() {
+    let target: EthernetAddress = std::env::args().nth(1)
+        .expect("usage: wake-on-lan <target mac>")
+        .parse().expect("invalid mac address");
+    let writer = wol::Writer::new(target);
+
+    let mut socket = RawSocketDesc::new("eth0").unwrap();
+    socket.bind_interface().unwrap();
+    let src_addr = "00:15:5d:b7:9f:a9".parse().unwrap();
+    let len = writer.send_raw(&mut socket, src_addr).unwrap();
+    println!("sent {} bytes raw frame to {}", len, target);
+
+    let len = writer.send_udp_broadcast().unwrap();
+    println!("sent {} bytes udp broadcast to {}", len, target);
+}
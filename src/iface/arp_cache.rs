@@ -0,0 +1,122 @@
+// IPv4 neighbour resolution cache, filled from ARP packets seen on the link
+use crate::link::{ArpOperation, ArpPacket, EthernetAddress, EthernetFrame, EthernetProtocol};
+use crate::net::Ipv4Address;
+use crate::raw_socket_sys::RawSocketDesc;
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    hardware_addr: EthernetAddress,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArpCache {
+    entries: HashMap<Ipv4Address, Entry>,
+    timeout: Duration,
+}
+
+impl ArpCache {
+    // Linux keeps reachable entries for about this long
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn new(timeout: Duration) -> Self {
+        Self { entries: HashMap::new(), timeout }
+    }
+    pub fn fill(&mut self, protocol_addr: Ipv4Address, hardware_addr: EthernetAddress, now: Instant) {
+        let expires_at = now + self.timeout;
+        self.entries.insert(protocol_addr, Entry { hardware_addr, expires_at });
+    }
+    pub fn lookup(&self, protocol_addr: Ipv4Address, now: Instant) -> Option<EthernetAddress> {
+        match self.entries.get(&protocol_addr) {
+            Some(entry) if entry.expires_at > now => Some(entry.hardware_addr),
+            _ => None,
+        }
+    }
+    pub fn remove(&mut self, protocol_addr: Ipv4Address) -> Option<EthernetAddress> {
+        self.entries.remove(&protocol_addr).map(|entry| entry.hardware_addr)
+    }
+    // drop expired entries, returns how many were removed
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| entry.expires_at > now);
+        before - self.entries.len()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Ipv4Address, EthernetAddress)> + '_ {
+        self.entries.iter().map(|(protocol_addr, entry)| (*protocol_addr, entry.hardware_addr))
+    }
+    // learn sender of an ARP packet carried in an Ethernet frame;
+    // returns the ARP operation if the frame was usable
+    pub fn process_frame(&mut self, frame: &[u8], now: Instant) -> Option<ArpOperation> {
+        if frame.len() < 14 + 28 {
+            return None
+        }
+        let frame = EthernetFrame::new(frame);
+        if frame.ethertype() != EthernetProtocol::Arp {
+            return None
+        }
+        let packet = ArpPacket::new(frame.payload());
+        if !packet.is_ethernet_ipv4() {
+            return None
+        }
+        // address probes have unspecified sender address
+        // Section 2.1.1, https://tools.ietf.org/html/rfc5227
+        if !packet.source_protocol_addr().is_unspecified() && packet.source_hardware_addr().is_unicast() {
+            self.fill(packet.source_protocol_addr(), packet.source_hardware_addr(), now);
+        }
+        Some(packet.operation())
+    }
+    // receive one frame from socket and learn from it
+    pub fn receive(&mut self, socket: &mut RawSocketDesc, buffer: &mut [u8]) -> io::Result<Option<ArpOperation>> {
+        let len = socket.recv(buffer)?;
+        Ok(self.process_frame(&buffer[..len], Instant::now()))
+    }
+}
+
+impl Default for ArpCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::{ArpWriter, EthernetFrame};
+
+    fn frame(mut writer: ArpWriter, src_addr: EthernetAddress) -> Vec<u8> {
+        let mut buf = vec![0u8; 14 + writer.len()];
+        let mut frame = EthernetFrame::new(&mut buf);
+        frame.set_dst_addr(writer.dst_addr());
+        frame.set_src_addr(src_addr);
+        frame.set_ethertype(writer.ethertype());
+        let _ = writer.write(frame.payload_mut());
+        buf
+    }
+
+    #[test]
+    fn arp_cache_learn_expire() {
+        let mac = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let now = Instant::now();
+        let mut cache = ArpCache::new(Duration::from_secs(10));
+        let request = frame(ArpWriter::request(mac, Ipv4Address::new(192, 168, 1, 2), Ipv4Address::new(192, 168, 1, 1)), mac);
+        assert_eq!(cache.process_frame(&request, now), Some(ArpOperation::Request));
+        assert_eq!(cache.lookup(Ipv4Address::new(192, 168, 1, 2), now), Some(mac));
+        assert_eq!(cache.lookup(Ipv4Address::new(192, 168, 1, 1), now), None);
+        assert_eq!(cache.lookup(Ipv4Address::new(192, 168, 1, 2), now + Duration::from_secs(10)), None);
+        assert_eq!(cache.expire(now + Duration::from_secs(5)), 0);
+        assert_eq!(cache.expire(now + Duration::from_secs(11)), 1);
+        assert!(cache.is_empty());
+        let probe = frame(ArpWriter::request(mac, Ipv4Address::UNSPECIFIED, Ipv4Address::new(192, 168, 1, 2)), mac);
+        assert_eq!(cache.process_frame(&probe, now), Some(ArpOperation::Request));
+        assert!(cache.is_empty());
+    }
+}
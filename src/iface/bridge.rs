@@ -0,0 +1,384 @@
+// Userspace transparent learning bridge, see IEEE 802.1Q Clause 8
+use crate::link::{EthernetAddress, EthernetFrame};
+use crate::raw_socket_sys::RawSocketDesc;
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+// frame source and sink of a bridge port
+pub trait Port {
+    fn recv(&mut self, buffer: &mut [u8]) -> io::Result<usize>;
+    fn send(&mut self, buffer: &[u8]) -> io::Result<usize>;
+}
+
+impl Port for RawSocketDesc {
+    fn recv(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        RawSocketDesc::recv(self, buffer)
+    }
+    fn send(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        RawSocketDesc::send(self, buffer)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PortCounters {
+    pub rx_frames: u64,
+    pub rx_bytes: u64,
+    pub tx_frames: u64,
+    pub tx_bytes: u64,
+    // frames received on this port and not forwarded anywhere
+    pub rx_dropped: u64,
+    pub tx_errors: u64,
+}
+
+// VLAN membership of a port, only used by VLAN-aware bridges
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortVlans {
+    // VLAN of untagged frames received on this port
+    pub pvid: u16,
+    // VLANs this port is member of; empty for all VLANs
+    pub members: Vec<u16>,
+}
+
+impl Default for PortVlans {
+    fn default() -> Self {
+        Self { pvid: 1, members: Vec::new() }
+    }
+}
+
+impl PortVlans {
+    fn is_member(&self, vlan_id: u16) -> bool {
+        self.members.is_empty() || self.members.contains(&vlan_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Forward,
+    Drop,
+}
+
+// called with ingress port index and frame before forwarding
+pub type Hook = Box<dyn FnMut(usize, &[u8]) -> Verdict>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MacEntry {
+    port: usize,
+    last_seen: Instant,
+}
+
+// learned addresses keyed by VLAN and address; VLAN is 0 in VLAN-unaware bridges
+#[derive(Debug, Clone)]
+pub struct MacTable {
+    entries: HashMap<(u16, EthernetAddress), MacEntry>,
+    aging_time: Duration,
+}
+
+impl MacTable {
+    // default ageing time of IEEE 802.1Q
+    pub const DEFAULT_AGING_TIME: Duration = Duration::from_secs(300);
+
+    pub fn new(aging_time: Duration) -> Self {
+        Self { entries: HashMap::new(), aging_time }
+    }
+    pub fn learn(&mut self, vlan_id: u16, address: EthernetAddress, port: usize, now: Instant) {
+        self.entries.insert((vlan_id, address), MacEntry { port, last_seen: now });
+    }
+    pub fn lookup(&self, vlan_id: u16, address: EthernetAddress, now: Instant) -> Option<usize> {
+        match self.entries.get(&(vlan_id, address)) {
+            Some(entry) if now.saturating_duration_since(entry.last_seen) < self.aging_time => Some(entry.port),
+            _ => None,
+        }
+    }
+    // drop aged entries, returns how many were removed
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let before = self.entries.len();
+        let aging_time = self.aging_time;
+        self.entries.retain(|_, entry| now.saturating_duration_since(entry.last_seen) < aging_time);
+        before - self.entries.len()
+    }
+    // forget everything learned on a port, e.g. when it goes down
+    pub fn flush_port(&mut self, port: usize) {
+        self.entries.retain(|_, entry| entry.port != port);
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (u16, EthernetAddress, usize)> + '_ {
+        self.entries.iter().map(|(&(vlan_id, address), entry)| (vlan_id, address, entry.port))
+    }
+}
+
+impl Default for MacTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_AGING_TIME)
+    }
+}
+
+struct PortState<P> {
+    port: P,
+    vlans: PortVlans,
+    counters: PortCounters,
+}
+
+pub struct Bridge<P> {
+    ports: Vec<PortState<P>>,
+    table: MacTable,
+    vlan_aware: bool,
+    hooks: Vec<Hook>,
+}
+
+impl<P: Port> Bridge<P> {
+    pub fn new() -> Self {
+        Self { ports: Vec::new(), table: MacTable::default(), vlan_aware: false, hooks: Vec::new() }
+    }
+    // learn and forward per VLAN; frames are forwarded with their tags unchanged
+    pub fn vlan_aware(self, vlan_aware: bool) -> Self {
+        Self { vlan_aware, ..self }
+    }
+    pub fn aging_time(self, aging_time: Duration) -> Self {
+        Self { table: MacTable::new(aging_time), ..self }
+    }
+    // returns port index
+    pub fn add_port(&mut self, port: P) -> usize {
+        self.add_port_with_vlans(port, PortVlans::default())
+    }
+    pub fn add_port_with_vlans(&mut self, port: P, vlans: PortVlans) -> usize {
+        self.ports.push(PortState { port, vlans, counters: PortCounters::default() });
+        self.ports.len() - 1
+    }
+    // hooks run in order; a frame is dropped as soon as one hook drops it
+    pub fn add_hook<F: FnMut(usize, &[u8]) -> Verdict + 'static>(&mut self, hook: F) {
+        self.hooks.push(Box::new(hook));
+    }
+    pub fn port(&self, idx: usize) -> &P {
+        &self.ports[idx].port
+    }
+    pub fn port_mut(&mut self, idx: usize) -> &mut P {
+        &mut self.ports[idx].port
+    }
+    pub fn counters(&self, idx: usize) -> PortCounters {
+        self.ports[idx].counters
+    }
+    pub fn mac_table(&self) -> &MacTable {
+        &self.table
+    }
+    pub fn mac_table_mut(&mut self) -> &mut MacTable {
+        &mut self.table
+    }
+    // receive at most one frame on each port and forward it;
+    // returns number of frames received
+    pub fn poll(&mut self, buffer: &mut [u8], now: Instant) -> io::Result<usize> {
+        let mut received = 0;
+        for ingress in 0..self.ports.len() {
+            let len = match self.ports[ingress].port.recv(buffer) {
+                Ok(len) => len,
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(err) => return Err(err),
+            };
+            received += 1;
+            self.process(ingress, &buffer[..len], now);
+        }
+        Ok(received)
+    }
+    // forward a frame received on ingress port
+    pub fn process(&mut self, ingress: usize, frame: &[u8], now: Instant) {
+        let counters = &mut self.ports[ingress].counters;
+        counters.rx_frames += 1;
+        counters.rx_bytes += frame.len() as u64;
+        let vlan_id = match self.classify(ingress, frame) {
+            Some(vlan_id) => vlan_id,
+            None => {
+                self.ports[ingress].counters.rx_dropped += 1;
+                return
+            },
+        };
+        let eth = EthernetFrame::new(frame);
+        let (src_addr, dst_addr) = (eth.src_addr(), eth.dst_addr());
+        // dropped frames must not be learned
+        if self.hooks.iter_mut().any(|hook| hook(ingress, frame) == Verdict::Drop) {
+            self.ports[ingress].counters.rx_dropped += 1;
+            return
+        }
+        if src_addr.is_unicast() {
+            self.table.learn(vlan_id, src_addr, ingress, now);
+        }
+        let known = if dst_addr.is_unicast() { self.table.lookup(vlan_id, dst_addr, now) } else { None };
+        let mut forwarded = false;
+        for egress in 0..self.ports.len() {
+            let selected = match known {
+                Some(port) => port == egress,
+                None => !self.vlan_aware || self.ports[egress].vlans.is_member(vlan_id),
+            };
+            if egress == ingress || !selected {
+                continue
+            }
+            let port = &mut self.ports[egress];
+            match port.port.send(frame) {
+                Ok(_) => {
+                    port.counters.tx_frames += 1;
+                    port.counters.tx_bytes += frame.len() as u64;
+                    forwarded = true;
+                },
+                Err(_) => port.counters.tx_errors += 1,
+            }
+        }
+        if !forwarded {
+            self.ports[ingress].counters.rx_dropped += 1;
+        }
+    }
+    // VLAN of frame, or None if ingress filtering rejects it
+    fn classify(&self, ingress: usize, frame: &[u8]) -> Option<u16> {
+        if frame.len() < 14 {
+            return None
+        }
+        if !self.vlan_aware {
+            return Some(0)
+        }
+        let vlans = &self.ports[ingress].vlans;
+        let vlan_id = match EthernetFrame::new(frame).vlan_tag() {
+            Some(tag) if frame.len() >= 18 && tag.vlan_id != 0 => tag.vlan_id,
+            _ => vlans.pvid,
+        };
+        if vlans.is_member(vlan_id) { Some(vlan_id) } else { None }
+    }
+}
+
+impl<P: Port> Default for Bridge<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::{EthernetProtocol, VlanTag};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct TestPort {
+        rx: Vec<Vec<u8>>,
+        tx: Vec<Vec<u8>>,
+    }
+
+    impl Port for TestPort {
+        fn recv(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            match self.rx.pop() {
+                Some(frame) => {
+                    buffer[..frame.len()].copy_from_slice(&frame);
+                    Ok(frame.len())
+                },
+                None => Err(io::ErrorKind::WouldBlock.into()),
+            }
+        }
+        fn send(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            self.tx.push(buffer.to_vec());
+            Ok(buffer.len())
+        }
+    }
+
+    fn frame(dst: &str, src: &str, vlan_id: Option<u16>) -> Vec<u8> {
+        let mut buf = vec![0u8; 60];
+        let mut frame = EthernetFrame::new(&mut buf);
+        frame.set_dst_addr(dst.parse().unwrap());
+        frame.set_src_addr(src.parse().unwrap());
+        match vlan_id {
+            Some(vlan_id) => {
+                frame.set_ethertype(EthernetProtocol::Vlan);
+                let tci = VlanTag::new(vlan_id).tci().to_be_bytes();
+                frame.payload_mut()[..2].copy_from_slice(&tci);
+            },
+            None => frame.set_ethertype(EthernetProtocol::Ipv6),
+        }
+        buf
+    }
+
+    const A: &str = "02-00-00-00-00-0a";
+    const B: &str = "02-00-00-00-00-0b";
+    const BROADCAST: &str = "ff-ff-ff-ff-ff-ff";
+
+    fn bridge(n_ports: usize) -> Bridge<TestPort> {
+        let mut bridge = Bridge::new();
+        for _ in 0..n_ports {
+            bridge.add_port(TestPort::default());
+        }
+        bridge
+    }
+
+    #[test]
+    fn bridge_learn_flood() {
+        let mut bridge = bridge(3);
+        let now = Instant::now();
+        // unknown destination is flooded
+        bridge.process(0, &frame(B, A, None), now);
+        assert_eq!((bridge.port(1).tx.len(), bridge.port(2).tx.len()), (1, 1));
+        assert_eq!(bridge.mac_table().lookup(0, A.parse().unwrap(), now), Some(0));
+        // reply goes to learned port only
+        bridge.process(1, &frame(A, B, None), now);
+        assert_eq!((bridge.port(0).tx.len(), bridge.port(2).tx.len()), (1, 1));
+        // broadcast is flooded
+        bridge.process(0, &frame(BROADCAST, A, None), now);
+        assert_eq!((bridge.port(1).tx.len(), bridge.port(2).tx.len()), (2, 2));
+        // frames to a station on ingress port are filtered
+        bridge.process(1, &frame(B, B, None), now);
+        assert_eq!(bridge.counters(1).rx_dropped, 1);
+        assert_eq!(bridge.counters(0).rx_frames, 2);
+        assert_eq!(bridge.counters(2).tx_frames, 2);
+        // aged entries are flooded again
+        let later = now + MacTable::DEFAULT_AGING_TIME;
+        assert_eq!(bridge.mac_table_mut().expire(later), 2);
+        bridge.process(1, &frame(A, B, None), later);
+        assert_eq!(bridge.port(2).tx.len(), 3);
+    }
+
+    #[test]
+    fn bridge_poll_hooks() {
+        let mut bridge = bridge(2);
+        let seen = Rc::new(Cell::new(0));
+        let counter = seen.clone();
+        bridge.add_hook(move |_, frame| {
+            counter.set(counter.get() + 1);
+            if EthernetFrame::new(frame).dst_addr().is_broadcast() { Verdict::Drop } else { Verdict::Forward }
+        });
+        bridge.process(1, &frame(BROADCAST, B, None), Instant::now());
+        assert_eq!(bridge.mac_table().lookup(0, B.parse().unwrap(), Instant::now()), None);
+        seen.set(0);
+        bridge.port_mut(0).rx = vec![frame(B, A, None), frame(BROADCAST, A, None)];
+        let mut buf = [0u8; 1514];
+        assert_eq!(bridge.poll(&mut buf, Instant::now()).unwrap(), 1);
+        assert_eq!(bridge.poll(&mut buf, Instant::now()).unwrap(), 1);
+        assert_eq!(bridge.poll(&mut buf, Instant::now()).unwrap(), 0);
+        assert_eq!(seen.get(), 2);
+        assert_eq!(bridge.port(1).tx.len(), 1);
+        assert_eq!(bridge.counters(0).rx_dropped, 1);
+    }
+
+    #[test]
+    fn bridge_vlan_aware() {
+        let mut bridge = Bridge::new().vlan_aware(true);
+        bridge.add_port_with_vlans(TestPort::default(), PortVlans { pvid: 10, members: vec![10, 20] });
+        bridge.add_port_with_vlans(TestPort::default(), PortVlans { pvid: 10, members: vec![10] });
+        bridge.add_port_with_vlans(TestPort::default(), PortVlans { pvid: 20, members: vec![20] });
+        let now = Instant::now();
+        bridge.process(0, &frame(BROADCAST, A, None), now);
+        assert_eq!((bridge.port(1).tx.len(), bridge.port(2).tx.len()), (1, 0));
+        bridge.process(0, &frame(BROADCAST, A, Some(20)), now);
+        assert_eq!((bridge.port(1).tx.len(), bridge.port(2).tx.len()), (1, 1));
+        assert_eq!(bridge.mac_table().lookup(10, A.parse().unwrap(), now), Some(0));
+        assert_eq!(bridge.mac_table().lookup(20, A.parse().unwrap(), now), Some(0));
+        // ingress filtering of VLAN the port is not member of
+        bridge.process(1, &frame(A, B, Some(20)), now);
+        assert_eq!(bridge.port(0).tx.len(), 0);
+        assert_eq!(bridge.counters(1).rx_dropped, 1);
+        // tagged frames cut off before or inside the TCI must not panic
+        for len in 14..18 {
+            bridge.process(0, &frame(BROADCAST, A, Some(20))[..len], now);
+        }
+        assert_eq!(bridge.counters(0).rx_frames, 6);
+    }
+}
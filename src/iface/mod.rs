@@ -1,3 +1,14 @@
+mod arp_cache;
+mod bridge;
+mod reassembly;
+
+pub use arp_cache::ArpCache;
+pub use reassembly::{Expired as ReassemblyExpired, ReassemblyError, Reassembler};
+pub use bridge::{
+    Bridge, Hook as BridgeHook, MacTable, Port as BridgePort, PortCounters as BridgePortCounters,
+    PortVlans as BridgePortVlans, Verdict as BridgeVerdict,
+};
+
 // network interface
 
 // create a socket (with a state machine), then use interface to change these sockets
@@ -0,0 +1,387 @@
+// IPv6 fragment reassembly: See https://tools.ietf.org/html/rfc8200#section-4.5
+use crate::net::ext_header::{ExtHeader, ExtHeaderError, Fragment};
+use crate::net::{IpAddress, IpPacket};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const IP_HEADER_LEN: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    src_addr: [u8; 16],
+    dst_addr: [u8; 16],
+    identification: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    first_seen: Instant,
+    // fixed header and extension headers before Fragment header, from first fragment
+    unfragmentable: Option<Vec<u8>>,
+    // index of next header field to patch in unfragmentable part
+    next_header_idx: usize,
+    next_header: u8,
+    // copy of the first fragment as received, quoted in Time Exceeded
+    first_fragment: Option<Vec<u8>>,
+    data: Vec<u8>,
+    // received ranges of data, never overlapping
+    ranges: Vec<(usize, usize)>,
+    total_len: Option<usize>,
+    // overlap was seen; remaining fragments are dropped until timeout
+    discarded: bool,
+}
+
+impl Entry {
+    fn memory(&self) -> usize {
+        self.data.len() + self.unfragmentable.as_ref().map_or(0, Vec::len) +
+            self.first_fragment.as_ref().map_or(0, Vec::len)
+    }
+    fn is_complete(&self) -> bool {
+        self.unfragmentable.is_some() &&
+            self.total_len == Some(self.ranges.iter().map(|(start, end)| end - start).sum())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReassemblyError {
+    // packet carries no Fragment header
+    NotFragment,
+    Malformed(ExtHeaderError),
+    // non-last fragment not a multiple of 8 bytes, reassembled packet too long,
+    // or fragments disagree on total length
+    BadLength,
+    // fragment overlaps data already received, Section 4 of RFC 5722
+    Overlap,
+    // datagram was discarded earlier because of an overlap
+    Discarded,
+    MemoryLimit,
+}
+
+// incomplete reassembly dropped by `expire`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expired {
+    pub src_addr: IpAddress,
+    pub dst_addr: IpAddress,
+    pub identification: u32,
+    // the first fragment, if it was received; only then an ICMPv6
+    // Time Exceeded (code 1) should be sent, quoting this packet
+    pub first_fragment: Option<Vec<u8>>,
+}
+
+// buffered bytes, kept up to date so limits are checked without a scan
+#[derive(Debug, Clone, Default)]
+struct Usage {
+    total: usize,
+    per_source: HashMap<[u8; 16], usize>,
+}
+
+impl Usage {
+    fn source(&self, src_addr: [u8; 16]) -> usize {
+        self.per_source.get(&src_addr).copied().unwrap_or(0)
+    }
+    fn charge(&mut self, src_addr: [u8; 16], bytes: usize) {
+        self.total += bytes;
+        *self.per_source.entry(src_addr).or_insert(0) += bytes;
+    }
+    fn release(&mut self, src_addr: [u8; 16], bytes: usize) {
+        self.total -= bytes;
+        if let Some(source) = self.per_source.get_mut(&src_addr) {
+            *source -= bytes;
+            if *source == 0 {
+                self.per_source.remove(&src_addr);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reassembler {
+    entries: HashMap<Key, Entry>,
+    usage: Usage,
+    timeout: Duration,
+    max_bytes_per_source: usize,
+    max_total_bytes: usize,
+}
+
+impl Reassembler {
+    // reassembly time limit of RFC 8200
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            usage: Usage::default(),
+            timeout,
+            max_bytes_per_source: 256 * 1024,
+            max_total_bytes: 4 * 1024 * 1024,
+        }
+    }
+    pub fn max_bytes_per_source(self, max_bytes_per_source: usize) -> Self {
+        Self { max_bytes_per_source, ..self }
+    }
+    pub fn max_total_bytes(self, max_total_bytes: usize) -> Self {
+        Self { max_total_bytes, ..self }
+    }
+    // number of datagrams being reassembled
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    // bytes buffered for all datagrams
+    pub fn memory(&self) -> usize {
+        self.usage.total
+    }
+    fn remove(&mut self, key: &Key) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.usage.release(key.src_addr, entry.memory());
+        Some(entry)
+    }
+    // add a fragment; returns the reassembled IPv6 packet once complete,
+    // with Fragment header removed and payload length fixed
+    pub fn process(&mut self, packet: &[u8], now: Instant) -> Result<Option<Vec<u8>>, ReassemblyError> {
+        if packet.len() < IP_HEADER_LEN {
+            return Err(ReassemblyError::Malformed(ExtHeaderError::Truncated))
+        }
+        let ip = IpPacket::new(packet);
+        let packet = &packet[..ip.total_len().min(packet.len())];
+        let mut headers = ip.ext_headers();
+        let mut next_header_idx = 6;
+        let (frag_start, fragment) = loop {
+            let offset = packet.len() - headers.remaining().len();
+            match headers.next() {
+                Some(Ok(ExtHeader::Fragment(fragment))) => break (offset, fragment),
+                Some(Ok(_)) => next_header_idx = offset,
+                Some(Err(err)) => return Err(ReassemblyError::Malformed(err)),
+                None => return Err(ReassemblyError::NotFragment),
+            }
+        };
+        let data = &packet[frag_start + Fragment::<()>::LEN..];
+        let offset = fragment.frag_offset() as usize;
+        let end = offset + data.len();
+        let more_fragments = fragment.more_fragments();
+        if (more_fragments && !data.len().is_multiple_of(8)) || frag_start + end > IP_HEADER_LEN + 0xffff {
+            return Err(ReassemblyError::BadLength)
+        }
+        // atomic fragments are processed in isolation, Section 4 of RFC 6946
+        if offset == 0 && !more_fragments {
+            return Ok(Some(defragment(packet[..frag_start].to_vec(), next_header_idx, fragment.next_header().into(), data)))
+        }
+        let key = Key {
+            src_addr: ip.src_addr().octets(),
+            dst_addr: ip.dst_addr().octets(),
+            identification: fragment.identification(),
+        };
+        // data buffer grows up to the end of the fragment, whatever its own length
+        let buffered = self.entries.get(&key).map_or(0, |entry| entry.data.len());
+        let added = end.saturating_sub(buffered) + if offset == 0 { frag_start + packet.len() } else { 0 };
+        if self.usage.source(key.src_addr) + added > self.max_bytes_per_source ||
+            self.usage.total + added > self.max_total_bytes
+        {
+            self.remove(&key);
+            return Err(ReassemblyError::MemoryLimit)
+        }
+        let entry = self.entries.entry(key).or_insert_with(|| Entry {
+            first_seen: now,
+            unfragmentable: None,
+            next_header_idx: 0,
+            next_header: 0,
+            first_fragment: None,
+            data: Vec::new(),
+            ranges: Vec::new(),
+            total_len: None,
+            discarded: false,
+        });
+        if entry.discarded {
+            return Err(ReassemblyError::Discarded)
+        }
+        if let Some(&(start, stop)) = entry.ranges.iter().find(|&&(start, stop)| offset < stop && start < end) {
+            // identical retransmissions are harmless
+            if (start, stop) == (offset, end) && entry.data[offset..end] == *data {
+                return Ok(None)
+            }
+            self.usage.release(key.src_addr, entry.memory());
+            entry.discarded = true;
+            entry.unfragmentable = None;
+            entry.first_fragment = None;
+            entry.data = Vec::new();
+            entry.ranges.clear();
+            return Err(ReassemblyError::Overlap)
+        }
+        let bad_len = match entry.total_len {
+            Some(total_len) => end > total_len || (!more_fragments && end != total_len),
+            None => !more_fragments && entry.ranges.iter().any(|&(_, stop)| stop > end),
+        };
+        if bad_len {
+            self.remove(&key);
+            return Err(ReassemblyError::BadLength)
+        }
+        if !more_fragments {
+            entry.total_len = Some(end);
+        }
+        let before = entry.memory();
+        if offset == 0 {
+            entry.unfragmentable = Some(packet[..frag_start].to_vec());
+            entry.next_header_idx = next_header_idx;
+            entry.next_header = fragment.next_header().into();
+            entry.first_fragment = Some(packet.to_vec());
+        }
+        if entry.data.len() < end {
+            entry.data.reserve_exact(end - entry.data.len());
+            entry.data.resize(end, 0);
+        }
+        entry.data[offset..end].copy_from_slice(data);
+        entry.ranges.push((offset, end));
+        self.usage.charge(key.src_addr, entry.memory() - before);
+        if !entry.is_complete() {
+            return Ok(None)
+        }
+        let entry = self.remove(&key).unwrap();
+        Ok(Some(defragment(entry.unfragmentable.unwrap(), entry.next_header_idx, entry.next_header, &entry.data)))
+    }
+    // drop reassemblies older than the timeout
+    pub fn expire(&mut self, now: Instant) -> Vec<Expired> {
+        let timeout = self.timeout;
+        let usage = &mut self.usage;
+        let mut expired = Vec::new();
+        self.entries.retain(|key, entry| {
+            if now.saturating_duration_since(entry.first_seen) < timeout {
+                return true
+            }
+            usage.release(key.src_addr, entry.memory());
+            if !entry.discarded {
+                expired.push(Expired {
+                    src_addr: IpAddress::from(key.src_addr),
+                    dst_addr: IpAddress::from(key.dst_addr),
+                    identification: key.identification,
+                    first_fragment: entry.first_fragment.take(),
+                });
+            }
+            false
+        });
+        expired
+    }
+}
+
+// append data to the unfragmentable part, dropping the Fragment header
+fn defragment(mut packet: Vec<u8>, next_header_idx: usize, next_header: u8, data: &[u8]) -> Vec<u8> {
+    packet[next_header_idx] = next_header;
+    packet.extend_from_slice(data);
+    let payload_len = (packet.len() - IP_HEADER_LEN) as u16;
+    IpPacket::new(&mut packet).set_payload_len(payload_len);
+    packet
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{IcmpWriter, IpFragmenter, IpProtocol};
+
+    fn fragment_packet(data_len: usize, mtu: usize) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let data = (0..data_len).map(|i| i as u8).collect::<Vec<_>>();
+        let mut template = [0u8; 40];
+        let mut ip = IpPacket::new(&mut template[..]);
+        ip.set_version(6);
+        ip.set_next_header(IpProtocol::Icmpv6);
+        ip.set_hop_limit(64);
+        ip.set_src_addr("fe80::1".parse().unwrap());
+        ip.set_dst_addr("fe80::2".parse().unwrap());
+        let mut whole = template.to_vec();
+        whole.resize(40 + data_len + 8, 0);
+        let _ = IcmpWriter::echo_request(1, 1, &data).write(&mut whole[40..]);
+        IpPacket::new(&mut whole).set_payload_len(data_len as u16 + 8);
+        let mut fragmenter = IpFragmenter::new(&IpPacket::new(&template), IcmpWriter::echo_request(1, 1, &data), mtu, 42).unwrap();
+        let mut fragments = Vec::new();
+        let mut buf = vec![0u8; mtu];
+        loop {
+            let len = fragmenter.write_next(&mut buf);
+            if len == 0 {
+                break (whole, fragments)
+            }
+            fragments.push(buf[..len].to_vec());
+        }
+    }
+
+    #[test]
+    fn reassemble_out_of_order() {
+        let (whole, fragments) = fragment_packet(3000, 1280);
+        let mut reassembler = Reassembler::default();
+        let now = Instant::now();
+        assert_eq!(reassembler.process(&fragments[2], now), Ok(None));
+        assert_eq!(reassembler.process(&fragments[0], now), Ok(None));
+        // duplicates are ignored
+        assert_eq!(reassembler.process(&fragments[0], now), Ok(None));
+        assert_eq!(reassembler.process(&fragments[1], now), Ok(Some(whole)));
+        assert!(reassembler.is_empty());
+        assert_eq!(reassembler.process(&fragment_packet(8, 1280).0, now), Err(ReassemblyError::NotFragment));
+    }
+
+    #[test]
+    fn reassemble_atomic_fragment() {
+        let (whole, fragments) = fragment_packet(3000, 1280);
+        let mut reassembler = Reassembler::default();
+        let now = Instant::now();
+        assert_eq!(reassembler.process(&fragments[0], now), Ok(None));
+        // same identification as the datagram in flight, but must not disturb it
+        let mut atomic = fragments[2].clone();
+        Fragment::new(&mut atomic[40..48]).set_frag_offset(0);
+        let packet = reassembler.process(&atomic, now).unwrap().unwrap();
+        let ip = IpPacket::new(&packet[..]);
+        assert_eq!(ip.next_header(), IpProtocol::Icmpv6);
+        assert_eq!(ip.payload_len() as usize, atomic.len() - 48);
+        assert_eq!(&packet[40..], &atomic[48..]);
+        assert_eq!(reassembler.len(), 1);
+        assert_eq!(reassembler.process(&fragments[1], now), Ok(None));
+        assert_eq!(reassembler.process(&fragments[2], now), Ok(Some(whole)));
+    }
+
+    #[test]
+    fn reassemble_overlap() {
+        let (_, fragments) = fragment_packet(3000, 1280);
+        let mut reassembler = Reassembler::default();
+        let now = Instant::now();
+        assert_eq!(reassembler.process(&fragments[0], now), Ok(None));
+        let mut overlapping = fragments[1].clone();
+        Fragment::new(&mut overlapping[40..48]).set_frag_offset(1224);
+        assert_eq!(reassembler.process(&overlapping, now), Err(ReassemblyError::Overlap));
+        assert_eq!(reassembler.process(&fragments[1], now), Err(ReassemblyError::Discarded));
+        assert_eq!(reassembler.process(&fragments[2], now), Err(ReassemblyError::Discarded));
+        // discarded datagrams are not reported
+        assert!(reassembler.expire(now + Reassembler::DEFAULT_TIMEOUT).is_empty());
+        assert!(reassembler.is_empty());
+    }
+
+    #[test]
+    fn reassemble_timeout_and_limits() {
+        let (_, fragments) = fragment_packet(3000, 1280);
+        let mut reassembler = Reassembler::default();
+        let now = Instant::now();
+        assert_eq!(reassembler.process(&fragments[1], now), Ok(None));
+        assert_eq!(reassembler.process(&fragments[0], now + Duration::from_secs(30)), Ok(None));
+        assert!(reassembler.expire(now + Duration::from_secs(59)).is_empty());
+        let expired = reassembler.expire(now + Duration::from_secs(60));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].identification, 42);
+        assert_eq!(expired[0].first_fragment.as_ref(), Some(&fragments[0]));
+        assert_eq!(reassembler.memory(), 0);
+        let mut reassembler = Reassembler::default().max_bytes_per_source(3000);
+        assert_eq!(reassembler.process(&fragments[1], now), Ok(None));
+        assert_eq!(reassembler.memory(), reassembler.entries.values().map(Entry::memory).sum::<usize>());
+        assert_eq!(reassembler.process(&fragments[2], now), Err(ReassemblyError::MemoryLimit));
+        assert!(reassembler.is_empty());
+        assert_eq!(reassembler.memory(), 0);
+        // a single small fragment far into the datagram is charged for the gap before it
+        let mut reassembler = Reassembler::default().max_bytes_per_source(16 * 1024);
+        let mut far = fragments[1].clone();
+        Fragment::new(&mut far[40..48]).set_frag_offset(64000);
+        assert_eq!(reassembler.process(&far, now), Err(ReassemblyError::MemoryLimit));
+        assert_eq!(reassembler.memory(), 0);
+    }
+}
@@ -2,5 +2,6 @@ pub mod raw_socket_sys;
 pub mod link;
 pub mod net;
 mod packet_write;
+pub use packet_write::PacketWriter;
 pub mod iface;
 pub mod socket;
@@ -0,0 +1,380 @@
+// ARP and RARP: See https://tools.ietf.org/html/rfc826
+// and https://tools.ietf.org/html/rfc903
+use super::eui::Eui48;
+use super::ether::Type as EthernetProtocol;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::ops::Range;
+use crate::net::Ipv4Address;
+use crate::packet_write::{PacketWrite, PacketWriter};
+
+// ARP packet; address fields are variable length, typed accessors assume
+// Ethernet hardware addresses and IPv4 protocol addresses
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<T> {
+    inner: T
+}
+
+impl<T> Packet<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const HTYPE: Range<usize> = 0..2;
+    const PTYPE: Range<usize> = 2..4;
+    const HLEN: usize = 4;
+    const PLEN: usize = 5;
+    const OPER: Range<usize> = 6..8;
+    const ADDRESSES_START: usize = 8;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn hardware_type(&self) -> Hardware {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::HTYPE]).into()
+    }
+    pub fn protocol_type(&self) -> EthernetProtocol {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::PTYPE]).into()
+    }
+    pub fn hardware_len(&self) -> u8 {
+        self.inner.as_ref()[Self::HLEN]
+    }
+    pub fn protocol_len(&self) -> u8 {
+        self.inner.as_ref()[Self::PLEN]
+    }
+    pub fn operation(&self) -> Operation {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::OPER]).into()
+    }
+    pub fn source_hardware_addr(&self) -> Eui48 {
+        Eui48::from_bytes(&self.inner.as_ref()[self.sha()])
+    }
+    pub fn source_protocol_addr(&self) -> Ipv4Address {
+        Ipv4Address::from_bytes(&self.inner.as_ref()[self.spa()])
+    }
+    pub fn target_hardware_addr(&self) -> Eui48 {
+        Eui48::from_bytes(&self.inner.as_ref()[self.tha()])
+    }
+    pub fn target_protocol_addr(&self) -> Ipv4Address {
+        Ipv4Address::from_bytes(&self.inner.as_ref()[self.tpa()])
+    }
+    // whether typed address accessors can be used
+    pub fn is_ethernet_ipv4(&self) -> bool {
+        self.hardware_type() == Hardware::Ethernet && self.hardware_len() == 6 &&
+            self.protocol_type() == EthernetProtocol::Ipv4 && self.protocol_len() == 4
+    }
+    // length of ARP packet including all addresses
+    pub fn total_len(&self) -> usize {
+        self.tpa().end
+    }
+    fn sha(&self) -> Range<usize> {
+        let start = Self::ADDRESSES_START;
+        start..start + self.hardware_len() as usize
+    }
+    fn spa(&self) -> Range<usize> {
+        let start = self.sha().end;
+        start..start + self.protocol_len() as usize
+    }
+    fn tha(&self) -> Range<usize> {
+        let start = self.spa().end;
+        start..start + self.hardware_len() as usize
+    }
+    fn tpa(&self) -> Range<usize> {
+        let start = self.tha().end;
+        start..start + self.protocol_len() as usize
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    pub fn source_hardware_addr_bytes(&self) -> &'a [u8] {
+        &self.inner.as_ref()[self.sha()]
+    }
+    pub fn source_protocol_addr_bytes(&self) -> &'a [u8] {
+        &self.inner.as_ref()[self.spa()]
+    }
+    pub fn target_hardware_addr_bytes(&self) -> &'a [u8] {
+        &self.inner.as_ref()[self.tha()]
+    }
+    pub fn target_protocol_addr_bytes(&self) -> &'a [u8] {
+        &self.inner.as_ref()[self.tpa()]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_hardware_type(&mut self, hardware: Hardware) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::HTYPE], hardware.into())
+    }
+    pub fn set_protocol_type(&mut self, protocol: EthernetProtocol) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::PTYPE], protocol.into())
+    }
+    pub fn set_hardware_len(&mut self, len: u8) {
+        self.inner.as_mut()[Self::HLEN] = len
+    }
+    pub fn set_protocol_len(&mut self, len: u8) {
+        self.inner.as_mut()[Self::PLEN] = len
+    }
+    pub fn set_operation(&mut self, operation: Operation) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::OPER], operation.into())
+    }
+    // address setters use lengths already written to the packet
+    pub fn set_source_hardware_addr(&mut self, addr: Eui48) {
+        let range = self.sha();
+        self.inner.as_mut()[range].copy_from_slice(&addr.to_bytes())
+    }
+    pub fn set_source_protocol_addr(&mut self, addr: Ipv4Address) {
+        let range = self.spa();
+        self.inner.as_mut()[range].copy_from_slice(&addr.octets())
+    }
+    pub fn set_target_hardware_addr(&mut self, addr: Eui48) {
+        let range = self.tha();
+        self.inner.as_mut()[range].copy_from_slice(&addr.to_bytes())
+    }
+    pub fn set_target_protocol_addr(&mut self, addr: Ipv4Address) {
+        let range = self.tpa();
+        self.inner.as_mut()[range].copy_from_slice(&addr.octets())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hardware {
+    Ethernet, // 1
+    Unknown(u16),
+}
+
+impl From<u16> for Hardware {
+    fn from(src: u16) -> Self {
+        match src {
+            1 => Hardware::Ethernet,
+            others => Hardware::Unknown(others),
+        }
+    }
+}
+
+impl From<Hardware> for u16 {
+    fn from(src: Hardware) -> u16 {
+        match src {
+            Hardware::Ethernet => 1,
+            Hardware::Unknown(others) => others
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Request, // 1
+    Reply, // 2
+    RarpRequest, // 3
+    RarpReply, // 4
+    Unknown(u16),
+}
+
+impl From<u16> for Operation {
+    fn from(src: u16) -> Self {
+        match src {
+            1 => Operation::Request,
+            2 => Operation::Reply,
+            3 => Operation::RarpRequest,
+            4 => Operation::RarpReply,
+            others => Operation::Unknown(others),
+        }
+    }
+}
+
+impl From<Operation> for u16 {
+    fn from(src: Operation) -> u16 {
+        match src {
+            Operation::Request => 1,
+            Operation::Reply => 2,
+            Operation::RarpRequest => 3,
+            Operation::RarpReply => 4,
+            Operation::Unknown(others) => others
+        }
+    }
+}
+
+// Ethernet/IPv4 ARP packet writer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Writer {
+    operation: Operation,
+    source_hardware_addr: Eui48,
+    source_protocol_addr: Ipv4Address,
+    target_hardware_addr: Eui48,
+    target_protocol_addr: Ipv4Address,
+    byte_idx: usize,
+}
+
+impl Writer {
+    const LEN: usize = 28;
+    fn new(
+        operation: Operation,
+        source_hardware_addr: Eui48, source_protocol_addr: Ipv4Address,
+        target_hardware_addr: Eui48, target_protocol_addr: Ipv4Address,
+    ) -> Writer {
+        Writer {
+            operation,
+            source_hardware_addr, source_protocol_addr,
+            target_hardware_addr, target_protocol_addr,
+            byte_idx: 0,
+        }
+    }
+    // who has target_protocol_addr? tell source_protocol_addr
+    pub fn request(source_hardware_addr: Eui48, source_protocol_addr: Ipv4Address, target_protocol_addr: Ipv4Address) -> Writer {
+        Writer::new(
+            Operation::Request,
+            source_hardware_addr, source_protocol_addr,
+            Eui48::default(), target_protocol_addr,
+        )
+    }
+    pub fn reply(
+        source_hardware_addr: Eui48, source_protocol_addr: Ipv4Address,
+        target_hardware_addr: Eui48, target_protocol_addr: Ipv4Address,
+    ) -> Writer {
+        Writer::new(
+            Operation::Reply,
+            source_hardware_addr, source_protocol_addr,
+            target_hardware_addr, target_protocol_addr,
+        )
+    }
+    // answer a received request with our hardware address
+    pub fn reply_to<T: AsRef<[u8]>>(request: &Packet<T>, hardware_addr: Eui48) -> Writer {
+        Writer::reply(
+            hardware_addr, request.target_protocol_addr(),
+            request.source_hardware_addr(), request.source_protocol_addr(),
+        )
+    }
+    // announce our own address, sent as a request for the address itself
+    // Section 4.6, https://tools.ietf.org/html/rfc5227
+    pub fn gratuitous(hardware_addr: Eui48, protocol_addr: Ipv4Address) -> Writer {
+        Writer::new(
+            Operation::Request,
+            hardware_addr, protocol_addr,
+            Eui48::default(), protocol_addr,
+        )
+    }
+    // ask for the protocol address of target_hardware_addr
+    pub fn rarp_request(source_hardware_addr: Eui48, target_hardware_addr: Eui48) -> Writer {
+        Writer::new(
+            Operation::RarpRequest,
+            source_hardware_addr, Ipv4Address::UNSPECIFIED,
+            target_hardware_addr, Ipv4Address::UNSPECIFIED,
+        )
+    }
+    // ethertype of frame carrying this packet
+    pub fn ethertype(&self) -> EthernetProtocol {
+        match self.operation {
+            Operation::RarpRequest | Operation::RarpReply => EthernetProtocol::Rarp,
+            _ => EthernetProtocol::Arp,
+        }
+    }
+    // destination of frame carrying this packet
+    pub fn dst_addr(&self) -> Eui48 {
+        match self.operation {
+            Operation::Reply | Operation::RarpReply => self.target_hardware_addr,
+            _ => Eui48::BROADCAST,
+        }
+    }
+    pub fn len(&self) -> usize {
+        Self::LEN
+    }
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+    #[must_use]
+    pub fn write(&mut self, buffer: &mut [u8]) -> usize {
+        let mut write = PacketWrite::new(buffer, self.byte_idx);
+        write.write_u16_at(0..2, Hardware::Ethernet.into());
+        write.write_u16_at(2..4, EthernetProtocol::Ipv4.into());
+        write.write_u8_at(4..5, 6);
+        write.write_u8_at(5..6, 4);
+        write.write_u16_at(6..8, self.operation.into());
+        write.write_slice_at(8.., &self.source_hardware_addr.to_bytes());
+        write.write_slice_at(14.., &self.source_protocol_addr.octets());
+        write.write_slice_at(18.., &self.target_hardware_addr.to_bytes());
+        write.write_slice_at(24.., &self.target_protocol_addr.octets());
+        self.byte_idx = write.buffer_index();
+        write.bytes_written()
+    }
+}
+
+impl PacketWriter for Writer {
+    fn packet_len(&self) -> usize {
+        self.len()
+    }
+    fn write_packet(&mut self, buffer: &mut [u8]) -> usize {
+        self.write(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: [u8; 28] = [
+        0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x01,
+        0x00, 0x15, 0x5d, 0xb7, 0x9f, 0xa9, 192, 168, 1, 2,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 192, 168, 1, 1,
+    ];
+
+    #[test]
+    fn arp_packet_parse() {
+        let packet = Packet::new(&REQUEST[..]);
+        assert!(packet.is_ethernet_ipv4());
+        assert_eq!(packet.operation(), Operation::Request);
+        assert_eq!(packet.source_hardware_addr(), "00-15-5d-b7-9f-a9".parse().unwrap());
+        assert_eq!(packet.source_protocol_addr(), Ipv4Address::new(192, 168, 1, 2));
+        assert_eq!(packet.target_hardware_addr(), Eui48::default());
+        assert_eq!(packet.target_protocol_addr(), Ipv4Address::new(192, 168, 1, 1));
+        assert_eq!(packet.total_len(), 28);
+        assert_eq!(packet.target_protocol_addr_bytes(), &[192, 168, 1, 1]);
+    }
+
+    #[test]
+    fn arp_packet_build() {
+        let mut buf = [0u8; 28];
+        let mut packet = Packet::new(&mut buf);
+        packet.set_hardware_type(Hardware::Ethernet);
+        packet.set_protocol_type(EthernetProtocol::Ipv4);
+        packet.set_hardware_len(6);
+        packet.set_protocol_len(4);
+        packet.set_operation(Operation::Request);
+        packet.set_source_hardware_addr("00-15-5d-b7-9f-a9".parse().unwrap());
+        packet.set_source_protocol_addr(Ipv4Address::new(192, 168, 1, 2));
+        packet.set_target_hardware_addr(Eui48::default());
+        packet.set_target_protocol_addr(Ipv4Address::new(192, 168, 1, 1));
+        assert_eq!(buf, REQUEST);
+    }
+
+    #[test]
+    fn arp_buffer_write() {
+        let mac = "00-15-5d-b7-9f-a9".parse().unwrap();
+        for chunk_len in 1..=28 {
+            let mut writer = Writer::request(mac, Ipv4Address::new(192, 168, 1, 2), Ipv4Address::new(192, 168, 1, 1));
+            let mut out = Vec::new();
+            let mut chunk = vec![0u8; chunk_len];
+            loop {
+                let len = writer.write(&mut chunk);
+                if len == 0 {
+                    break
+                }
+                out.extend_from_slice(&chunk[..len]);
+            }
+            assert_eq!(out, REQUEST);
+        }
+    }
+
+    #[test]
+    fn arp_reply_to() {
+        let mac = "00-15-5d-ee-22-75".parse().unwrap();
+        let request = Packet::new(&REQUEST[..]);
+        let mut writer = Writer::reply_to(&request, mac);
+        assert_eq!(writer.dst_addr(), request.source_hardware_addr());
+        let mut buf = [0u8; 28];
+        assert_eq!(writer.write(&mut buf), 28);
+        let reply = Packet::new(&buf[..]);
+        assert_eq!(reply.operation(), Operation::Reply);
+        assert_eq!(reply.source_hardware_addr(), mac);
+        assert_eq!(reply.source_protocol_addr(), Ipv4Address::new(192, 168, 1, 1));
+        assert_eq!(reply.target_protocol_addr(), Ipv4Address::new(192, 168, 1, 2));
+        let gratuitous = Writer::gratuitous(mac, Ipv4Address::new(192, 168, 1, 1));
+        assert_eq!(gratuitous.dst_addr(), Eui48::BROADCAST);
+    }
+}
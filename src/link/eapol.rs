@@ -0,0 +1,498 @@
+// EAPOL: See IEEE 802.1X-2020 Clause 11, EAP: See https://tools.ietf.org/html/rfc3748
+use super::eui::Eui48;
+use super::ether::{Frame as EthernetFrame, Type as EthernetProtocol};
+use byteorder::{ByteOrder, NetworkEndian};
+use core::ops::{Range, RangeFrom};
+
+// port access entity group address
+pub const MULTICAST_ADDR: Eui48 = Eui48::new([0x01, 0x80, 0xc2, 0x00, 0x00, 0x03]);
+
+// EAPOL packet
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<T> {
+    inner: T
+}
+
+impl<T> Packet<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const VERSION: usize = 0;
+    const TYPE: usize = 1;
+    const BODY_LEN: Range<usize> = 2..4;
+    const BODY: RangeFrom<usize> = 4..;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn version(&self) -> u8 {
+        self.inner.as_ref()[Self::VERSION]
+    }
+    pub fn packet_type(&self) -> Type {
+        self.inner.as_ref()[Self::TYPE].into()
+    }
+    pub fn body_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::BODY_LEN])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    // body without Ethernet padding
+    pub fn body(&self) -> &'a [u8] {
+        let body = &self.inner.as_ref()[Self::BODY];
+        &body[..usize::min(self.body_len() as usize, body.len())]
+    }
+}
+
+impl<T: AsMut<[u8]>> Packet<T> {
+    pub fn set_version(&mut self, version: u8) {
+        self.inner.as_mut()[Self::VERSION] = version
+    }
+    pub fn set_packet_type(&mut self, ty: Type) {
+        self.inner.as_mut()[Self::TYPE] = ty.into()
+    }
+    pub fn set_body_len(&mut self, len: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::BODY_LEN], len)
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Packet<&mut T> {
+    pub fn body_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.as_mut()[Self::BODY]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    EapPacket, // 0
+    Start, // 1
+    Logoff, // 2
+    Key, // 3
+    EncapsulatedAsfAlert, // 4
+    Unknown(u8),
+}
+
+impl From<u8> for Type {
+    fn from(src: u8) -> Self {
+        match src {
+            0 => Type::EapPacket,
+            1 => Type::Start,
+            2 => Type::Logoff,
+            3 => Type::Key,
+            4 => Type::EncapsulatedAsfAlert,
+            others => Type::Unknown(others),
+        }
+    }
+}
+
+impl From<Type> for u8 {
+    fn from(src: Type) -> u8 {
+        match src {
+            Type::EapPacket => 0,
+            Type::Start => 1,
+            Type::Logoff => 2,
+            Type::Key => 3,
+            Type::EncapsulatedAsfAlert => 4,
+            Type::Unknown(others) => others
+        }
+    }
+}
+
+// EAP packet carried in EAPOL body
+#[derive(Debug, Clone, Copy)]
+pub struct Eap<T> {
+    inner: T
+}
+
+impl<T> Eap<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const CODE: usize = 0;
+    const IDENTIFIER: usize = 1;
+    const LENGTH: Range<usize> = 2..4;
+    // only present in Request and Response
+    const TYPE: usize = 4;
+    const HEADER_LEN: usize = 4;
+}
+
+impl<T: AsRef<[u8]>> Eap<T> {
+    pub fn code(&self) -> EapCode {
+        self.inner.as_ref()[Self::CODE].into()
+    }
+    pub fn identifier(&self) -> u8 {
+        self.inner.as_ref()[Self::IDENTIFIER]
+    }
+    // length of EAP packet including header
+    pub fn len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::LENGTH])
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn eap_type(&self) -> Option<EapType> {
+        match self.code() {
+            EapCode::Request | EapCode::Response if self.len() as usize > Self::HEADER_LEN =>
+                self.inner.as_ref().get(Self::TYPE).map(|&eap_type| eap_type.into()),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Eap<&'a T> {
+    // data following the type field, e.g. identity string
+    pub fn type_data(&self) -> &'a [u8] {
+        let data = self.inner.as_ref();
+        let end = usize::min(self.len() as usize, data.len());
+        data.get(Self::TYPE + 1..end).unwrap_or(&[])
+    }
+}
+
+impl<T: AsMut<[u8]>> Eap<T> {
+    pub fn set_code(&mut self, code: EapCode) {
+        self.inner.as_mut()[Self::CODE] = code.into()
+    }
+    pub fn set_identifier(&mut self, identifier: u8) {
+        self.inner.as_mut()[Self::IDENTIFIER] = identifier
+    }
+    pub fn set_len(&mut self, len: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::LENGTH], len)
+    }
+    pub fn set_eap_type(&mut self, ty: EapType) {
+        self.inner.as_mut()[Self::TYPE] = ty.into()
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Eap<&mut T> {
+    pub fn type_data_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.as_mut()[Self::TYPE + 1..]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EapCode {
+    Request, // 1
+    Response, // 2
+    Success, // 3
+    Failure, // 4
+    Unknown(u8),
+}
+
+impl From<u8> for EapCode {
+    fn from(src: u8) -> Self {
+        match src {
+            1 => EapCode::Request,
+            2 => EapCode::Response,
+            3 => EapCode::Success,
+            4 => EapCode::Failure,
+            others => EapCode::Unknown(others),
+        }
+    }
+}
+
+impl From<EapCode> for u8 {
+    fn from(src: EapCode) -> u8 {
+        match src {
+            EapCode::Request => 1,
+            EapCode::Response => 2,
+            EapCode::Success => 3,
+            EapCode::Failure => 4,
+            EapCode::Unknown(others) => others
+        }
+    }
+}
+
+// See https://www.iana.org/assignments/eap-numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EapType {
+    Identity, // 1
+    Notification, // 2
+    Nak, // 3
+    Md5Challenge, // 4
+    Tls, // 13
+    Ttls, // 21
+    Peap, // 25
+    MsChapV2, // 26
+    Unknown(u8),
+}
+
+impl From<u8> for EapType {
+    fn from(src: u8) -> Self {
+        match src {
+            1 => EapType::Identity,
+            2 => EapType::Notification,
+            3 => EapType::Nak,
+            4 => EapType::Md5Challenge,
+            13 => EapType::Tls,
+            21 => EapType::Ttls,
+            25 => EapType::Peap,
+            26 => EapType::MsChapV2,
+            others => EapType::Unknown(others),
+        }
+    }
+}
+
+impl From<EapType> for u8 {
+    fn from(src: EapType) -> u8 {
+        match src {
+            EapType::Identity => 1,
+            EapType::Notification => 2,
+            EapType::Nak => 3,
+            EapType::Md5Challenge => 4,
+            EapType::Tls => 13,
+            EapType::Ttls => 21,
+            EapType::Peap => 25,
+            EapType::MsChapV2 => 26,
+            EapType::Unknown(others) => others
+        }
+    }
+}
+
+// EAPOL-Key descriptor carried in EAPOL body, IEEE 802.11-2020 Section 12.7.2
+#[derive(Debug, Clone, Copy)]
+pub struct Key<T> {
+    inner: T
+}
+
+impl<T> Key<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const DESCRIPTOR_TYPE: usize = 0;
+    const KEY_INFORMATION: Range<usize> = 1..3;
+    const KEY_LENGTH: Range<usize> = 3..5;
+    const REPLAY_COUNTER: Range<usize> = 5..13;
+    const NONCE: Range<usize> = 13..45;
+    const IV: Range<usize> = 45..61;
+    const RSC: Range<usize> = 61..69;
+    const MIC: Range<usize> = 77..93;
+    const KEY_DATA_LEN: Range<usize> = 93..95;
+    const KEY_DATA: RangeFrom<usize> = 95..;
+}
+
+impl<T: AsRef<[u8]>> Key<T> {
+    pub fn descriptor_type(&self) -> KeyDescriptor {
+        self.inner.as_ref()[Self::DESCRIPTOR_TYPE].into()
+    }
+    pub fn key_information(&self) -> KeyInformation {
+        KeyInformation(NetworkEndian::read_u16(&self.inner.as_ref()[Self::KEY_INFORMATION]))
+    }
+    pub fn key_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::KEY_LENGTH])
+    }
+    pub fn replay_counter(&self) -> u64 {
+        NetworkEndian::read_u64(&self.inner.as_ref()[Self::REPLAY_COUNTER])
+    }
+    pub fn key_data_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::KEY_DATA_LEN])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Key<&'a T> {
+    pub fn nonce(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::NONCE]
+    }
+    pub fn iv(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::IV]
+    }
+    pub fn rsc(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::RSC]
+    }
+    // assumes 16-byte MIC of the HMAC-SHA1 and AES-CMAC key descriptor versions
+    pub fn mic(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::MIC]
+    }
+    pub fn key_data(&self) -> &'a [u8] {
+        let data = &self.inner.as_ref()[Self::KEY_DATA];
+        &data[..usize::min(self.key_data_len() as usize, data.len())]
+    }
+}
+
+impl<T: AsMut<[u8]>> Key<T> {
+    pub fn set_descriptor_type(&mut self, ty: KeyDescriptor) {
+        self.inner.as_mut()[Self::DESCRIPTOR_TYPE] = ty.into()
+    }
+    pub fn set_key_information(&mut self, info: KeyInformation) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::KEY_INFORMATION], info.0)
+    }
+    pub fn set_key_len(&mut self, len: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::KEY_LENGTH], len)
+    }
+    pub fn set_replay_counter(&mut self, counter: u64) {
+        NetworkEndian::write_u64(&mut self.inner.as_mut()[Self::REPLAY_COUNTER], counter)
+    }
+    pub fn set_nonce(&mut self, nonce: &[u8; 32]) {
+        self.inner.as_mut()[Self::NONCE].copy_from_slice(nonce)
+    }
+    pub fn set_mic(&mut self, mic: &[u8; 16]) {
+        self.inner.as_mut()[Self::MIC].copy_from_slice(mic)
+    }
+    pub fn set_key_data_len(&mut self, len: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::KEY_DATA_LEN], len)
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Key<&mut T> {
+    pub fn key_data_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.as_mut()[Self::KEY_DATA]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyDescriptor {
+    Rc4, // 1
+    Ieee80211, // 2, RSN
+    Wpa, // 254
+    Unknown(u8),
+}
+
+impl From<u8> for KeyDescriptor {
+    fn from(src: u8) -> Self {
+        match src {
+            1 => KeyDescriptor::Rc4,
+            2 => KeyDescriptor::Ieee80211,
+            254 => KeyDescriptor::Wpa,
+            others => KeyDescriptor::Unknown(others),
+        }
+    }
+}
+
+impl From<KeyDescriptor> for u8 {
+    fn from(src: KeyDescriptor) -> u8 {
+        match src {
+            KeyDescriptor::Rc4 => 1,
+            KeyDescriptor::Ieee80211 => 2,
+            KeyDescriptor::Wpa => 254,
+            KeyDescriptor::Unknown(others) => others
+        }
+    }
+}
+
+// key information field bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct KeyInformation(pub u16);
+
+impl KeyInformation {
+    pub const PAIRWISE: u16 = 1 << 3;
+    pub const INSTALL: u16 = 1 << 6;
+    pub const ACK: u16 = 1 << 7;
+    pub const MIC: u16 = 1 << 8;
+    pub const SECURE: u16 = 1 << 9;
+    pub const ERROR: u16 = 1 << 10;
+    pub const REQUEST: u16 = 1 << 11;
+    pub const ENCRYPTED_KEY_DATA: u16 = 1 << 12;
+
+    // 1: HMAC-MD5/RC4, 2: HMAC-SHA1/AES, 3: AES-CMAC/AES
+    pub fn descriptor_version(self) -> u8 {
+        (self.0 & 0x07) as u8
+    }
+    pub fn pairwise(self) -> bool {
+        self.0 & Self::PAIRWISE != 0
+    }
+    pub fn install(self) -> bool {
+        self.0 & Self::INSTALL != 0
+    }
+    pub fn ack(self) -> bool {
+        self.0 & Self::ACK != 0
+    }
+    pub fn mic(self) -> bool {
+        self.0 & Self::MIC != 0
+    }
+    pub fn secure(self) -> bool {
+        self.0 & Self::SECURE != 0
+    }
+    pub fn error(self) -> bool {
+        self.0 & Self::ERROR != 0
+    }
+    pub fn request(self) -> bool {
+        self.0 & Self::REQUEST != 0
+    }
+    pub fn encrypted_key_data(self) -> bool {
+        self.0 & Self::ENCRYPTED_KEY_DATA != 0
+    }
+}
+
+// EAPOL packet of a sniffed Ethernet frame
+pub fn parse_frame(frame: &[u8]) -> Option<Packet<&[u8]>> {
+    if frame.len() < 14 + 4 {
+        return None
+    }
+    let frame = EthernetFrame::new(frame);
+    if frame.ethertype() != EthernetProtocol::Eapol {
+        return None
+    }
+    Some(Packet::new(frame.payload()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eapol_identity_request() {
+        let mut buf = [0u8; 14 + 4 + 5 + 4];
+        let mut frame = EthernetFrame::new(&mut buf);
+        frame.set_dst_addr(MULTICAST_ADDR);
+        frame.set_ethertype(EthernetProtocol::Eapol);
+        let mut packet = Packet::new(frame.payload_mut());
+        packet.set_version(2);
+        packet.set_packet_type(Type::EapPacket);
+        packet.set_body_len(9);
+        let mut eap = Eap::new(packet.body_mut());
+        eap.set_code(EapCode::Response);
+        eap.set_identifier(7);
+        eap.set_len(9);
+        eap.set_eap_type(EapType::Identity);
+        eap.type_data_mut()[..4].copy_from_slice(b"user");
+
+        let packet = parse_frame(&buf).unwrap();
+        assert_eq!(packet.version(), 2);
+        assert_eq!(packet.packet_type(), Type::EapPacket);
+        let eap = Eap::new(packet.body());
+        assert_eq!(eap.code(), EapCode::Response);
+        assert_eq!(eap.identifier(), 7);
+        assert_eq!(eap.eap_type(), Some(EapType::Identity));
+        assert_eq!(eap.type_data(), b"user");
+        // length field claims more than the buffer holds
+        let truncated = Eap::new(&packet.body()[..4]);
+        assert_eq!(truncated.eap_type(), None);
+        assert_eq!(truncated.type_data(), b"");
+    }
+
+    #[test]
+    fn eapol_key() {
+        let mut buf = [0u8; 4 + 95 + 2];
+        let mut packet = Packet::new(&mut buf[..]);
+        packet.set_version(2);
+        packet.set_packet_type(Type::Key);
+        packet.set_body_len(97);
+        let mut key = Key::new(packet.body_mut());
+        key.set_descriptor_type(KeyDescriptor::Ieee80211);
+        key.set_key_information(KeyInformation(2 | KeyInformation::PAIRWISE | KeyInformation::ACK));
+        key.set_key_len(16);
+        key.set_replay_counter(1);
+        key.set_nonce(&[0xaa; 32]);
+        key.set_key_data_len(2);
+        key.key_data_mut()[..2].copy_from_slice(&[0xdd, 0x00]);
+
+        let packet = Packet::new(&buf[..]);
+        let key = Key::new(packet.body());
+        assert_eq!(key.descriptor_type(), KeyDescriptor::Ieee80211);
+        let info = key.key_information();
+        assert_eq!(info.descriptor_version(), 2);
+        assert!(info.pairwise() && info.ack() && !info.mic() && !info.install());
+        assert_eq!(key.key_len(), 16);
+        assert_eq!(key.replay_counter(), 1);
+        assert_eq!(key.nonce(), &[0xaa; 32][..]);
+        assert_eq!(key.mic(), &[0; 16][..]);
+        assert_eq!(key.key_data(), &[0xdd, 0x00]);
+    }
+}
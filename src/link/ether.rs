@@ -1,6 +1,7 @@
 use super::eui::Eui48 as Address;
 use byteorder::{ByteOrder, NetworkEndian};
 use core::ops::{Range, RangeFrom};
+use crate::packet_write::{PacketWrite, PacketWriter};
 
 pub struct Frame<T> {
     inner: T
@@ -25,11 +26,27 @@ impl<T: AsRef<[u8]>> Frame<T> {
     pub fn src_addr(&self) -> Address {
         Address::from_bytes(&self.inner.as_ref()[Self::SRC_ADDR])
     }
-    // does not support IEEE 802.3 frames and 802.1Q fields
+    // raw type/length field: a VLAN TPID for tagged frames (see `vlan_tag`),
+    // a length for IEEE 802.3 frames (see `llc_len`)
     pub fn ethertype(&self) -> Type {
         let ty = NetworkEndian::read_u16(&self.inner.as_ref()[Self::ETHERTYPE]);
         Type::from(ty)
     }
+    // outermost 802.1Q or 802.1ad tag, if any; None if the frame ends before the TCI
+    pub fn vlan_tag(&self) -> Option<VlanTag> {
+        match self.ethertype() {
+            tpid @ (Type::Vlan | Type::ServiceVlan) => {
+                let tci = NetworkEndian::read_u16(self.inner.as_ref()[Self::PAYLOAD].get(0..2)?);
+                Some(VlanTag::from_tci(tpid, tci))
+            },
+            _ => None,
+        }
+    }
+    // IEEE 802.3 frames with LLC header carry payload length in ethertype field
+    pub fn llc_len(&self) -> Option<usize> {
+        let len = NetworkEndian::read_u16(&self.inner.as_ref()[Self::ETHERTYPE]);
+        if len <= 1500 { Some(len as usize) } else { None }
+    }
     pub fn into_inner(self) -> T {
         self.inner
     }
@@ -53,22 +70,46 @@ impl<T: AsMut<[u8]>> Frame<T> {
     }
 }
 
-impl<'a, T: AsMut<[u8]> + ?Sized> Frame<&'a mut T> {
+impl<T: AsMut<[u8]> + ?Sized> Frame<&mut T> {
     pub fn payload_mut(&mut self) -> &mut [u8] {
         &mut self.inner.as_mut()[Self::PAYLOAD]
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Type {
+    Ipv4,
+    Arp,
+    Vlan,
+    ServiceVlan,
+    WakeOnLan,
+    Rarp,
     Ipv6,
+    Mpls,
+    MplsMulticast,
+    PppoeDiscovery,
+    PppoeSession,
+    Eapol,
+    Lldp,
     Unknown(u16),
 }
 
 impl From<u16> for Type {
     fn from(src: u16) -> Self {
         match src {
+            0x0800 => Type::Ipv4,
+            0x0806 => Type::Arp,
+            0x0842 => Type::WakeOnLan,
+            0x8100 => Type::Vlan,
+            0x8035 => Type::Rarp,
             0x86DD => Type::Ipv6,
+            0x8847 => Type::Mpls,
+            0x8848 => Type::MplsMulticast,
+            0x8863 => Type::PppoeDiscovery,
+            0x8864 => Type::PppoeSession,
+            0x888E => Type::Eapol,
+            0x88A8 => Type::ServiceVlan,
+            0x88CC => Type::Lldp,
             others => Type::Unknown(others),
         }
     }
@@ -77,8 +118,258 @@ impl From<u16> for Type {
 impl From<Type> for u16 {
     fn from(src: Type) -> u16 {
         match src {
+            Type::Ipv4 => 0x0800,
+            Type::Arp => 0x0806,
+            Type::WakeOnLan => 0x0842,
+            Type::Vlan => 0x8100,
+            Type::Rarp => 0x8035,
             Type::Ipv6 => 0x86DD,
+            Type::Mpls => 0x8847,
+            Type::MplsMulticast => 0x8848,
+            Type::PppoeDiscovery => 0x8863,
+            Type::PppoeSession => 0x8864,
+            Type::Eapol => 0x888E,
+            Type::ServiceVlan => 0x88A8,
+            Type::Lldp => 0x88CC,
             Type::Unknown(others) => others
         }
     }
 }
+
+// 802.1Q tag: 3-bit priority code point, drop eligible indicator and 12-bit VLAN ID
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VlanTag {
+    pub tpid: Type,
+    pub priority: u8,
+    pub drop_eligible: bool,
+    pub vlan_id: u16,
+}
+
+impl VlanTag {
+    // customer VLAN tag
+    pub fn new(vlan_id: u16) -> Self {
+        debug_assert!(vlan_id <= 0x0fff);
+        Self { tpid: Type::Vlan, priority: 0, drop_eligible: false, vlan_id }
+    }
+    // service VLAN tag, outer tag of 802.1ad (QinQ) frames
+    pub fn service(vlan_id: u16) -> Self {
+        Self { tpid: Type::ServiceVlan, ..Self::new(vlan_id) }
+    }
+    pub fn with_priority(self, priority: u8) -> Self {
+        debug_assert!(priority <= 7);
+        Self { priority, ..self }
+    }
+    pub fn tci(self) -> u16 {
+        ((self.priority as u16 & 0x07) << 13) | ((self.drop_eligible as u16) << 12) | (self.vlan_id & 0x0fff)
+    }
+    pub fn from_tci(tpid: Type, tci: u16) -> Self {
+        Self { tpid, priority: (tci >> 13) as u8, drop_eligible: tci & 0x1000 != 0, vlan_id: tci & 0x0fff }
+    }
+}
+
+// Ethernet frame writer around a resumable payload writer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Writer<W> {
+    dst_addr: Address,
+    src_addr: Address,
+    vlan_tags: Vec<VlanTag>,
+    ethertype: Type,
+    payload: W,
+    padding: bool,
+    fcs: bool,
+    crc: u32,
+    byte_idx: usize,
+}
+
+impl<W> Writer<W> {
+    // minimum untagged frame length without FCS; each VLAN tag adds 4 bytes
+    pub const MIN_LEN: usize = 60;
+
+    // frames are padded to minimum length and carry no FCS by default,
+    // as expected by raw sockets
+    pub fn new(dst_addr: Address, src_addr: Address, ethertype: Type, payload: W) -> Writer<W> {
+        Writer {
+            dst_addr, src_addr,
+            vlan_tags: Vec::new(),
+            ethertype,
+            payload,
+            padding: true,
+            fcs: false,
+            crc: CRC32_INIT,
+            byte_idx: 0,
+        }
+    }
+    // tags are written in order, outermost first
+    pub fn vlan_tag(mut self, tag: VlanTag) -> Self {
+        self.vlan_tags.push(tag);
+        self
+    }
+    pub fn padding(self, padding: bool) -> Self {
+        Self { padding, ..self }
+    }
+    // append CRC-32 frame check sequence
+    pub fn fcs(self, fcs: bool) -> Self {
+        Self { fcs, ..self }
+    }
+    pub fn into_payload(self) -> W {
+        self.payload
+    }
+    fn header_len(&self) -> usize {
+        14 + 4 * self.vlan_tags.len()
+    }
+}
+
+impl<W: PacketWriter> Writer<W> {
+    // frame length without FCS
+    fn data_len(&self) -> usize {
+        let len = self.header_len() + self.payload.packet_len();
+        if self.padding { usize::max(len, Self::MIN_LEN + 4 * self.vlan_tags.len()) } else { len }
+    }
+    pub fn len(&self) -> usize {
+        self.data_len() + if self.fcs { 4 } else { 0 }
+    }
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+    #[must_use]
+    pub fn write(&mut self, buffer: &mut [u8]) -> usize {
+        let header_len = self.header_len();
+        let mut write = PacketWrite::new(&mut *buffer, self.byte_idx);
+        write.write_slice_at(0.., self.dst_addr.as_bytes());
+        write.write_slice_at(6.., self.src_addr.as_bytes());
+        for (idx, tag) in self.vlan_tags.iter().enumerate() {
+            let start = 12 + 4 * idx;
+            write.write_u16_at(start..start + 2, tag.tpid.into());
+            write.write_u16_at(start + 2..start + 4, tag.tci());
+        }
+        write.write_u16_at(header_len - 2..header_len, self.ethertype.into());
+        self.byte_idx = write.buffer_index();
+        let mut cur_idx = write.bytes_written();
+        let payload_end = header_len + self.payload.packet_len();
+        if (header_len..payload_end).contains(&self.byte_idx) {
+            let write_len = self.payload.write_packet(&mut buffer[cur_idx..]);
+            self.byte_idx += write_len;
+            cur_idx += write_len;
+        }
+        let data_len = self.data_len();
+        if (payload_end..data_len).contains(&self.byte_idx) {
+            let write_len = usize::min(data_len - self.byte_idx, buffer.len() - cur_idx);
+            for byte in &mut buffer[cur_idx..cur_idx + write_len] {
+                *byte = 0;
+            }
+            self.byte_idx += write_len;
+            cur_idx += write_len;
+        }
+        if !self.fcs {
+            return cur_idx
+        }
+        // everything written so far in this call is covered by FCS
+        self.crc = crc32_update(self.crc, &buffer[..cur_idx]);
+        let fcs = (!self.crc).to_le_bytes();
+        let mut write = PacketWrite::new(&mut buffer[cur_idx..], self.byte_idx);
+        write.write_slice_at(data_len.., &fcs);
+        self.byte_idx = write.buffer_index();
+        cur_idx + write.bytes_written()
+    }
+}
+
+impl<W: PacketWriter> PacketWriter for Writer<W> {
+    fn packet_len(&self) -> usize {
+        self.len()
+    }
+    fn write_packet(&mut self, buffer: &mut [u8]) -> usize {
+        self.write(buffer)
+    }
+}
+
+const CRC32_INIT: u32 = 0xffff_ffff;
+
+// reflected CRC-32 of IEEE 802.3, polynomial 0x04C11DB7
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+};
+
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::IcmpWriter;
+
+    fn write_all<W: PacketWriter>(mut writer: W, chunk_len: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut chunk = vec![0xffu8; chunk_len];
+        loop {
+            let len = writer.write_packet(&mut chunk);
+            if len == 0 {
+                break
+            }
+            out.extend_from_slice(&chunk[..len]);
+        }
+        out
+    }
+
+    #[test]
+    fn crc32_check() {
+        assert_eq!(!crc32_update(CRC32_INIT, b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn ether_writer_padding() {
+        let dst = "00-15-5d-ee-22-75".parse().unwrap();
+        let src = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let icmp = IcmpWriter::echo_request(1, 2, [0xaau8; 4]);
+        let writer = Writer::new(dst, src, Type::Ipv6, icmp);
+        assert_eq!(writer.len(), 60);
+        let frame = write_all(writer.clone(), 1500);
+        assert_eq!(frame.len(), 60);
+        assert_eq!(&frame[26..], &[0u8; 34][..]);
+        let parsed = Frame::new(&frame[..]);
+        assert_eq!(parsed.dst_addr(), dst);
+        assert_eq!(parsed.ethertype(), Type::Ipv6);
+        assert_eq!(write_all(writer.clone().padding(false), 1500).len(), 26);
+        // tags do not count towards the minimum
+        let tagged = writer.clone().vlan_tag(VlanTag::new(10)).vlan_tag(VlanTag::new(20));
+        assert_eq!(write_all(tagged, 1500).len(), 68);
+        for chunk_len in 1..=61 {
+            assert_eq!(write_all(writer.clone(), chunk_len), frame);
+        }
+    }
+
+    #[test]
+    fn ether_writer_vlan_fcs() {
+        let dst = "00-15-5d-ee-22-75".parse().unwrap();
+        let src = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let icmp = IcmpWriter::echo_request(1, 2, [0xaau8; 64]);
+        let writer = Writer::new(dst, src, Type::Ipv6, icmp)
+            .vlan_tag(VlanTag::service(100))
+            .vlan_tag(VlanTag::new(200).with_priority(5))
+            .fcs(true);
+        assert_eq!(writer.len(), 22 + 72 + 4);
+        let frame = write_all(writer.clone(), 1500);
+        assert_eq!(&frame[12..22], &[0x88, 0xa8, 0x00, 100, 0x81, 0x00, 0xa0, 200, 0x86, 0xdd]);
+        assert_eq!(Frame::new(&frame[..]).vlan_tag().map(|tag| tag.vlan_id), Some(100));
+        assert_eq!(Frame::new(&frame[..15]).vlan_tag(), None);
+        // CRC over data followed by FCS leaves the magic residue
+        assert_eq!(crc32_update(CRC32_INIT, &frame), 0xdebb_20e3);
+        for chunk_len in 1..=99 {
+            assert_eq!(write_all(writer.clone(), chunk_len), frame);
+        }
+    }
+}
@@ -1,16 +1,41 @@
 //! EUI-48 address format
 use core::str::FromStr;
 use core::fmt;
+use std::io;
+use crate::net::IpAddress;
+use crate::raw_socket_sys::getrandom;
 
 // is also called mac address
 /// EUI-48 Address
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub struct Eui48([u8; 6]);
 
 impl Eui48 {
-    pub fn new(bytes: [u8; 6]) -> Self {
+    pub const BROADCAST: Self = Eui48([0xff; 6]);
+    pub const fn new(bytes: [u8; 6]) -> Self {
+        Self(bytes)
+    }
+    // set the locally administered bit and clear the multicast bit
+    pub fn new_local(bytes: [u8; 6]) -> Self {
+        let mut bytes = bytes;
+        bytes[0] = (bytes[0] & !0x01) | 0x02;
         Self(bytes)
     }
+    // random locally administered unicast address, e.g. for virtual devices
+    pub fn random_local() -> io::Result<Self> {
+        let mut bytes = [0u8; 6];
+        getrandom(&mut bytes)?;
+        Ok(Self::new_local(bytes))
+    }
+    // 33-33-xx-xx-xx-xx, where xx are low 32 bits of the IPv6 multicast address
+    // Section 7, https://datatracker.ietf.org/doc/html/rfc2464
+    pub fn from_ipv6_multicast(address: IpAddress) -> Option<Self> {
+        if !address.is_multicast() {
+            return None
+        }
+        let octets = address.octets();
+        Some(Self([0x33, 0x33, octets[12], octets[13], octets[14], octets[15]]))
+    }
     pub fn from_bytes(src: &[u8]) -> Self {
         let mut bytes = [0; 6];
         bytes.copy_from_slice(src);
@@ -19,6 +44,42 @@ impl Eui48 {
     pub fn to_bytes(self) -> [u8; 6] {
         self.0
     }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+    // I/G bit is set; broadcast address is also a multicast address
+    pub fn is_multicast(self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+    pub fn is_unicast(self) -> bool {
+        !self.is_multicast()
+    }
+    pub fn is_broadcast(self) -> bool {
+        self == Self::BROADCAST
+    }
+    // U/L bit is cleared, address is assigned by IEEE OUI owner
+    pub fn is_universal(self) -> bool {
+        !self.is_local()
+    }
+    pub fn is_local(self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+    // display with given separator, uppercase by default
+    pub fn display(self, separator: Separator) -> Eui48Display {
+        Eui48Display { address: self, separator, uppercase: true }
+    }
+}
+
+impl From<[u8; 6]> for Eui48 {
+    #[inline] fn from(bytes: [u8; 6]) -> Eui48 {
+        Eui48::new(bytes)
+    }
+}
+
+impl From<Eui48> for [u8; 6] {
+    #[inline] fn from(src: Eui48) -> [u8; 6] {
+        src.to_bytes()
+    }
 }
 
 // allowed format: 00-11-22-33-44-55 or 00:11:22:33:44:55
@@ -72,13 +133,59 @@ impl fmt::Display for Eui48 {
     }
 }
 
+/// Separator used when displaying EUI-48 address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Separator {
+    Colon, // 00:11:22:33:44:55
+    Dash, // 00-11-22-33-44-55
+    Dot, // 0011.2233.4455
+}
+
+/// Configurable display form of EUI-48 address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Eui48Display {
+    address: Eui48,
+    separator: Separator,
+    uppercase: bool,
+}
+
+impl Eui48Display {
+    pub fn uppercase(self) -> Self {
+        Self { uppercase: true, ..self }
+    }
+    pub fn lowercase(self) -> Self {
+        Self { uppercase: false, ..self }
+    }
+}
+
+impl fmt::Display for Eui48Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, byte) in self.address.0.iter().enumerate() {
+            match (self.separator, idx) {
+                (_, 0) => {},
+                (Separator::Colon, _) => write!(f, ":")?,
+                (Separator::Dash, _) => write!(f, "-")?,
+                (Separator::Dot, 2 | 4) => write!(f, ".")?,
+                (Separator::Dot, _) => {},
+            }
+            if self.uppercase {
+                write!(f, "{:02X}", byte)?;
+            } else {
+                write!(f, "{:02x}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// EUI-48 address parse error
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Eui48ParseError(());
 
 #[cfg(test)]
 mod test {
-    use super::Eui48;
+    use super::{Eui48, Separator};
+    use crate::net::IpAddress;
     #[test]
     fn eui48_parse() {
         assert_eq!("00-01-02-03-04-05".parse(), Ok(Eui48([0, 1, 2, 3, 4, 5])));
@@ -90,4 +197,37 @@ mod test {
         assert!("1a-b2-3G-D4-50-06".parse::<Eui48>().is_err());
         assert!("1a-b2-3G-D4-50-6".parse::<Eui48>().is_err());
     }
+    #[test]
+    fn eui48_classify() {
+        let unicast = Eui48([0x00, 0x15, 0x5d, 0xb7, 0x9f, 0xa9]);
+        assert!(unicast.is_unicast() && unicast.is_universal());
+        assert!(!unicast.is_multicast() && !unicast.is_local() && !unicast.is_broadcast());
+        let multicast = Eui48([0x33, 0x33, 0, 0, 0, 1]);
+        assert!(multicast.is_multicast() && multicast.is_local() && !multicast.is_broadcast());
+        assert!(Eui48::BROADCAST.is_broadcast() && Eui48::BROADCAST.is_multicast());
+        let local = Eui48::new_local([0xff, 1, 2, 3, 4, 5]);
+        assert_eq!(local, Eui48([0xfe, 1, 2, 3, 4, 5]));
+        assert!(local.is_unicast() && local.is_local());
+        let random = Eui48::random_local().unwrap();
+        assert!(random.is_unicast() && random.is_local());
+    }
+    #[test]
+    fn eui48_ipv6_multicast() {
+        let all_nodes: IpAddress = "ff02::1".parse().unwrap();
+        assert_eq!(Eui48::from_ipv6_multicast(all_nodes), Some(Eui48([0x33, 0x33, 0, 0, 0, 1])));
+        let solicited: IpAddress = "ff02::1:ffb7:9fa9".parse().unwrap();
+        assert_eq!(Eui48::from_ipv6_multicast(solicited), Some(Eui48([0x33, 0x33, 0xff, 0xb7, 0x9f, 0xa9])));
+        let link_local: IpAddress = "fe80::1".parse().unwrap();
+        assert_eq!(Eui48::from_ipv6_multicast(link_local), None);
+    }
+    #[test]
+    fn eui48_display() {
+        let addr = Eui48([0x00, 0x15, 0x5d, 0xb7, 0x9f, 0xa9]);
+        assert_eq!("00-15-5D-B7-9F-A9", addr.to_string());
+        assert_eq!("00:15:5D:B7:9F:A9", addr.display(Separator::Colon).to_string());
+        assert_eq!("00:15:5d:b7:9f:a9", addr.display(Separator::Colon).lowercase().to_string());
+        assert_eq!("00-15-5d-b7-9f-a9", addr.display(Separator::Dash).lowercase().to_string());
+        assert_eq!("0015.5DB7.9FA9", addr.display(Separator::Dot).to_string());
+        assert_eq!("0015.5db7.9fa9", addr.display(Separator::Dot).lowercase().to_string());
+    }
 }
@@ -0,0 +1,63 @@
+// IEEE 802.2 LLC header carried by 802.3 frames, where the ethertype field
+// holds payload length instead of protocol
+use core::ops::RangeFrom;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<T> {
+    inner: T
+}
+
+impl<T> Packet<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const DSAP: usize = 0;
+    const SSAP: usize = 1;
+    const CONTROL: usize = 2;
+    // only unnumbered (U-format) frames with one byte control field are supported
+    const PAYLOAD: RangeFrom<usize> = 3..;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn dsap(&self) -> u8 {
+        self.inner.as_ref()[Self::DSAP]
+    }
+    pub fn ssap(&self) -> u8 {
+        self.inner.as_ref()[Self::SSAP]
+    }
+    pub fn control(&self) -> u8 {
+        self.inner.as_ref()[Self::CONTROL]
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    pub fn payload(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::PAYLOAD]
+    }
+}
+
+impl<T: AsMut<[u8]>> Packet<T> {
+    pub fn set_dsap(&mut self, dsap: u8) {
+        self.inner.as_mut()[Self::DSAP] = dsap
+    }
+    pub fn set_ssap(&mut self, ssap: u8) {
+        self.inner.as_mut()[Self::SSAP] = ssap
+    }
+    pub fn set_control(&mut self, control: u8) {
+        self.inner.as_mut()[Self::CONTROL] = control
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Packet<&mut T> {
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.as_mut()[Self::PAYLOAD]
+    }
+}
+
+// well-known service access points
+pub const SAP_STP: u8 = 0x42;
+// unnumbered information
+pub const CONTROL_UI: u8 = 0x03;
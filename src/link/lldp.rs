@@ -0,0 +1,595 @@
+// LLDP: See IEEE 802.1AB-2016
+use super::eui::Eui48;
+use super::ether::{Frame as EthernetFrame, Type as EthernetProtocol};
+use byteorder::{ByteOrder, NetworkEndian};
+
+// nearest bridge group address, LLDPDUs are not forwarded by bridges
+pub const MULTICAST_ADDR: Eui48 = Eui48::new([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]);
+
+// 802.1 and 802.3 organizationally specific TLVs
+pub const OUI_IEEE_8021: [u8; 3] = [0x00, 0x80, 0xc2];
+pub const OUI_IEEE_8023: [u8; 3] = [0x00, 0x12, 0x0f];
+
+// system capabilities bits, Section 8.5.8
+pub mod capabilities {
+    pub const OTHER: u16 = 1 << 0;
+    pub const REPEATER: u16 = 1 << 1;
+    pub const MAC_BRIDGE: u16 = 1 << 2;
+    pub const WLAN_ACCESS_POINT: u16 = 1 << 3;
+    pub const ROUTER: u16 = 1 << 4;
+    pub const TELEPHONE: u16 = 1 << 5;
+    pub const DOCSIS_CABLE_DEVICE: u16 = 1 << 6;
+    pub const STATION_ONLY: u16 = 1 << 7;
+    pub const C_VLAN: u16 = 1 << 8;
+    pub const S_VLAN: u16 = 1 << 9;
+    pub const TWO_PORT_MAC_RELAY: u16 = 1 << 10;
+}
+
+// raw TLV: 7-bit type, 9-bit length, then value
+#[derive(Debug, Clone, Copy)]
+pub struct Tlv<T> {
+    inner: T
+}
+
+impl<T> Tlv<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const HEADER_LEN: usize = 2;
+    pub const MAX_VALUE_LEN: usize = 0x01ff;
+}
+
+impl<T: AsRef<[u8]>> Tlv<T> {
+    pub fn tlv_type(&self) -> TlvType {
+        (self.inner.as_ref()[0] >> 1).into()
+    }
+    pub fn value_len(&self) -> usize {
+        (NetworkEndian::read_u16(&self.inner.as_ref()[0..2]) & 0x01ff) as usize
+    }
+    pub fn total_len(&self) -> usize {
+        Self::HEADER_LEN + self.value_len()
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Tlv<&'a T> {
+    pub fn value(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::HEADER_LEN..Self::HEADER_LEN + self.value_len()]
+    }
+}
+
+impl<T: AsMut<[u8]>> Tlv<T> {
+    // value length is a 9-bit field
+    pub fn set_header(&mut self, tlv_type: TlvType, value_len: usize) {
+        assert!(value_len <= Self::MAX_VALUE_LEN, "TLV value of {} bytes too long", value_len);
+        let header = ((u8::from(tlv_type) as u16) << 9) | value_len as u16;
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[0..2], header)
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Tlv<&mut T> {
+    pub fn value_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.as_mut()[Self::HEADER_LEN..]
+    }
+}
+
+// iterates TLVs of an LLDPDU, stops after End of LLDPDU TLV or on truncated data
+#[derive(Debug, Clone)]
+pub struct TlvIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TlvIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = Tlv<&'a [u8]>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < Tlv::<()>::HEADER_LEN {
+            return None
+        }
+        let tlv = Tlv::new(self.data);
+        let total_len = tlv.total_len();
+        if total_len > self.data.len() {
+            self.data = &[];
+            return None
+        }
+        let tlv = Tlv::new(&self.data[..total_len]);
+        self.data = if tlv.tlv_type() == TlvType::End { &[] } else { &self.data[total_len..] };
+        Some(tlv)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlvType {
+    End, // 0
+    ChassisId, // 1
+    PortId, // 2
+    Ttl, // 3
+    PortDescription, // 4
+    SystemName, // 5
+    SystemDescription, // 6
+    SystemCapabilities, // 7
+    ManagementAddress, // 8
+    OrganizationallySpecific, // 127
+    Unknown(u8),
+}
+
+impl From<u8> for TlvType {
+    fn from(src: u8) -> Self {
+        match src {
+            0 => TlvType::End,
+            1 => TlvType::ChassisId,
+            2 => TlvType::PortId,
+            3 => TlvType::Ttl,
+            4 => TlvType::PortDescription,
+            5 => TlvType::SystemName,
+            6 => TlvType::SystemDescription,
+            7 => TlvType::SystemCapabilities,
+            8 => TlvType::ManagementAddress,
+            127 => TlvType::OrganizationallySpecific,
+            others => TlvType::Unknown(others),
+        }
+    }
+}
+
+impl From<TlvType> for u8 {
+    fn from(src: TlvType) -> u8 {
+        match src {
+            TlvType::End => 0,
+            TlvType::ChassisId => 1,
+            TlvType::PortId => 2,
+            TlvType::Ttl => 3,
+            TlvType::PortDescription => 4,
+            TlvType::SystemName => 5,
+            TlvType::SystemDescription => 6,
+            TlvType::SystemCapabilities => 7,
+            TlvType::ManagementAddress => 8,
+            TlvType::OrganizationallySpecific => 127,
+            TlvType::Unknown(others) => others
+        }
+    }
+}
+
+// Chassis ID subtypes, Section 8.5.2.2
+pub mod chassis_id {
+    pub const CHASSIS_COMPONENT: u8 = 1;
+    pub const INTERFACE_ALIAS: u8 = 2;
+    pub const PORT_COMPONENT: u8 = 3;
+    pub const MAC_ADDRESS: u8 = 4;
+    pub const NETWORK_ADDRESS: u8 = 5;
+    pub const INTERFACE_NAME: u8 = 6;
+    pub const LOCALLY_ASSIGNED: u8 = 7;
+}
+
+// Port ID subtypes, Section 8.5.3.2
+pub mod port_id {
+    pub const INTERFACE_ALIAS: u8 = 1;
+    pub const PORT_COMPONENT: u8 = 2;
+    pub const MAC_ADDRESS: u8 = 3;
+    pub const NETWORK_ADDRESS: u8 = 4;
+    pub const INTERFACE_NAME: u8 = 5;
+    pub const AGENT_CIRCUIT_ID: u8 = 6;
+    pub const LOCALLY_ASSIGNED: u8 = 7;
+}
+
+// decoded TLV; string fields are kept as bytes, they are not required to be UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repr<'a> {
+    End,
+    ChassisId { subtype: u8, id: &'a [u8] },
+    PortId { subtype: u8, id: &'a [u8] },
+    Ttl(u16),
+    PortDescription(&'a [u8]),
+    SystemName(&'a [u8]),
+    SystemDescription(&'a [u8]),
+    SystemCapabilities { capabilities: u16, enabled: u16 },
+    ManagementAddress {
+        // address family number from IANA, e.g. 1 for IPv4 and 2 for IPv6
+        address_subtype: u8,
+        address: &'a [u8],
+        interface_subtype: u8,
+        interface_number: u32,
+        oid: &'a [u8],
+    },
+    Ieee8021(Ieee8021<'a>),
+    Ieee8023(Ieee8023<'a>),
+    OrganizationallySpecific { oui: [u8; 3], subtype: u8, info: &'a [u8] },
+    Unknown { tlv_type: u8, value: &'a [u8] },
+}
+
+// IEEE 802.1 organizationally specific TLVs, Annex D
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ieee8021<'a> {
+    PortVlanId(u16),
+    PortAndProtocolVlanId { flags: u8, vlan_id: u16 },
+    VlanName { vlan_id: u16, name: &'a [u8] },
+    ProtocolIdentity(&'a [u8]),
+}
+
+// IEEE 802.3 organizationally specific TLVs, IEEE 802.3 Clause 79
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ieee8023<'a> {
+    MacPhyConfigStatus { autoneg: u8, pmd_capability: u16, mau_type: u16 },
+    // extension holds the IEEE 802.3at and 802.3bt fields following power class, if any
+    PowerViaMdi { support: u8, pse_power_pair: u8, power_class: u8, extension: &'a [u8] },
+    LinkAggregation { status: u8, port_id: u32 },
+    MaxFrameSize(u16),
+}
+
+/// LLDP TLV parse error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseTlvError(());
+
+impl<'a> Repr<'a> {
+    pub fn parse(tlv: &Tlv<&'a [u8]>) -> Result<Repr<'a>, ParseTlvError> {
+        let value = tlv.value();
+        let err = ParseTlvError(());
+        let repr = match tlv.tlv_type() {
+            TlvType::End => Repr::End,
+            TlvType::ChassisId if value.len() >= 2 =>
+                Repr::ChassisId { subtype: value[0], id: &value[1..] },
+            TlvType::PortId if value.len() >= 2 =>
+                Repr::PortId { subtype: value[0], id: &value[1..] },
+            TlvType::Ttl if value.len() == 2 => Repr::Ttl(NetworkEndian::read_u16(value)),
+            TlvType::PortDescription => Repr::PortDescription(value),
+            TlvType::SystemName => Repr::SystemName(value),
+            TlvType::SystemDescription => Repr::SystemDescription(value),
+            TlvType::SystemCapabilities if value.len() == 4 => Repr::SystemCapabilities {
+                capabilities: NetworkEndian::read_u16(&value[0..2]),
+                enabled: NetworkEndian::read_u16(&value[2..4]),
+            },
+            TlvType::ManagementAddress => {
+                // address string length includes the subtype octet
+                let addr_len = *value.first().ok_or(err)? as usize;
+                if addr_len < 2 || value.len() < 1 + addr_len + 6 {
+                    return Err(err)
+                }
+                let rest = &value[1 + addr_len..];
+                let oid_len = rest[5] as usize;
+                if rest.len() < 6 + oid_len {
+                    return Err(err)
+                }
+                Repr::ManagementAddress {
+                    address_subtype: value[1],
+                    address: &value[2..1 + addr_len],
+                    interface_subtype: rest[0],
+                    interface_number: NetworkEndian::read_u32(&rest[1..5]),
+                    oid: &rest[6..6 + oid_len],
+                }
+            },
+            TlvType::OrganizationallySpecific if value.len() >= 4 => {
+                let oui = [value[0], value[1], value[2]];
+                let (subtype, info) = (value[3], &value[4..]);
+                match (oui, subtype) {
+                    (OUI_IEEE_8021, 1) if info.len() == 2 =>
+                        Repr::Ieee8021(Ieee8021::PortVlanId(NetworkEndian::read_u16(info))),
+                    (OUI_IEEE_8021, 2) if info.len() == 3 => Repr::Ieee8021(Ieee8021::PortAndProtocolVlanId {
+                        flags: info[0],
+                        vlan_id: NetworkEndian::read_u16(&info[1..3]),
+                    }),
+                    (OUI_IEEE_8021, 3) if info.len() >= 3 && info.len() == 3 + info[2] as usize =>
+                        Repr::Ieee8021(Ieee8021::VlanName {
+                            vlan_id: NetworkEndian::read_u16(&info[0..2]),
+                            name: &info[3..],
+                        }),
+                    (OUI_IEEE_8021, 4) if !info.is_empty() && info.len() == 1 + info[0] as usize =>
+                        Repr::Ieee8021(Ieee8021::ProtocolIdentity(&info[1..])),
+                    (OUI_IEEE_8023, 1) if info.len() == 5 => Repr::Ieee8023(Ieee8023::MacPhyConfigStatus {
+                        autoneg: info[0],
+                        pmd_capability: NetworkEndian::read_u16(&info[1..3]),
+                        mau_type: NetworkEndian::read_u16(&info[3..5]),
+                    }),
+                    (OUI_IEEE_8023, 2) if info.len() >= 3 => Repr::Ieee8023(Ieee8023::PowerViaMdi {
+                        support: info[0],
+                        pse_power_pair: info[1],
+                        power_class: info[2],
+                        extension: &info[3..],
+                    }),
+                    (OUI_IEEE_8023, 3) if info.len() == 5 => Repr::Ieee8023(Ieee8023::LinkAggregation {
+                        status: info[0],
+                        port_id: NetworkEndian::read_u32(&info[1..5]),
+                    }),
+                    (OUI_IEEE_8023, 4) if info.len() == 2 =>
+                        Repr::Ieee8023(Ieee8023::MaxFrameSize(NetworkEndian::read_u16(info))),
+                    (OUI_IEEE_8021, _) | (OUI_IEEE_8023, _) if subtype <= 4 => return Err(err),
+                    _ => Repr::OrganizationallySpecific { oui, subtype, info },
+                }
+            },
+            TlvType::Unknown(tlv_type) => Repr::Unknown { tlv_type, value },
+            _ => return Err(err),
+        };
+        Ok(repr)
+    }
+    pub fn tlv_type(&self) -> TlvType {
+        match self {
+            Repr::End => TlvType::End,
+            Repr::ChassisId { .. } => TlvType::ChassisId,
+            Repr::PortId { .. } => TlvType::PortId,
+            Repr::Ttl(_) => TlvType::Ttl,
+            Repr::PortDescription(_) => TlvType::PortDescription,
+            Repr::SystemName(_) => TlvType::SystemName,
+            Repr::SystemDescription(_) => TlvType::SystemDescription,
+            Repr::SystemCapabilities { .. } => TlvType::SystemCapabilities,
+            Repr::ManagementAddress { .. } => TlvType::ManagementAddress,
+            Repr::Ieee8021(_) | Repr::Ieee8023(_) | Repr::OrganizationallySpecific { .. } =>
+                TlvType::OrganizationallySpecific,
+            Repr::Unknown { tlv_type, .. } => TlvType::Unknown(*tlv_type),
+        }
+    }
+    pub fn value_len(&self) -> usize {
+        match self {
+            Repr::End => 0,
+            Repr::ChassisId { id, .. } | Repr::PortId { id, .. } => 1 + id.len(),
+            Repr::Ttl(_) => 2,
+            Repr::PortDescription(s) | Repr::SystemName(s) | Repr::SystemDescription(s) => s.len(),
+            Repr::SystemCapabilities { .. } => 4,
+            Repr::ManagementAddress { address, oid, .. } => 2 + address.len() + 6 + oid.len(),
+            Repr::Ieee8021(tlv) => 4 + match tlv {
+                Ieee8021::PortVlanId(_) => 2,
+                Ieee8021::PortAndProtocolVlanId { .. } => 3,
+                Ieee8021::VlanName { name, .. } => 3 + name.len(),
+                Ieee8021::ProtocolIdentity(id) => 1 + id.len(),
+            },
+            Repr::Ieee8023(tlv) => 4 + match tlv {
+                Ieee8023::MacPhyConfigStatus { .. } => 5,
+                Ieee8023::PowerViaMdi { extension, .. } => 3 + extension.len(),
+                Ieee8023::LinkAggregation { .. } => 5,
+                Ieee8023::MaxFrameSize(_) => 2,
+            },
+            Repr::OrganizationallySpecific { info, .. } => 4 + info.len(),
+            Repr::Unknown { value, .. } => value.len(),
+        }
+    }
+    pub fn total_len(&self) -> usize {
+        Tlv::<()>::HEADER_LEN + self.value_len()
+    }
+    // value and the length octets inside it fit their fields
+    pub fn is_valid(&self) -> bool {
+        let fits_u8 = |len: usize| len <= u8::MAX as usize;
+        let inner = match self {
+            Repr::ManagementAddress { address, oid, .. } => fits_u8(1 + address.len()) && fits_u8(oid.len()),
+            Repr::Ieee8021(Ieee8021::VlanName { name, .. }) => fits_u8(name.len()),
+            Repr::Ieee8021(Ieee8021::ProtocolIdentity(id)) => fits_u8(id.len()),
+            _ => true,
+        };
+        inner && self.value_len() <= Tlv::<()>::MAX_VALUE_LEN
+    }
+    // buffer must be at least total_len() long; panics unless is_valid()
+    pub fn emit(&self, buffer: &mut [u8]) {
+        assert!(self.is_valid(), "{:?} TLV too long", self.tlv_type());
+        let mut tlv = Tlv::new(&mut *buffer);
+        tlv.set_header(self.tlv_type(), self.value_len());
+        let value = &mut tlv.value_mut()[..self.value_len()];
+        match *self {
+            Repr::End => {},
+            Repr::ChassisId { subtype, id } | Repr::PortId { subtype, id } => {
+                value[0] = subtype;
+                value[1..].copy_from_slice(id);
+            },
+            Repr::Ttl(ttl) => NetworkEndian::write_u16(value, ttl),
+            Repr::PortDescription(s) | Repr::SystemName(s) | Repr::SystemDescription(s) =>
+                value.copy_from_slice(s),
+            Repr::SystemCapabilities { capabilities, enabled } => {
+                NetworkEndian::write_u16(&mut value[0..2], capabilities);
+                NetworkEndian::write_u16(&mut value[2..4], enabled);
+            },
+            Repr::ManagementAddress { address_subtype, address, interface_subtype, interface_number, oid } => {
+                value[0] = (1 + address.len()) as u8;
+                value[1] = address_subtype;
+                value[2..2 + address.len()].copy_from_slice(address);
+                let rest = &mut value[2 + address.len()..];
+                rest[0] = interface_subtype;
+                NetworkEndian::write_u32(&mut rest[1..5], interface_number);
+                rest[5] = oid.len() as u8;
+                rest[6..].copy_from_slice(oid);
+            },
+            Repr::Ieee8021(tlv) => {
+                let (header, info) = value.split_at_mut(4);
+                header[0..3].copy_from_slice(&OUI_IEEE_8021);
+                header[3] = match tlv {
+                    Ieee8021::PortVlanId(vlan_id) => {
+                        NetworkEndian::write_u16(info, vlan_id);
+                        1
+                    },
+                    Ieee8021::PortAndProtocolVlanId { flags, vlan_id } => {
+                        info[0] = flags;
+                        NetworkEndian::write_u16(&mut info[1..3], vlan_id);
+                        2
+                    },
+                    Ieee8021::VlanName { vlan_id, name } => {
+                        NetworkEndian::write_u16(&mut info[0..2], vlan_id);
+                        info[2] = name.len() as u8;
+                        info[3..].copy_from_slice(name);
+                        3
+                    },
+                    Ieee8021::ProtocolIdentity(id) => {
+                        info[0] = id.len() as u8;
+                        info[1..].copy_from_slice(id);
+                        4
+                    },
+                };
+            },
+            Repr::Ieee8023(tlv) => {
+                let (header, info) = value.split_at_mut(4);
+                header[0..3].copy_from_slice(&OUI_IEEE_8023);
+                header[3] = match tlv {
+                    Ieee8023::MacPhyConfigStatus { autoneg, pmd_capability, mau_type } => {
+                        info[0] = autoneg;
+                        NetworkEndian::write_u16(&mut info[1..3], pmd_capability);
+                        NetworkEndian::write_u16(&mut info[3..5], mau_type);
+                        1
+                    },
+                    Ieee8023::PowerViaMdi { support, pse_power_pair, power_class, extension } => {
+                        info[..3].copy_from_slice(&[support, pse_power_pair, power_class]);
+                        info[3..].copy_from_slice(extension);
+                        2
+                    },
+                    Ieee8023::LinkAggregation { status, port_id } => {
+                        info[0] = status;
+                        NetworkEndian::write_u32(&mut info[1..5], port_id);
+                        3
+                    },
+                    Ieee8023::MaxFrameSize(size) => {
+                        NetworkEndian::write_u16(info, size);
+                        4
+                    },
+                };
+            },
+            Repr::OrganizationallySpecific { oui, subtype, info } => {
+                value[0..3].copy_from_slice(&oui);
+                value[3] = subtype;
+                value[4..].copy_from_slice(info);
+            },
+            Repr::Unknown { value: src, .. } => value.copy_from_slice(src),
+        }
+    }
+}
+
+// LLDPDU builder; mandatory TLVs come first and End of LLDPDU is appended on emit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Builder<'a> {
+    tlvs: Vec<Repr<'a>>,
+}
+
+impl<'a> Builder<'a> {
+    // panics if a TLV is too long, see Repr::is_valid
+    pub fn new(chassis_id: Repr<'a>, port_id: Repr<'a>, ttl: u16) -> Self {
+        debug_assert!(chassis_id.tlv_type() == TlvType::ChassisId);
+        debug_assert!(port_id.tlv_type() == TlvType::PortId);
+        assert!(chassis_id.is_valid() && port_id.is_valid(), "chassis or port ID too long");
+        Self { tlvs: vec![chassis_id, port_id, Repr::Ttl(ttl)] }
+    }
+    // chassis identified by MAC address and port by interface name, the common choice
+    pub fn with_mac_address(chassis: &'a Eui48, port_name: &'a str, ttl: u16) -> Self {
+        Self::new(
+            Repr::ChassisId { subtype: chassis_id::MAC_ADDRESS, id: chassis.as_bytes() },
+            Repr::PortId { subtype: port_id::INTERFACE_NAME, id: port_name.as_bytes() },
+            ttl,
+        )
+    }
+    pub fn push(mut self, tlv: Repr<'a>) -> Self {
+        assert!(tlv.is_valid(), "{:?} TLV too long", tlv.tlv_type());
+        self.tlvs.push(tlv);
+        self
+    }
+    pub fn port_description(self, description: &'a str) -> Self {
+        self.push(Repr::PortDescription(description.as_bytes()))
+    }
+    pub fn system_name(self, name: &'a str) -> Self {
+        self.push(Repr::SystemName(name.as_bytes()))
+    }
+    pub fn system_description(self, description: &'a str) -> Self {
+        self.push(Repr::SystemDescription(description.as_bytes()))
+    }
+    pub fn system_capabilities(self, capabilities: u16, enabled: u16) -> Self {
+        self.push(Repr::SystemCapabilities { capabilities, enabled })
+    }
+    // LLDPDU length including End of LLDPDU TLV
+    pub fn len(&self) -> usize {
+        self.tlvs.iter().map(Repr::total_len).sum::<usize>() + Repr::End.total_len()
+    }
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+    // buffer must be at least len() long, returns bytes written
+    pub fn emit(&self, buffer: &mut [u8]) -> usize {
+        let mut offset = 0;
+        for tlv in self.tlvs.iter().chain(Some(&Repr::End)) {
+            tlv.emit(&mut buffer[offset..]);
+            offset += tlv.total_len();
+        }
+        offset
+    }
+    // Ethernet frame length, padded to the 60-byte minimum
+    pub fn frame_len(&self) -> usize {
+        usize::max(14 + self.len(), 60)
+    }
+    // write whole Ethernet frame to buffer, which must be at least frame_len() long;
+    // returns frame length
+    pub fn emit_frame(&self, buffer: &mut [u8], src_addr: Eui48) -> usize {
+        let mut frame = EthernetFrame::new(&mut *buffer);
+        frame.set_dst_addr(MULTICAST_ADDR);
+        frame.set_src_addr(src_addr);
+        frame.set_ethertype(EthernetProtocol::Lldp);
+        let len = 14 + self.emit(frame.payload_mut());
+        let frame_len = self.frame_len();
+        buffer[len..frame_len].fill(0);
+        frame_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lldp_build_parse() {
+        let mac: Eui48 = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let mgmt_addr = [192, 168, 1, 2];
+        let builder = Builder::with_mac_address(&mac, "eth0", 120)
+            .port_description("uplink")
+            .system_name("lab-sw1")
+            .system_capabilities(capabilities::MAC_BRIDGE | capabilities::ROUTER, capabilities::MAC_BRIDGE)
+            .push(Repr::ManagementAddress {
+                address_subtype: 1, address: &mgmt_addr, interface_subtype: 2, interface_number: 3, oid: &[],
+            })
+            .push(Repr::Ieee8021(Ieee8021::PortVlanId(100)))
+            .push(Repr::Ieee8021(Ieee8021::VlanName { vlan_id: 100, name: b"lab" }))
+            .push(Repr::Ieee8023(Ieee8023::MaxFrameSize(1522)))
+            .push(Repr::Ieee8023(Ieee8023::PowerViaMdi {
+                support: 0x0f, pse_power_pair: 1, power_class: 5, extension: &[0x51, 0x00, 0xff, 0x00, 0xff],
+            }))
+            .push(Repr::OrganizationallySpecific { oui: [0x00, 0x12, 0xbb], subtype: 1, info: &[0, 1] });
+        let mut buf = vec![0u8; builder.frame_len()];
+        assert_eq!(builder.emit_frame(&mut buf, mac), buf.len());
+        let frame = EthernetFrame::new(&buf[..]);
+        assert_eq!(frame.ethertype(), EthernetProtocol::Lldp);
+        assert_eq!(frame.dst_addr(), MULTICAST_ADDR);
+        let tlvs = TlvIter::new(frame.payload())
+            .map(|tlv| Repr::parse(&tlv).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(&tlvs[..builder.tlvs.len()], &builder.tlvs[..]);
+        assert_eq!(tlvs.last(), Some(&Repr::End));
+        // a minimal LLDPDU is padded to the minimum frame size
+        let short = Builder::with_mac_address(&mac, "e0", 120);
+        let mut buf = [0xffu8; 64];
+        assert_eq!(short.emit_frame(&mut buf, mac), 60);
+        assert_eq!(14 + short.len(), 34);
+        assert!(buf[34..60].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn lldp_length_limits() {
+        assert!(Repr::SystemDescription(&[b'x'; 511]).is_valid());
+        assert!(!Repr::SystemDescription(&[b'x'; 512]).is_valid());
+        assert!(!Repr::Ieee8021(Ieee8021::VlanName { vlan_id: 1, name: &[b'x'; 256] }).is_valid());
+        let mgmt = |address: &'static [u8]| Repr::ManagementAddress {
+            address_subtype: 1, address, interface_subtype: 2, interface_number: 3, oid: &[],
+        };
+        assert!(mgmt(&[0; 254]).is_valid() && !mgmt(&[0; 255]).is_valid());
+        let description = "x".repeat(600);
+        let mac: Eui48 = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let result = std::panic::catch_unwind(|| Builder::with_mac_address(&mac, "eth0", 120).system_description(&description));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn lldp_malformed() {
+        // TTL value must be exactly 2 bytes
+        let data = [0x06, 0x01, 0x00];
+        let tlv = TlvIter::new(&data).next().unwrap();
+        assert!(Repr::parse(&tlv).is_err());
+        let data = [0x06, 0x03, 0x00, 0x78, 0x00];
+        let tlv = TlvIter::new(&data).next().unwrap();
+        assert!(Repr::parse(&tlv).is_err());
+        // length runs past the end
+        let data = [0x02, 0x07, 0x04, 0x00];
+        assert!(TlvIter::new(&data).next().is_none());
+    }
+}
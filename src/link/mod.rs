@@ -1,7 +1,23 @@
 mod eui;
 mod ether;
+mod arp;
+pub mod lldp;
+pub mod wol;
+pub mod stp;
+pub mod mpls;
+pub mod pppoe;
+pub mod eapol;
+mod llc;
+#[cfg(feature = "oui")]
+mod oui;
 
-pub use eui::Eui48 as EthernetAddress;
+pub use eui::{
+    Eui48 as EthernetAddress, Eui48Display as EthernetAddressDisplay, Separator as EthernetAddressSeparator,
+};
 pub use ether::{
-    Frame as EthernetFrame, Type as EthernetProtocol,
+    Frame as EthernetFrame, Type as EthernetProtocol, VlanTag, Writer as EthernetWriter,
+};
+pub use arp::{
+    Packet as ArpPacket, Hardware as ArpHardware, Operation as ArpOperation, Writer as ArpWriter,
 };
+pub use llc::Packet as LlcPacket;
@@ -0,0 +1,274 @@
+// MPLS label stack: See https://tools.ietf.org/html/rfc3032
+use super::ether::{Frame as EthernetFrame, Type as EthernetProtocol};
+use crate::net::{IpPacket, Ipv4Packet};
+use byteorder::{ByteOrder, NetworkEndian};
+
+// reserved label values, Section 2.1
+pub const IPV4_EXPLICIT_NULL: u32 = 0;
+pub const ROUTER_ALERT: u32 = 1;
+pub const IPV6_EXPLICIT_NULL: u32 = 2;
+pub const IMPLICIT_NULL: u32 = 3;
+
+// 20-bit label, 3-bit traffic class, bottom of stack bit and 8-bit TTL
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<T> {
+    inner: T
+}
+
+impl<T> Entry<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const LEN: usize = 4;
+}
+
+impl<T: AsRef<[u8]>> Entry<T> {
+    pub fn label(&self) -> u32 {
+        NetworkEndian::read_u32(self.inner.as_ref()) >> 12
+    }
+    pub fn traffic_class(&self) -> u8 {
+        (self.inner.as_ref()[2] >> 1) & 0x07
+    }
+    pub fn bottom_of_stack(&self) -> bool {
+        self.inner.as_ref()[2] & 0x01 != 0
+    }
+    pub fn ttl(&self) -> u8 {
+        self.inner.as_ref()[3]
+    }
+    pub fn repr(&self) -> Label {
+        Label::from(NetworkEndian::read_u32(self.inner.as_ref()))
+    }
+}
+
+impl<T: AsMut<[u8]>> Entry<T> {
+    pub fn set_label(&mut self, label: u32) {
+        debug_assert!(label <= 0x000fffff);
+        let data = self.inner.as_mut();
+        let value = (NetworkEndian::read_u32(data) & 0x0fff) | (label << 12);
+        NetworkEndian::write_u32(data, value)
+    }
+    pub fn set_traffic_class(&mut self, traffic_class: u8) {
+        let data = self.inner.as_mut();
+        data[2] = (data[2] & !0x0e) | ((traffic_class & 0x07) << 1)
+    }
+    pub fn set_bottom_of_stack(&mut self, bottom_of_stack: bool) {
+        let data = self.inner.as_mut();
+        data[2] = (data[2] & !0x01) | bottom_of_stack as u8
+    }
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.inner.as_mut()[3] = ttl
+    }
+}
+
+// owned label stack entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub label: u32,
+    pub traffic_class: u8,
+    pub bottom_of_stack: bool,
+    pub ttl: u8,
+}
+
+impl Label {
+    pub fn new(label: u32, traffic_class: u8, ttl: u8) -> Self {
+        Self { label, traffic_class, bottom_of_stack: false, ttl }
+    }
+    pub fn to_bytes(self) -> [u8; 4] {
+        u32::from(self).to_be_bytes()
+    }
+}
+
+impl From<u32> for Label {
+    fn from(src: u32) -> Self {
+        Label {
+            label: src >> 12,
+            traffic_class: ((src >> 9) & 0x07) as u8,
+            bottom_of_stack: src & 0x0100 != 0,
+            ttl: src as u8,
+        }
+    }
+}
+
+impl From<Label> for u32 {
+    fn from(src: Label) -> u32 {
+        debug_assert!(src.label <= 0x000fffff);
+        (src.label << 12) | ((src.traffic_class as u32 & 0x07) << 9) |
+            ((src.bottom_of_stack as u32) << 8) | src.ttl as u32
+    }
+}
+
+// label stack followed by payload
+#[derive(Debug, Clone, Copy)]
+pub struct Stack<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Stack<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+    pub fn entries(&self) -> StackIter<'a> {
+        StackIter { data: self.data, finished: false }
+    }
+    // number of entries up to and including bottom of stack, or None if truncated
+    pub fn depth(&self) -> Option<usize> {
+        let mut depth = 0;
+        for entry in self.entries() {
+            depth += 1;
+            if entry.bottom_of_stack() {
+                return Some(depth)
+            }
+        }
+        None
+    }
+    // data after bottom of stack entry
+    pub fn payload(&self) -> Option<&'a [u8]> {
+        self.depth().map(|depth| &self.data[depth * Entry::<()>::LEN..])
+    }
+    // MPLS does not identify its payload; sniff the IP version nibble
+    pub fn payload_protocol(&self) -> Option<Payload<'a>> {
+        let payload = self.payload()?;
+        Some(match payload.first().map(|byte| byte >> 4) {
+            Some(4) => match Ipv4Packet::new_checked(payload) {
+                Some(packet) => Payload::Ipv4(packet),
+                None => Payload::Unknown(payload),
+            },
+            Some(6) if payload.len() >= 40 => Payload::Ipv6(IpPacket::new(payload)),
+            _ => Payload::Unknown(payload),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StackIter<'a> {
+    data: &'a [u8],
+    finished: bool,
+}
+
+impl<'a> Iterator for StackIter<'a> {
+    type Item = Entry<&'a [u8]>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.data.len() < Entry::<()>::LEN {
+            return None
+        }
+        let (entry, rest) = self.data.split_at(Entry::<()>::LEN);
+        let entry = Entry::new(entry);
+        self.finished = entry.bottom_of_stack();
+        self.data = rest;
+        Some(entry)
+    }
+}
+
+pub enum Payload<'a> {
+    Ipv4(Ipv4Packet<&'a [u8]>),
+    Ipv6(IpPacket<&'a [u8]>),
+    Unknown(&'a [u8]),
+}
+
+// label stack of an MPLS Ethernet frame, or None for other protocols
+pub fn parse_frame(frame: &[u8]) -> Option<Stack<'_>> {
+    if frame.len() < 14 {
+        return None
+    }
+    let frame = EthernetFrame::new(frame);
+    match frame.ethertype() {
+        EthernetProtocol::Mpls | EthernetProtocol::MplsMulticast => Some(Stack::new(frame.payload())),
+        _ => None,
+    }
+}
+
+// push label onto an Ethernet frame, turning it into a unicast MPLS frame;
+// bottom of stack is set when the frame was not labelled before;
+// None if the frame is too short to have an Ethernet header
+pub fn push_label(frame: &mut Vec<u8>, label: Label) -> Option<()> {
+    if frame.len() < 14 {
+        return None
+    }
+    let ethertype = EthernetFrame::new(&frame[..]).ethertype();
+    let labelled = matches!(ethertype, EthernetProtocol::Mpls | EthernetProtocol::MplsMulticast);
+    let label = Label { bottom_of_stack: !labelled, ..label };
+    frame.splice(14..14, label.to_bytes().iter().copied());
+    if !labelled {
+        EthernetFrame::new(&mut frame[..]).set_ethertype(EthernetProtocol::Mpls);
+    }
+    Some(())
+}
+
+// pop top label off an MPLS Ethernet frame; after popping the bottom label
+// ethertype is restored from the IP version of the payload
+pub fn pop_label(frame: &mut Vec<u8>) -> Option<Label> {
+    let stack = parse_frame(frame)?;
+    let label = stack.entries().next()?.repr();
+    let ethertype = if label.bottom_of_stack {
+        match frame.get(18).map(|byte| byte >> 4) {
+            Some(4) => EthernetProtocol::Ipv4,
+            Some(6) => EthernetProtocol::Ipv6,
+            _ => return None,
+        }
+    } else {
+        EthernetFrame::new(&frame[..]).ethertype()
+    };
+    frame.drain(14..18);
+    EthernetFrame::new(&mut frame[..]).set_ethertype(ethertype);
+    Some(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv6_frame() -> Vec<u8> {
+        let mut buf = vec![0u8; 14 + 40];
+        let mut frame = EthernetFrame::new(&mut buf);
+        frame.set_ethertype(EthernetProtocol::Ipv6);
+        let mut ip = IpPacket::new(frame.payload_mut());
+        ip.set_version(6);
+        ip.set_hop_limit(64);
+        buf
+    }
+
+    #[test]
+    fn mpls_entry() {
+        let mut buf = [0u8; 4];
+        let mut entry = Entry::new(&mut buf);
+        entry.set_label(0x12345);
+        entry.set_traffic_class(5);
+        entry.set_bottom_of_stack(true);
+        entry.set_ttl(64);
+        assert_eq!(buf, [0x12, 0x34, 0x5b, 0x40]);
+        let entry = Entry::new(&buf);
+        assert_eq!(entry.label(), 0x12345);
+        assert_eq!(entry.traffic_class(), 5);
+        assert!(entry.bottom_of_stack());
+        assert_eq!(entry.ttl(), 64);
+        assert_eq!(entry.repr(), Label { label: 0x12345, traffic_class: 5, bottom_of_stack: true, ttl: 64 });
+        assert_eq!(entry.repr().to_bytes(), buf);
+    }
+
+    #[test]
+    fn mpls_push_pop() {
+        let original = ipv6_frame();
+        let mut frame = original.clone();
+        push_label(&mut frame, Label::new(100, 0, 255)).unwrap();
+        push_label(&mut frame, Label::new(200, 1, 254)).unwrap();
+        assert_eq!(frame.len(), original.len() + 8);
+        let stack = parse_frame(&frame).unwrap();
+        let labels = stack.entries().map(|entry| (entry.label(), entry.bottom_of_stack())).collect::<Vec<_>>();
+        assert_eq!(labels, vec![(200, false), (100, true)]);
+        assert_eq!(stack.depth(), Some(2));
+        match stack.payload_protocol() {
+            Some(Payload::Ipv6(ip)) => assert_eq!(ip.hop_limit(), 64),
+            _ => panic!("expected IPv6 payload"),
+        }
+        assert_eq!(pop_label(&mut frame).map(|label| label.label), Some(200));
+        assert_eq!(pop_label(&mut frame).map(|label| label.label), Some(100));
+        assert_eq!(frame, original);
+        assert_eq!(pop_label(&mut frame), None);
+        let mut short = original[..13].to_vec();
+        assert_eq!(push_label(&mut short, Label::new(100, 0, 255)), None);
+        assert_eq!(short, &original[..13]);
+    }
+}
@@ -0,0 +1,77 @@
+//! IEEE OUI vendor registry lookup
+use super::eui::Eui48;
+
+// Vendor names are deduplicated into one string, each assignment table is
+// sorted by prefix and refers to names by index
+struct Registry {
+    names: &'static str,
+    name_ends: &'static [usize],
+    // MA-S (36 bits), MA-M (28 bits), MA-L (24 bits)
+    assignments: [&'static [(u64, usize)]; 3],
+}
+
+const ASSIGNMENT_BITS: [u32; 3] = [36, 28, 24];
+
+include!(concat!(env!("OUT_DIR"), "/oui_registry.rs"));
+
+impl Registry {
+    // longest assignment wins, e.g. a MA-S block inside IEEE's own MA-L
+    fn lookup(&self, address: Eui48) -> Option<&'static str> {
+        let mut bytes = [0u8; 8];
+        bytes[2..].copy_from_slice(&address.to_bytes());
+        let repr = u64::from_be_bytes(bytes);
+        for (table, bits) in self.assignments.iter().zip(ASSIGNMENT_BITS) {
+            let prefix = repr >> (48 - bits);
+            if let Ok(idx) = table.binary_search_by_key(&prefix, |&(prefix, _)| prefix) {
+                return Some(self.name(table[idx].1))
+            }
+        }
+        None
+    }
+    fn name(&self, id: usize) -> &'static str {
+        let start = if id == 0 { 0 } else { self.name_ends[id - 1] };
+        &self.names[start..self.name_ends[id]]
+    }
+}
+
+impl Eui48 {
+    // organization the address block is assigned to; locally administered
+    // addresses never match
+    pub fn vendor(self) -> Option<&'static str> {
+        if self.is_local() {
+            return None
+        }
+        REGISTRY.lookup(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Eui48, Registry};
+
+    static TEST_REGISTRY: Registry = Registry {
+        names: "IEEE Registration AuthorityVendor MVendor S",
+        name_ends: &[27, 35, 43],
+        assignments: [
+            &[(0x70b3d5123, 2)],
+            &[(0x70b3d51, 1)],
+            &[(0x70b3d5, 0)],
+        ],
+    };
+
+    #[test]
+    fn oui_longest_assignment() {
+        let lookup = |s: &str| TEST_REGISTRY.lookup(s.parse().unwrap());
+        assert_eq!(lookup("70-B3-D5-12-34-56"), Some("Vendor S"));
+        assert_eq!(lookup("70-B3-D5-12-44-56"), Some("Vendor M"));
+        assert_eq!(lookup("70-B3-D5-22-34-56"), Some("IEEE Registration Authority"));
+        assert_eq!(lookup("70-B3-D6-12-34-56"), None);
+    }
+
+    #[test]
+    fn oui_vendor() {
+        let addr: Eui48 = "00:15:5d:b7:9f:a9".parse().unwrap();
+        assert_eq!(addr.vendor(), Some("Microsoft Corporation"));
+        assert_eq!(Eui48::new_local(addr.to_bytes()).vendor(), None);
+    }
+}
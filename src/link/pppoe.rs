@@ -0,0 +1,555 @@
+// PPPoE: See https://tools.ietf.org/html/rfc2516
+use super::eui::Eui48;
+use super::ether::{Frame as EthernetFrame, Type as EthernetProtocol, Writer as EthernetWriter};
+use crate::raw_socket_sys::RawSocketDesc;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::ops::{Range, RangeFrom};
+use std::io;
+use std::time::{Duration, Instant};
+
+// PPPoE header shared by discovery and session stages
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<T> {
+    inner: T
+}
+
+impl<T> Packet<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const VER_TYPE: usize = 0;
+    const CODE: usize = 1;
+    const SESSION_ID: Range<usize> = 2..4;
+    const LENGTH: Range<usize> = 4..6;
+    const PAYLOAD: RangeFrom<usize> = 6..;
+    const HEADER_LEN: usize = 6;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn version(&self) -> u8 {
+        self.inner.as_ref()[Self::VER_TYPE] >> 4
+    }
+    pub fn packet_type(&self) -> u8 {
+        self.inner.as_ref()[Self::VER_TYPE] & 0x0f
+    }
+    pub fn code(&self) -> Code {
+        self.inner.as_ref()[Self::CODE].into()
+    }
+    pub fn session_id(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::SESSION_ID])
+    }
+    pub fn payload_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::LENGTH])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    // payload without Ethernet padding
+    pub fn payload(&self) -> &'a [u8] {
+        let payload = &self.inner.as_ref()[Self::PAYLOAD];
+        &payload[..usize::min(self.payload_len() as usize, payload.len())]
+    }
+    // tags of discovery packets
+    pub fn tags(&self) -> TagIter<'a> {
+        TagIter { data: self.payload() }
+    }
+    // PPP protocol field of session packets
+    pub fn ppp_protocol(&self) -> PppProtocol {
+        match self.payload() {
+            [hi, lo, ..] => NetworkEndian::read_u16(&[*hi, *lo]).into(),
+            _ => PppProtocol::Unknown(0),
+        }
+    }
+    pub fn ppp_payload(&self) -> &'a [u8] {
+        self.payload().get(2..).unwrap_or(&[])
+    }
+}
+
+impl<T: AsMut<[u8]>> Packet<T> {
+    // version and type are both 1 in RFC 2516
+    pub fn set_version_type(&mut self) {
+        self.inner.as_mut()[Self::VER_TYPE] = 0x11
+    }
+    pub fn set_code(&mut self, code: Code) {
+        self.inner.as_mut()[Self::CODE] = code.into()
+    }
+    pub fn set_session_id(&mut self, session_id: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::SESSION_ID], session_id)
+    }
+    pub fn set_payload_len(&mut self, len: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::LENGTH], len)
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Packet<&mut T> {
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.as_mut()[Self::PAYLOAD]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    Session, // 0x00
+    Pado, // 0x07
+    Padi, // 0x09
+    Padr, // 0x19
+    Pads, // 0x65
+    Padt, // 0xa7
+    Unknown(u8),
+}
+
+impl From<u8> for Code {
+    fn from(src: u8) -> Self {
+        match src {
+            0x00 => Code::Session,
+            0x07 => Code::Pado,
+            0x09 => Code::Padi,
+            0x19 => Code::Padr,
+            0x65 => Code::Pads,
+            0xa7 => Code::Padt,
+            others => Code::Unknown(others),
+        }
+    }
+}
+
+impl From<Code> for u8 {
+    fn from(src: Code) -> u8 {
+        match src {
+            Code::Session => 0x00,
+            Code::Pado => 0x07,
+            Code::Padi => 0x09,
+            Code::Padr => 0x19,
+            Code::Pads => 0x65,
+            Code::Padt => 0xa7,
+            Code::Unknown(others) => others
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagType {
+    EndOfList, // 0x0000
+    ServiceName, // 0x0101
+    AcName, // 0x0102
+    HostUniq, // 0x0103
+    AcCookie, // 0x0104
+    VendorSpecific, // 0x0105
+    RelaySessionId, // 0x0110
+    ServiceNameError, // 0x0201
+    AcSystemError, // 0x0202
+    GenericError, // 0x0203
+    Unknown(u16),
+}
+
+impl From<u16> for TagType {
+    fn from(src: u16) -> Self {
+        match src {
+            0x0000 => TagType::EndOfList,
+            0x0101 => TagType::ServiceName,
+            0x0102 => TagType::AcName,
+            0x0103 => TagType::HostUniq,
+            0x0104 => TagType::AcCookie,
+            0x0105 => TagType::VendorSpecific,
+            0x0110 => TagType::RelaySessionId,
+            0x0201 => TagType::ServiceNameError,
+            0x0202 => TagType::AcSystemError,
+            0x0203 => TagType::GenericError,
+            others => TagType::Unknown(others),
+        }
+    }
+}
+
+impl From<TagType> for u16 {
+    fn from(src: TagType) -> u16 {
+        match src {
+            TagType::EndOfList => 0x0000,
+            TagType::ServiceName => 0x0101,
+            TagType::AcName => 0x0102,
+            TagType::HostUniq => 0x0103,
+            TagType::AcCookie => 0x0104,
+            TagType::VendorSpecific => 0x0105,
+            TagType::RelaySessionId => 0x0110,
+            TagType::ServiceNameError => 0x0201,
+            TagType::AcSystemError => 0x0202,
+            TagType::GenericError => 0x0203,
+            TagType::Unknown(others) => others
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tag<'a> {
+    pub tag_type: TagType,
+    pub value: &'a [u8],
+}
+
+impl<'a> Tag<'a> {
+    pub fn new(tag_type: TagType, value: &'a [u8]) -> Self {
+        Self { tag_type, value }
+    }
+    pub fn total_len(&self) -> usize {
+        4 + self.value.len()
+    }
+    // buffer must be at least total_len() long
+    pub fn emit(&self, buffer: &mut [u8]) {
+        NetworkEndian::write_u16(&mut buffer[0..2], self.tag_type.into());
+        NetworkEndian::write_u16(&mut buffer[2..4], self.value.len() as u16);
+        buffer[4..self.total_len()].copy_from_slice(self.value);
+    }
+}
+
+// stops at End-Of-List tag or truncated data
+#[derive(Debug, Clone)]
+pub struct TagIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for TagIter<'a> {
+    type Item = Tag<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < 4 {
+            return None
+        }
+        let tag_type = NetworkEndian::read_u16(&self.data[0..2]).into();
+        let len = NetworkEndian::read_u16(&self.data[2..4]) as usize;
+        if tag_type == TagType::EndOfList || self.data.len() < 4 + len {
+            self.data = &[];
+            return None
+        }
+        let value = &self.data[4..4 + len];
+        self.data = &self.data[4 + len..];
+        Some(Tag { tag_type, value })
+    }
+}
+
+// PPP protocol field: See https://www.iana.org/assignments/ppp-numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PppProtocol {
+    Ipv4, // 0x0021
+    Ipv6, // 0x0057
+    Ipcp, // 0x8021
+    Ipv6cp, // 0x8057
+    Lcp, // 0xc021
+    Pap, // 0xc023
+    Chap, // 0xc223
+    Unknown(u16),
+}
+
+impl From<u16> for PppProtocol {
+    fn from(src: u16) -> Self {
+        match src {
+            0x0021 => PppProtocol::Ipv4,
+            0x0057 => PppProtocol::Ipv6,
+            0x8021 => PppProtocol::Ipcp,
+            0x8057 => PppProtocol::Ipv6cp,
+            0xc021 => PppProtocol::Lcp,
+            0xc023 => PppProtocol::Pap,
+            0xc223 => PppProtocol::Chap,
+            others => PppProtocol::Unknown(others),
+        }
+    }
+}
+
+impl From<PppProtocol> for u16 {
+    fn from(src: PppProtocol) -> u16 {
+        match src {
+            PppProtocol::Ipv4 => 0x0021,
+            PppProtocol::Ipv6 => 0x0057,
+            PppProtocol::Ipcp => 0x8021,
+            PppProtocol::Ipv6cp => 0x8057,
+            PppProtocol::Lcp => 0xc021,
+            PppProtocol::Pap => 0xc023,
+            PppProtocol::Chap => 0xc223,
+            PppProtocol::Unknown(others) => others
+        }
+    }
+}
+
+// write discovery frame with given tags, zero-padded to the Ethernet
+// minimum; returns frame length
+pub fn emit_discovery(
+    buffer: &mut [u8], src_addr: Eui48, dst_addr: Eui48,
+    code: Code, session_id: u16, tags: &[Tag<'_>],
+) -> usize {
+    let payload_len: usize = tags.iter().map(Tag::total_len).sum();
+    let mut frame = EthernetFrame::new(&mut *buffer);
+    frame.set_dst_addr(dst_addr);
+    frame.set_src_addr(src_addr);
+    frame.set_ethertype(EthernetProtocol::PppoeDiscovery);
+    let mut packet = Packet::new(frame.payload_mut());
+    packet.set_version_type();
+    packet.set_code(code);
+    packet.set_session_id(session_id);
+    packet.set_payload_len(payload_len as u16);
+    let mut offset = 0;
+    for tag in tags {
+        tag.emit(&mut packet.payload_mut()[offset..]);
+        offset += tag.total_len();
+    }
+    let len = 14 + Packet::<()>::HEADER_LEN + payload_len;
+    let padded_len = usize::max(len, EthernetWriter::<()>::MIN_LEN);
+    buffer[len..padded_len].fill(0);
+    padded_len
+}
+
+// PPPoE packet of a sniffed Ethernet frame, either stage
+pub fn parse_frame(frame: &[u8]) -> Option<Packet<&[u8]>> {
+    if frame.len() < 14 + Packet::<()>::HEADER_LEN {
+        return None
+    }
+    let frame = EthernetFrame::new(frame);
+    match frame.ethertype() {
+        EthernetProtocol::PppoeDiscovery | EthernetProtocol::PppoeSession => Some(Packet::new(frame.payload())),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    // PADI sent, waiting for PADO
+    Initiation,
+    // PADR sent to chosen access concentrator, waiting for PADS
+    Request { ac_addr: Eui48, ac_cookie: Option<Vec<u8>> },
+    Session { ac_addr: Eui48, session_id: u16 },
+    // PADT received or an error tag was reported
+    Terminated,
+}
+
+// discovery stage client; the caller feeds received frames and polls for timeouts
+#[derive(Debug, Clone)]
+pub struct Client {
+    src_addr: Eui48,
+    service_name: Vec<u8>,
+    host_uniq: [u8; 4],
+    state: State,
+    retransmit_at: Instant,
+    timeout: Duration,
+    // PADI or PADR sent in the current state
+    attempts: u32,
+}
+
+impl Client {
+    // initial timeout, doubled after each retransmission; Section 7 of RFC 2516
+    pub const INITIAL_TIMEOUT: Duration = Duration::from_secs(1);
+    pub const MAX_TIMEOUT: Duration = Duration::from_secs(16);
+    // give up after this many unanswered PADI or PADR
+    pub const MAX_ATTEMPTS: u32 = 5;
+
+    pub fn new(src_addr: Eui48, service_name: &[u8], host_uniq: [u8; 4], now: Instant) -> Self {
+        Self {
+            src_addr,
+            service_name: service_name.to_vec(),
+            host_uniq,
+            state: State::Initiation,
+            retransmit_at: now,
+            timeout: Self::INITIAL_TIMEOUT,
+            attempts: 0,
+        }
+    }
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+    pub fn session_id(&self) -> Option<u16> {
+        match self.state {
+            State::Session { session_id, .. } => Some(session_id),
+            _ => None,
+        }
+    }
+    // when poll has to be called next
+    pub fn poll_at(&self) -> Instant {
+        self.retransmit_at
+    }
+    // the last retransmission went unanswered
+    pub fn timed_out(&self, now: Instant) -> bool {
+        match self.state {
+            State::Initiation | State::Request { .. } => self.attempts >= Self::MAX_ATTEMPTS && now >= self.retransmit_at,
+            State::Session { .. } | State::Terminated => false,
+        }
+    }
+    // write frame to send now if any, returns frame length
+    pub fn poll(&mut self, buffer: &mut [u8], now: Instant) -> Option<usize> {
+        if now < self.retransmit_at || self.attempts >= Self::MAX_ATTEMPTS {
+            return None
+        }
+        let len = match &self.state {
+            State::Initiation => self.emit(buffer, Eui48::BROADCAST, Code::Padi, None),
+            State::Request { ac_addr, ac_cookie } => {
+                let ac_cookie = ac_cookie.clone();
+                self.emit(buffer, *ac_addr, Code::Padr, ac_cookie.as_deref())
+            },
+            State::Session { .. } | State::Terminated => return None,
+        };
+        self.retransmit_at = now + self.timeout;
+        self.timeout = Duration::min(self.timeout * 2, Self::MAX_TIMEOUT);
+        self.attempts += 1;
+        Some(len)
+    }
+    // process a received frame, returns whether the state changed
+    pub fn process_frame(&mut self, frame: &[u8], now: Instant) -> bool {
+        let packet = match parse_frame(frame) {
+            Some(packet) => packet,
+            None => return false,
+        };
+        let eth = EthernetFrame::new(frame);
+        if eth.ethertype() != EthernetProtocol::PppoeDiscovery || eth.dst_addr() != self.src_addr {
+            return false
+        }
+        let (mut host_uniq_ok, mut ac_cookie, mut error) = (false, None, false);
+        for tag in packet.tags() {
+            match tag.tag_type {
+                TagType::HostUniq => host_uniq_ok = tag.value == self.host_uniq,
+                TagType::AcCookie => ac_cookie = Some(tag.value.to_vec()),
+                TagType::ServiceNameError | TagType::AcSystemError | TagType::GenericError => error = true,
+                _ => {},
+            }
+        }
+        let ac_addr = eth.src_addr();
+        let next = match (&self.state, packet.code()) {
+            (_, Code::Padt) => match self.state {
+                State::Session { ac_addr: addr, session_id } if addr == ac_addr && session_id == packet.session_id() =>
+                    State::Terminated,
+                _ => return false,
+            },
+            _ if !host_uniq_ok => return false,
+            (State::Initiation, Code::Pado) if !error => State::Request { ac_addr, ac_cookie },
+            (State::Request { ac_addr: addr, .. }, Code::Pads) if *addr == ac_addr => {
+                if error || packet.session_id() == 0 {
+                    State::Terminated
+                } else {
+                    State::Session { ac_addr, session_id: packet.session_id() }
+                }
+            },
+            _ => return false,
+        };
+        self.state = next;
+        self.retransmit_at = now;
+        self.timeout = Self::INITIAL_TIMEOUT;
+        self.attempts = 0;
+        true
+    }
+    // write PADT for an established session, returns frame length
+    pub fn terminate(&mut self, buffer: &mut [u8]) -> Option<usize> {
+        let (ac_addr, session_id) = match self.state {
+            State::Session { ac_addr, session_id } => (ac_addr, session_id),
+            _ => return None,
+        };
+        self.state = State::Terminated;
+        let len = emit_discovery(buffer, self.src_addr, ac_addr, Code::Padt, session_id, &[]);
+        Some(len)
+    }
+    // drive discovery on a bound socket until a session is established or terminated;
+    // fails with TimedOut when no access concentrator answers
+    pub fn run(&mut self, socket: &mut RawSocketDesc, buffer: &mut [u8]) -> io::Result<Option<u16>> {
+        loop {
+            let now = Instant::now();
+            if let Some(len) = self.poll(buffer, now) {
+                socket.send(&buffer[..len])?;
+            }
+            if self.timed_out(now) {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no answer from access concentrator"))
+            }
+            if !socket.wait(self.poll_at().saturating_duration_since(Instant::now()))? {
+                continue
+            }
+            match socket.recv(buffer) {
+                Ok(len) => { self.process_frame(&buffer[..len], Instant::now()); },
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {},
+                Err(err) => return Err(err),
+            }
+            match self.state {
+                State::Session { session_id, .. } => return Ok(Some(session_id)),
+                State::Terminated => return Ok(None),
+                _ => {},
+            }
+        }
+    }
+    fn emit(&self, buffer: &mut [u8], dst_addr: Eui48, code: Code, ac_cookie: Option<&[u8]>) -> usize {
+        let mut tags = vec![
+            Tag::new(TagType::ServiceName, &self.service_name),
+            Tag::new(TagType::HostUniq, &self.host_uniq),
+        ];
+        if let Some(ac_cookie) = ac_cookie {
+            tags.push(Tag::new(TagType::AcCookie, ac_cookie));
+        }
+        emit_discovery(buffer, self.src_addr, dst_addr, code, 0, &tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pppoe_discovery() {
+        let client_addr: Eui48 = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let ac_addr: Eui48 = "00-15-5d-ee-22-75".parse().unwrap();
+        let host_uniq = [1, 2, 3, 4];
+        let now = Instant::now();
+        let mut client = Client::new(client_addr, b"", host_uniq, now);
+        let mut buf = [0u8; 1514];
+
+        let len = client.poll(&mut buf, now).unwrap();
+        let padi = parse_frame(&buf[..len]).unwrap();
+        assert_eq!(padi.code(), Code::Padi);
+        assert_eq!(padi.tags().count(), 2);
+        assert_eq!(client.poll(&mut buf, now), None);
+        assert!(client.poll(&mut buf, now + Client::INITIAL_TIMEOUT).is_some());
+
+        let tags = [
+            Tag::new(TagType::AcName, b"lab-bras"),
+            Tag::new(TagType::HostUniq, &host_uniq),
+            Tag::new(TagType::AcCookie, b"cookie"),
+        ];
+        let len = emit_discovery(&mut buf, ac_addr, client_addr, Code::Pado, 0, &tags);
+        assert!(client.process_frame(&buf[..len], now));
+        assert_eq!(client.state(), &State::Request { ac_addr, ac_cookie: Some(b"cookie".to_vec()) });
+
+        let len = client.poll(&mut buf, now).unwrap();
+        let padr = parse_frame(&buf[..len]).unwrap();
+        assert_eq!(padr.code(), Code::Padr);
+        assert!(padr.tags().any(|tag| tag == Tag::new(TagType::AcCookie, b"cookie")));
+
+        let len = emit_discovery(&mut buf, ac_addr, client_addr, Code::Pads, 0x1234, &tags[1..2]);
+        assert!(client.process_frame(&buf[..len], now));
+        assert_eq!(client.session_id(), Some(0x1234));
+
+        let len = emit_discovery(&mut buf, ac_addr, client_addr, Code::Padt, 0x1234, &[]);
+        assert_eq!(len, 60);
+        assert_eq!(parse_frame(&buf[..len]).unwrap().payload_len(), 0);
+        assert!(client.process_frame(&buf[..len], now));
+        assert_eq!(client.state(), &State::Terminated);
+    }
+
+    #[test]
+    fn pppoe_discovery_timeout() {
+        let client_addr: Eui48 = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let mut now = Instant::now();
+        let mut client = Client::new(client_addr, b"", [1, 2, 3, 4], now);
+        let mut buf = [0u8; 1514];
+        for _ in 0..Client::MAX_ATTEMPTS {
+            assert!(!client.timed_out(now));
+            assert!(client.poll(&mut buf, now).is_some());
+            now = client.poll_at();
+        }
+        assert_eq!(client.poll(&mut buf, now), None);
+        assert!(client.timed_out(now));
+    }
+
+    #[test]
+    fn pppoe_session() {
+        let data = [
+            0x11, 0x00, 0x12, 0x34, 0x00, 0x06,
+            0x80, 0x57, 0x01, 0x01, 0x00, 0x0e,
+            0xff, 0xff, // Ethernet padding
+        ];
+        let packet = Packet::new(&data[..]);
+        assert_eq!(packet.version(), 1);
+        assert_eq!(packet.code(), Code::Session);
+        assert_eq!(packet.session_id(), 0x1234);
+        assert_eq!(packet.ppp_protocol(), PppProtocol::Ipv6cp);
+        assert_eq!(packet.ppp_payload(), &[0x01, 0x01, 0x00, 0x0e]);
+    }
+}
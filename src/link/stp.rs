@@ -0,0 +1,579 @@
+// Spanning tree BPDUs: See IEEE 802.1D-2004 Clause 9 and IEEE 802.1Q-2018 Clause 14
+use super::eui::Eui48;
+use super::ether::Frame as EthernetFrame;
+use super::llc::{self, Packet as LlcPacket};
+use crate::raw_socket_sys::RawSocketDesc;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+use core::ops::Range;
+use core::time::Duration;
+use std::collections::HashMap;
+use std::io;
+
+// bridge group address used by STP, RSTP and MSTP
+pub const MULTICAST_ADDR: Eui48 = Eui48::new([0x01, 0x80, 0xc2, 0x00, 0x00, 0x00]);
+
+// 4-bit priority, 12-bit system ID extension (VLAN or MSTI) and bridge address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BridgeId {
+    repr: u64,
+}
+
+impl BridgeId {
+    pub fn new(priority: u16, system_id_ext: u16, address: Eui48) -> Self {
+        debug_assert!(priority & 0x0fff == 0 && system_id_ext <= 0x0fff);
+        let mut bytes = [0u8; 8];
+        NetworkEndian::write_u16(&mut bytes[0..2], priority | system_id_ext);
+        bytes[2..8].copy_from_slice(address.as_bytes());
+        Self { repr: u64::from_be_bytes(bytes) }
+    }
+    pub fn from_bytes(src: &[u8]) -> Self {
+        Self { repr: NetworkEndian::read_u64(src) }
+    }
+    pub fn to_bytes(self) -> [u8; 8] {
+        self.repr.to_be_bytes()
+    }
+    // multiple of 4096, smaller is preferred
+    pub fn priority(self) -> u16 {
+        (self.repr >> 48) as u16 & 0xf000
+    }
+    pub fn system_id_ext(self) -> u16 {
+        (self.repr >> 48) as u16 & 0x0fff
+    }
+    pub fn address(self) -> Eui48 {
+        Eui48::from_bytes(&self.to_bytes()[2..8])
+    }
+}
+
+// same form as Linux bridge utilities, e.g. 8000.00155db79fa9
+impl fmt::Display for BridgeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}.{:012x}", self.repr >> 48, self.repr & 0xffff_ffff_ffff)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Stp, // 0
+    Rstp, // 2
+    Mstp, // 3
+    Unknown(u8),
+}
+
+impl From<u8> for Version {
+    fn from(src: u8) -> Self {
+        match src {
+            0 => Version::Stp,
+            2 => Version::Rstp,
+            3 => Version::Mstp,
+            others => Version::Unknown(others),
+        }
+    }
+}
+
+impl From<Version> for u8 {
+    fn from(src: Version) -> u8 {
+        match src {
+            Version::Stp => 0,
+            Version::Rstp => 2,
+            Version::Mstp => 3,
+            Version::Unknown(others) => others
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BpduType {
+    Config, // 0x00
+    Tcn, // 0x80
+    Rst, // 0x02, also used by MSTP
+    Unknown(u8),
+}
+
+impl From<u8> for BpduType {
+    fn from(src: u8) -> Self {
+        match src {
+            0x00 => BpduType::Config,
+            0x80 => BpduType::Tcn,
+            0x02 => BpduType::Rst,
+            others => BpduType::Unknown(others),
+        }
+    }
+}
+
+impl From<BpduType> for u8 {
+    fn from(src: BpduType) -> u8 {
+        match src {
+            BpduType::Config => 0x00,
+            BpduType::Tcn => 0x80,
+            BpduType::Rst => 0x02,
+            BpduType::Unknown(others) => others
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortRole {
+    Master, // 0, unknown in RSTP
+    AlternateOrBackup, // 1
+    Root, // 2
+    Designated, // 3
+}
+
+// flags field of RST BPDU and MSTI records, Section 9.3.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Flags(pub u8);
+
+impl Flags {
+    pub fn topology_change(self) -> bool {
+        self.0 & 0x01 != 0
+    }
+    pub fn proposal(self) -> bool {
+        self.0 & 0x02 != 0
+    }
+    pub fn port_role(self) -> PortRole {
+        match (self.0 >> 2) & 0x03 {
+            0 => PortRole::Master,
+            1 => PortRole::AlternateOrBackup,
+            2 => PortRole::Root,
+            _ => PortRole::Designated,
+        }
+    }
+    pub fn learning(self) -> bool {
+        self.0 & 0x10 != 0
+    }
+    pub fn forwarding(self) -> bool {
+        self.0 & 0x20 != 0
+    }
+    pub fn agreement(self) -> bool {
+        self.0 & 0x40 != 0
+    }
+    pub fn topology_change_ack(self) -> bool {
+        self.0 & 0x80 != 0
+    }
+}
+
+// BPDU following LLC header
+#[derive(Debug, Clone, Copy)]
+pub struct Bpdu<T> {
+    inner: T
+}
+
+impl<T> Bpdu<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    const PROTOCOL_ID: Range<usize> = 0..2;
+    const VERSION: usize = 2;
+    const BPDU_TYPE: usize = 3;
+    // configuration BPDU
+    const FLAGS: usize = 4;
+    const ROOT_ID: Range<usize> = 5..13;
+    const ROOT_PATH_COST: Range<usize> = 13..17;
+    const BRIDGE_ID: Range<usize> = 17..25;
+    const PORT_ID: Range<usize> = 25..27;
+    const MESSAGE_AGE: Range<usize> = 27..29;
+    const MAX_AGE: Range<usize> = 29..31;
+    const HELLO_TIME: Range<usize> = 31..33;
+    const FORWARD_DELAY: Range<usize> = 33..35;
+    const CONFIG_END: usize = 35;
+    // RST BPDU
+    const VERSION_1_LEN: usize = 35;
+    const RST_END: usize = 36;
+    // MST BPDU
+    const VERSION_3_LEN: Range<usize> = 36..38;
+    const MST_CONFIG_NAME: Range<usize> = 39..71;
+    const MST_CONFIG_REVISION: Range<usize> = 71..73;
+    const MST_CONFIG_DIGEST: Range<usize> = 73..89;
+    const CIST_INTERNAL_ROOT_PATH_COST: Range<usize> = 89..93;
+    const CIST_BRIDGE_ID: Range<usize> = 93..101;
+    const CIST_REMAINING_HOPS: usize = 101;
+    const MSTI_START: usize = 102;
+}
+
+// timer values are in units of 1/256 second
+fn timer(src: &[u8]) -> Duration {
+    Duration::from_micros(NetworkEndian::read_u16(src) as u64 * 1_000_000 / 256)
+}
+
+impl<T: AsRef<[u8]>> Bpdu<T> {
+    // check length against BPDU type and version; should be used on sniffed data
+    pub fn new_checked(inner: T) -> Option<Self> {
+        let bpdu = Self { inner };
+        let data = bpdu.inner.as_ref();
+        if data.len() < 4 || bpdu.protocol_id() != 0 {
+            return None
+        }
+        let min_len = match (bpdu.bpdu_type(), bpdu.version()) {
+            (BpduType::Tcn, _) => 4,
+            (BpduType::Config, _) => Self::CONFIG_END,
+            (BpduType::Rst, Version::Mstp) if data.len() >= Self::MSTI_START => {
+                Self::VERSION_3_LEN.end + bpdu.version_3_len() as usize
+            },
+            (BpduType::Rst, _) => Self::RST_END,
+            (BpduType::Unknown(_), _) => return None,
+        };
+        if data.len() < min_len {
+            return None
+        }
+        Some(bpdu)
+    }
+    pub fn protocol_id(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::PROTOCOL_ID])
+    }
+    pub fn version(&self) -> Version {
+        self.inner.as_ref()[Self::VERSION].into()
+    }
+    pub fn bpdu_type(&self) -> BpduType {
+        self.inner.as_ref()[Self::BPDU_TYPE].into()
+    }
+    // config BPDUs only use topology change and topology change ack flags
+    pub fn flags(&self) -> Flags {
+        Flags(self.inner.as_ref()[Self::FLAGS])
+    }
+    // CIST root for MSTP
+    pub fn root_id(&self) -> BridgeId {
+        BridgeId::from_bytes(&self.inner.as_ref()[Self::ROOT_ID])
+    }
+    // CIST external root path cost for MSTP
+    pub fn root_path_cost(&self) -> u32 {
+        NetworkEndian::read_u32(&self.inner.as_ref()[Self::ROOT_PATH_COST])
+    }
+    // CIST regional root for MSTP
+    pub fn bridge_id(&self) -> BridgeId {
+        BridgeId::from_bytes(&self.inner.as_ref()[Self::BRIDGE_ID])
+    }
+    // 4-bit priority and 12-bit port number
+    pub fn port_id(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::PORT_ID])
+    }
+    pub fn message_age(&self) -> Duration {
+        timer(&self.inner.as_ref()[Self::MESSAGE_AGE])
+    }
+    pub fn max_age(&self) -> Duration {
+        timer(&self.inner.as_ref()[Self::MAX_AGE])
+    }
+    pub fn hello_time(&self) -> Duration {
+        timer(&self.inner.as_ref()[Self::HELLO_TIME])
+    }
+    pub fn forward_delay(&self) -> Duration {
+        timer(&self.inner.as_ref()[Self::FORWARD_DELAY])
+    }
+    pub fn version_1_len(&self) -> u8 {
+        self.inner.as_ref()[Self::VERSION_1_LEN]
+    }
+    pub fn is_mstp(&self) -> bool {
+        self.bpdu_type() == BpduType::Rst && self.version() == Version::Mstp &&
+            self.inner.as_ref().len() >= Self::MSTI_START
+    }
+    pub fn version_3_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::VERSION_3_LEN])
+    }
+    pub fn mst_config_revision(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::MST_CONFIG_REVISION])
+    }
+    pub fn cist_internal_root_path_cost(&self) -> u32 {
+        NetworkEndian::read_u32(&self.inner.as_ref()[Self::CIST_INTERNAL_ROOT_PATH_COST])
+    }
+    pub fn cist_bridge_id(&self) -> BridgeId {
+        BridgeId::from_bytes(&self.inner.as_ref()[Self::CIST_BRIDGE_ID])
+    }
+    pub fn cist_remaining_hops(&self) -> u8 {
+        self.inner.as_ref()[Self::CIST_REMAINING_HOPS]
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Bpdu<&'a T> {
+    // configuration name, padded with zeros
+    pub fn mst_config_name(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::MST_CONFIG_NAME]
+    }
+    pub fn mst_config_digest(&self) -> &'a [u8] {
+        &self.inner.as_ref()[Self::MST_CONFIG_DIGEST]
+    }
+    // MSTI configuration messages of an MST BPDU
+    pub fn mstis(&self) -> MstiIter<'a> {
+        if !self.is_mstp() {
+            return MstiIter { data: &[] }
+        }
+        let data = self.inner.as_ref();
+        let end = usize::min(Self::VERSION_3_LEN.end + self.version_3_len() as usize, data.len());
+        MstiIter { data: data.get(Self::MSTI_START..end).unwrap_or(&[]) }
+    }
+}
+
+impl<T: AsMut<[u8]>> Bpdu<T> {
+    pub fn set_protocol_id(&mut self, value: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::PROTOCOL_ID], value)
+    }
+    pub fn set_version(&mut self, version: Version) {
+        self.inner.as_mut()[Self::VERSION] = version.into()
+    }
+    pub fn set_bpdu_type(&mut self, ty: BpduType) {
+        self.inner.as_mut()[Self::BPDU_TYPE] = ty.into()
+    }
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.inner.as_mut()[Self::FLAGS] = flags.0
+    }
+    pub fn set_root_id(&mut self, id: BridgeId) {
+        self.inner.as_mut()[Self::ROOT_ID].copy_from_slice(&id.to_bytes())
+    }
+    pub fn set_root_path_cost(&mut self, cost: u32) {
+        NetworkEndian::write_u32(&mut self.inner.as_mut()[Self::ROOT_PATH_COST], cost)
+    }
+    pub fn set_bridge_id(&mut self, id: BridgeId) {
+        self.inner.as_mut()[Self::BRIDGE_ID].copy_from_slice(&id.to_bytes())
+    }
+    pub fn set_port_id(&mut self, port_id: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::PORT_ID], port_id)
+    }
+    // timers are truncated to 1/256 second
+    pub fn set_timers(&mut self, message_age: Duration, max_age: Duration, hello_time: Duration, forward_delay: Duration) {
+        let data = self.inner.as_mut();
+        for (range, value) in [
+            (Self::MESSAGE_AGE, message_age), (Self::MAX_AGE, max_age),
+            (Self::HELLO_TIME, hello_time), (Self::FORWARD_DELAY, forward_delay),
+        ] {
+            let value = (value.as_micros() * 256 / 1_000_000) as u16;
+            NetworkEndian::write_u16(&mut data[range], value);
+        }
+    }
+    pub fn set_version_1_len(&mut self, len: u8) {
+        self.inner.as_mut()[Self::VERSION_1_LEN] = len
+    }
+}
+
+// MSTI configuration message, Section 14.6.1 of IEEE 802.1Q
+#[derive(Debug, Clone, Copy)]
+pub struct Msti<T> {
+    inner: T
+}
+
+impl<T> Msti<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    const FLAGS: usize = 0;
+    const REGIONAL_ROOT_ID: Range<usize> = 1..9;
+    const INTERNAL_ROOT_PATH_COST: Range<usize> = 9..13;
+    const BRIDGE_PRIORITY: usize = 13;
+    const PORT_PRIORITY: usize = 14;
+    const REMAINING_HOPS: usize = 15;
+    const LEN: usize = 16;
+}
+
+impl<T: AsRef<[u8]>> Msti<T> {
+    pub fn flags(&self) -> Flags {
+        Flags(self.inner.as_ref()[Self::FLAGS])
+    }
+    // system ID extension holds the MSTI number
+    pub fn regional_root_id(&self) -> BridgeId {
+        BridgeId::from_bytes(&self.inner.as_ref()[Self::REGIONAL_ROOT_ID])
+    }
+    pub fn msti(&self) -> u16 {
+        self.regional_root_id().system_id_ext()
+    }
+    pub fn internal_root_path_cost(&self) -> u32 {
+        NetworkEndian::read_u32(&self.inner.as_ref()[Self::INTERNAL_ROOT_PATH_COST])
+    }
+    // high 4 bits of bridge priority
+    pub fn bridge_priority(&self) -> u8 {
+        self.inner.as_ref()[Self::BRIDGE_PRIORITY] & 0xf0
+    }
+    // high 4 bits of port priority
+    pub fn port_priority(&self) -> u8 {
+        self.inner.as_ref()[Self::PORT_PRIORITY] & 0xf0
+    }
+    pub fn remaining_hops(&self) -> u8 {
+        self.inner.as_ref()[Self::REMAINING_HOPS]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MstiIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for MstiIter<'a> {
+    type Item = Msti<&'a [u8]>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < Msti::<()>::LEN {
+            return None
+        }
+        let (msti, rest) = self.data.split_at(Msti::<()>::LEN);
+        self.data = rest;
+        Some(Msti::new(msti))
+    }
+}
+
+// find BPDU in a sniffed Ethernet frame
+pub fn parse_frame(frame: &[u8]) -> Option<Bpdu<&[u8]>> {
+    if frame.len() < 14 + 3 {
+        return None
+    }
+    let frame = EthernetFrame::new(frame);
+    let len = usize::min(frame.llc_len()?, frame.payload().len());
+    // length field may claim less than the LLC header
+    if len < 3 {
+        return None
+    }
+    let llc = LlcPacket::new(&frame.payload()[..len]);
+    if llc.dsap() != llc::SAP_STP || llc.ssap() != llc::SAP_STP || llc.control() != llc::CONTROL_UI {
+        return None
+    }
+    Bpdu::new_checked(llc.payload())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    // root bridge announced on the segment differs from previous one
+    RootChanged { old: Option<BridgeId>, new: BridgeId },
+    // a bridge started signalling topology change
+    TopologyChange { bridge: BridgeId },
+    // a bridge sent a topology change notification BPDU towards root
+    TopologyChangeNotification { src_addr: Eui48 },
+}
+
+// watches BPDUs on a segment and reports root and topology changes
+#[derive(Debug, Clone, Default)]
+pub struct Monitor {
+    root: Option<BridgeId>,
+    // last topology change flag seen from each designated bridge
+    topology_change: HashMap<BridgeId, bool>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn root(&self) -> Option<BridgeId> {
+        self.root
+    }
+    pub fn process_frame(&mut self, frame: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        let bpdu = match parse_frame(frame) {
+            Some(bpdu) => bpdu,
+            None => return events,
+        };
+        if bpdu.bpdu_type() == BpduType::Tcn {
+            let src_addr = EthernetFrame::new(frame).src_addr();
+            events.push(Event::TopologyChangeNotification { src_addr });
+            return events
+        }
+        let root = bpdu.root_id();
+        if self.root != Some(root) {
+            events.push(Event::RootChanged { old: self.root, new: root });
+            self.root = Some(root);
+        }
+        let bridge = bpdu.bridge_id();
+        let topology_change = bpdu.flags().topology_change();
+        let previous = self.topology_change.insert(bridge, topology_change);
+        if topology_change && previous != Some(true) {
+            events.push(Event::TopologyChange { bridge });
+        }
+        events
+    }
+    // receive one frame from socket and process it
+    pub fn receive(&mut self, socket: &mut RawSocketDesc, buffer: &mut [u8]) -> io::Result<Vec<Event>> {
+        let len = socket.recv(buffer)?;
+        Ok(self.process_frame(&buffer[..len]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(bpdu: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0u8; 14];
+        let mut frame = EthernetFrame::new(&mut buf);
+        frame.set_dst_addr(MULTICAST_ADDR);
+        frame.set_src_addr("00-15-5d-b7-9f-a9".parse().unwrap());
+        frame.set_ethertype((3 + bpdu.len() as u16).into());
+        buf.extend(&[llc::SAP_STP, llc::SAP_STP, llc::CONTROL_UI]);
+        buf.extend(bpdu);
+        buf
+    }
+
+    fn rst_bpdu(root: BridgeId, flags: u8) -> Vec<u8> {
+        let mut buf = vec![0u8; 36];
+        let mut bpdu = Bpdu::new(&mut buf);
+        bpdu.set_version(Version::Rstp);
+        bpdu.set_bpdu_type(BpduType::Rst);
+        bpdu.set_flags(Flags(flags));
+        bpdu.set_root_id(root);
+        bpdu.set_root_path_cost(20000);
+        bpdu.set_bridge_id(BridgeId::new(0x8000, 1, "00-15-5d-ee-22-75".parse().unwrap()));
+        bpdu.set_port_id(0x8001);
+        bpdu.set_timers(
+            Duration::from_secs(1), Duration::from_secs(20),
+            Duration::from_secs(2), Duration::from_secs(15),
+        );
+        buf
+    }
+
+    #[test]
+    fn stp_rst_bpdu() {
+        let root = BridgeId::new(0x1000, 1, "00-15-5d-b7-9f-a9".parse().unwrap());
+        assert_eq!(root.to_string(), "1001.00155db79fa9");
+        let frame = frame(&rst_bpdu(root, 0x3c));
+        let bpdu = parse_frame(&frame).unwrap();
+        assert_eq!(bpdu.version(), Version::Rstp);
+        assert_eq!(bpdu.root_id(), root);
+        assert_eq!(bpdu.root_id().priority(), 0x1000);
+        assert_eq!(bpdu.root_id().system_id_ext(), 1);
+        assert_eq!(bpdu.root_path_cost(), 20000);
+        assert_eq!(bpdu.port_id(), 0x8001);
+        assert_eq!(bpdu.flags().port_role(), PortRole::Designated);
+        assert!(bpdu.flags().learning() && bpdu.flags().forwarding());
+        assert!(!bpdu.flags().topology_change());
+        assert_eq!(bpdu.max_age(), Duration::from_secs(20));
+        assert_eq!(bpdu.hello_time(), Duration::from_secs(2));
+        assert_eq!(bpdu.mstis().count(), 0);
+        // length field shorter than the LLC header
+        let mut short = frame.clone();
+        for len in 0..3u8 {
+            short[12..14].copy_from_slice(&[0, len]);
+            assert!(parse_frame(&short).is_none());
+        }
+    }
+
+    #[test]
+    fn stp_mst_bpdu() {
+        let root = BridgeId::new(0x1000, 0, "00-15-5d-b7-9f-a9".parse().unwrap());
+        let mut data = rst_bpdu(root, 0x3c);
+        data[Bpdu::<()>::VERSION] = 3;
+        data.resize(Bpdu::<()>::MSTI_START + 2 * 16, 0);
+        NetworkEndian::write_u16(&mut data[Bpdu::<()>::VERSION_3_LEN], 64 + 2 * 16);
+        data[Bpdu::<()>::CIST_REMAINING_HOPS] = 20;
+        let msti = BridgeId::new(0x8000, 2, "00-15-5d-ee-22-75".parse().unwrap());
+        data[Bpdu::<()>::MSTI_START + 16 + 1..][..8].copy_from_slice(&msti.to_bytes());
+        let frame = frame(&data);
+        let bpdu = parse_frame(&frame).unwrap();
+        assert!(bpdu.is_mstp());
+        assert_eq!(bpdu.cist_remaining_hops(), 20);
+        let mstis = bpdu.mstis().collect::<Vec<_>>();
+        assert_eq!(mstis.len(), 2);
+        assert_eq!(mstis[1].msti(), 2);
+        assert_eq!(mstis[1].regional_root_id(), msti);
+    }
+
+    #[test]
+    fn stp_monitor() {
+        let mut monitor = Monitor::new();
+        let root1 = BridgeId::new(0x8000, 0, "00-15-5d-b7-9f-a9".parse().unwrap());
+        let root2 = BridgeId::new(0x1000, 0, "00-15-5d-b7-9f-a9".parse().unwrap());
+        assert_eq!(monitor.process_frame(&frame(&rst_bpdu(root1, 0))),
+            vec![Event::RootChanged { old: None, new: root1 }]);
+        assert_eq!(monitor.process_frame(&frame(&rst_bpdu(root1, 0))), vec![]);
+        let events = monitor.process_frame(&frame(&rst_bpdu(root2, 0x01)));
+        assert_eq!(events[0], Event::RootChanged { old: Some(root1), new: root2 });
+        assert!(matches!(events[1], Event::TopologyChange { .. }));
+        assert_eq!(monitor.process_frame(&frame(&rst_bpdu(root2, 0x01))), vec![]);
+        let events = monitor.process_frame(&frame(&[0, 0, 0, 0x80]));
+        assert!(matches!(events[..], [Event::TopologyChangeNotification { .. }]));
+    }
+}
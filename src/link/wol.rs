@@ -0,0 +1,199 @@
+// Wake-on-LAN magic packet: six 0xFF bytes followed by sixteen repetitions
+// of target MAC address, optionally followed by a 4 or 6 byte SecureOn password
+use super::eui::Eui48;
+use super::ether::{Frame as EthernetFrame, Type as EthernetProtocol};
+use crate::packet_write::{PacketWrite, PacketWriter};
+use crate::raw_socket_sys::RawSocketDesc;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+const SYNC_STREAM: [u8; 6] = [0xff; 6];
+const REPETITIONS: usize = 16;
+const MAGIC_LEN: usize = 6 + 6 * REPETITIONS;
+
+// usual destination of UDP magic packets, port 7 is also used
+pub const UDP_PORT: u16 = 9;
+
+// magic packet found in a payload
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<T> {
+    inner: T
+}
+
+impl<T> Packet<T> {
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    // check that data starts with a magic packet
+    pub fn new_checked(inner: T) -> Option<Self> {
+        let data = inner.as_ref();
+        if data.len() < MAGIC_LEN || data[..6] != SYNC_STREAM {
+            return None
+        }
+        let target = &data[6..12];
+        if !data[6..MAGIC_LEN].chunks(6).all(|chunk| chunk == target) {
+            return None
+        }
+        Some(Self { inner })
+    }
+    pub fn target(&self) -> Eui48 {
+        Eui48::from_bytes(&self.inner.as_ref()[6..12])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    // SecureOn password, if the packet is exactly followed by 4 or 6 bytes
+    pub fn password(&self) -> Option<&'a [u8]> {
+        let rest = &self.inner.as_ref()[MAGIC_LEN..];
+        match rest.len() {
+            4 | 6 => Some(rest),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Packet<&'a [u8]> {
+    // magic packet can be anywhere in the payload of any protocol,
+    // e.g. raw 0x0842 frames or UDP datagrams
+    pub fn find(data: &'a [u8]) -> Option<Self> {
+        (0..data.len().saturating_sub(MAGIC_LEN - 1))
+            .find_map(|start| Self::new_checked(&data[start..]))
+            .map(|packet| {
+                // do not take trailing bytes such as Ethernet padding as password
+                let data = packet.inner;
+                let len = match data.len() - MAGIC_LEN {
+                    len @ (4 | 6) => MAGIC_LEN + len,
+                    _ => MAGIC_LEN,
+                };
+                Self { inner: &data[..len] }
+            })
+    }
+}
+
+// find magic packet in a sniffed Ethernet frame
+pub fn parse_frame(frame: &[u8]) -> Option<Packet<&[u8]>> {
+    if frame.len() < 14 {
+        return None
+    }
+    Packet::find(EthernetFrame::new(frame).payload())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Writer {
+    target: Eui48,
+    password: Option<Vec<u8>>,
+    byte_idx: usize,
+}
+
+impl Writer {
+    pub fn new(target: Eui48) -> Writer {
+        Writer { target, password: None, byte_idx: 0 }
+    }
+    // SecureOn password must be 4 or 6 bytes, panics otherwise
+    pub fn secure_on(target: Eui48, password: &[u8]) -> Writer {
+        assert!(matches!(password.len(), 4 | 6), "SecureOn password must be 4 or 6 bytes");
+        Writer { target, password: Some(password.to_vec()), byte_idx: 0 }
+    }
+    pub fn len(&self) -> usize {
+        MAGIC_LEN + self.password.as_ref().map_or(0, Vec::len)
+    }
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+    #[must_use]
+    pub fn write(&mut self, buffer: &mut [u8]) -> usize {
+        let mut write = PacketWrite::new(buffer, self.byte_idx);
+        write.write_slice_at(0.., &SYNC_STREAM);
+        for idx in 0..REPETITIONS {
+            write.write_slice_at(6 + idx * 6.., self.target.as_bytes());
+        }
+        if let Some(password) = &self.password {
+            write.write_slice_at(MAGIC_LEN.., password);
+        }
+        self.byte_idx = write.buffer_index();
+        write.bytes_written()
+    }
+    fn to_vec(&self) -> Vec<u8> {
+        let mut writer = Writer { byte_idx: 0, ..self.clone() };
+        let mut buf = vec![0u8; writer.len()];
+        let _ = writer.write(&mut buf);
+        buf
+    }
+    // send as raw EtherType 0x0842 broadcast frame; socket must be bound
+    pub fn send_raw(&self, socket: &mut RawSocketDesc, src_addr: Eui48) -> io::Result<usize> {
+        // pad to minimum Ethernet frame length
+        let mut buf = vec![0u8; usize::max(14 + self.len(), 60)];
+        let mut frame = EthernetFrame::new(&mut buf);
+        frame.set_dst_addr(Eui48::BROADCAST);
+        frame.set_src_addr(src_addr);
+        frame.set_ethertype(EthernetProtocol::WakeOnLan);
+        let _ = Writer { byte_idx: 0, ..self.clone() }.write(frame.payload_mut());
+        socket.send(&buf)
+    }
+    // send as UDP datagram, e.g. to a directed broadcast address
+    pub fn send_udp(&self, addr: SocketAddr) -> io::Result<usize> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        socket.send_to(&self.to_vec(), addr)
+    }
+    // send to limited broadcast address on UDP_PORT
+    pub fn send_udp_broadcast(&self) -> io::Result<usize> {
+        self.send_udp((Ipv4Addr::BROADCAST, UDP_PORT).into())
+    }
+}
+
+impl PacketWriter for Writer {
+    fn packet_len(&self) -> usize {
+        self.len()
+    }
+    fn write_packet(&mut self, buffer: &mut [u8]) -> usize {
+        self.write(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wol_write_parse() {
+        let target: Eui48 = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let bytes = Writer::new(target).to_vec();
+        assert_eq!(bytes.len(), 102);
+        assert_eq!(&bytes[..6], &[0xff; 6]);
+        assert_eq!(&bytes[96..], target.as_bytes());
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.target(), target);
+        assert_eq!(packet.password(), None);
+        let bytes = Writer::secure_on(target, &[1, 2, 3, 4]).to_vec();
+        assert_eq!(bytes.len(), 106);
+        assert_eq!(Packet::new_checked(&bytes[..]).unwrap().password(), Some(&[1, 2, 3, 4][..]));
+        let bytes = Writer::secure_on(target, &[1, 2, 3, 4, 5, 6]).to_vec();
+        assert_eq!(Packet::new_checked(&bytes[..]).unwrap().password(), Some(&[1, 2, 3, 4, 5, 6][..]));
+        let mut broken = bytes.clone();
+        broken[50] ^= 1;
+        assert!(Packet::new_checked(&broken[..]).is_none());
+    }
+
+    #[test]
+    fn wol_find_in_frame() {
+        let target: Eui48 = "00-15-5d-b7-9f-a9".parse().unwrap();
+        // UDP-like payload with some header bytes in front and padding behind
+        let mut frame = vec![0u8; 14 + 28];
+        frame.extend(Writer::new(target).to_vec());
+        frame.extend(&[0u8; 3]);
+        let packet = parse_frame(&frame).unwrap();
+        assert_eq!(packet.target(), target);
+        assert_eq!(packet.password(), None);
+        assert!(parse_frame(&frame[..100]).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn wol_bad_password_len() {
+        let _ = Writer::secure_on("00-15-5d-b7-9f-a9".parse().unwrap(), &[1, 2, 3, 4, 5]);
+    }
+}
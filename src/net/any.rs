@@ -0,0 +1,329 @@
+// Version-agnostic address, CIDR and packet wrappers for dual-stack code
+use super::ip::{self, Protocol};
+use super::ipv4::{self, parse_prefix};
+use core::fmt;
+use core::str::FromStr;
+use std::net::IpAddr;
+
+// IPv4 or IPv6 address, IPv4 addresses sort first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Address {
+    Ipv4(ipv4::Address),
+    Ipv6(ip::Address),
+}
+
+impl Address {
+    pub fn version(self) -> u8 {
+        match self {
+            Address::Ipv4(_) => 4,
+            Address::Ipv6(_) => 6,
+        }
+    }
+    pub fn is_ipv4(self) -> bool {
+        matches!(self, Address::Ipv4(_))
+    }
+    pub fn is_ipv6(self) -> bool {
+        matches!(self, Address::Ipv6(_))
+    }
+    pub fn is_unspecified(self) -> bool {
+        match self {
+            Address::Ipv4(address) => address.is_unspecified(),
+            Address::Ipv6(address) => address.is_unspecified(),
+        }
+    }
+    pub fn is_loopback(self) -> bool {
+        match self {
+            Address::Ipv4(address) => address.is_loopback(),
+            Address::Ipv6(address) => address.is_loopback(),
+        }
+    }
+    pub fn is_multicast(self) -> bool {
+        match self {
+            Address::Ipv4(address) => address.is_multicast(),
+            Address::Ipv6(address) => address.is_multicast(),
+        }
+    }
+    // 4 or 16 bytes in network order
+    pub fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Address::Ipv4(address) => address.octets().to_vec(),
+            Address::Ipv6(address) => address.octets().to_vec(),
+        }
+    }
+}
+
+impl From<ipv4::Address> for Address {
+    fn from(src: ipv4::Address) -> Self {
+        Address::Ipv4(src)
+    }
+}
+
+impl From<ip::Address> for Address {
+    fn from(src: ip::Address) -> Self {
+        Address::Ipv6(src)
+    }
+}
+
+impl From<IpAddr> for Address {
+    fn from(src: IpAddr) -> Self {
+        match src {
+            IpAddr::V4(address) => Address::Ipv4(address.into()),
+            IpAddr::V6(address) => Address::Ipv6(address.into()),
+        }
+    }
+}
+
+impl From<Address> for IpAddr {
+    fn from(src: Address) -> Self {
+        match src {
+            Address::Ipv4(address) => IpAddr::V4(address.into()),
+            Address::Ipv6(address) => IpAddr::V6(address.into()),
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Ipv4(address) => address.fmt(f),
+            Address::Ipv6(address) => address.fmt(f),
+        }
+    }
+}
+
+impl FromStr for Address {
+    type Err = ParseError;
+    // IPv6 addresses always contain a colon
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(':') {
+            s.parse().map(Address::Ipv6).map_err(|_| ParseError(()))
+        } else {
+            s.parse().map(Address::Ipv4).map_err(|_| ParseError(()))
+        }
+    }
+}
+
+/// IP address or CIDR parse error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseError(());
+
+// IPv4 or IPv6 subnet, ordered by network then prefix
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Cidr {
+    Ipv4(ipv4::Subnet),
+    Ipv6(ip::Subnet),
+}
+
+impl Cidr {
+    // None if prefix is too long or host bits are set
+    pub fn new(network: Address, prefix: u8) -> Option<Cidr> {
+        let cidr = Cidr::from_address(network, prefix)?;
+        if cidr.network() != network {
+            return None
+        }
+        Some(cidr)
+    }
+    // subnet containing address, host bits cleared
+    pub fn from_address(address: Address, prefix: u8) -> Option<Cidr> {
+        match address {
+            Address::Ipv4(address) if prefix <= 32 => Some(Cidr::Ipv4(ipv4::Subnet::from_address(address, prefix))),
+            Address::Ipv6(address) if prefix <= 128 => Some(Cidr::Ipv6(ip::Subnet::from_address(address, prefix))),
+            _ => None,
+        }
+    }
+    pub fn version(self) -> u8 {
+        self.network().version()
+    }
+    pub fn network(self) -> Address {
+        match self {
+            Cidr::Ipv4(subnet) => Address::Ipv4(subnet.network()),
+            Cidr::Ipv6(subnet) => Address::Ipv6(subnet.network()),
+        }
+    }
+    pub fn prefix(self) -> u8 {
+        match self {
+            Cidr::Ipv4(subnet) => subnet.prefix(),
+            Cidr::Ipv6(subnet) => subnet.prefix(),
+        }
+    }
+    // addresses of the other family never match
+    pub fn contains(self, address: Address) -> bool {
+        match (self, address) {
+            (Cidr::Ipv4(subnet), Address::Ipv4(address)) => subnet.contains(address),
+            (Cidr::Ipv6(subnet), Address::Ipv6(address)) => subnet.contains(address),
+            _ => false,
+        }
+    }
+    pub fn contains_cidr(self, other: Cidr) -> bool {
+        self.prefix() <= other.prefix() && self.contains(other.network())
+    }
+}
+
+impl From<ipv4::Subnet> for Cidr {
+    fn from(src: ipv4::Subnet) -> Self {
+        Cidr::Ipv4(src)
+    }
+}
+
+impl From<ip::Subnet> for Cidr {
+    fn from(src: ip::Subnet) -> Self {
+        Cidr::Ipv6(src)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network(), self.prefix())
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ParseError;
+    // host bits must be zero
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (network, prefix) = s.split_once('/').ok_or(ParseError(()))?;
+        let network: Address = network.parse()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = parse_prefix(prefix, max).ok_or(ParseError(()))?;
+        Cidr::new(network, prefix).ok_or(ParseError(()))
+    }
+}
+
+// IPv4 or IPv6 header, chosen by the version nibble
+#[derive(Debug, Clone, Copy)]
+pub enum Packet<T> {
+    Ipv4(ipv4::Packet<T>),
+    Ipv6(ip::Packet<T>),
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    // None for other versions or data shorter than the fixed header
+    pub fn new_checked(inner: T) -> Option<Self> {
+        let data = inner.as_ref();
+        if data.is_empty() {
+            return None
+        }
+        match ip::Packet::new(data).version() {
+            4 => ipv4::Packet::new_checked(inner).map(Packet::Ipv4),
+            6 if data.len() >= 40 => Some(Packet::Ipv6(ip::Packet::new(inner))),
+            _ => None,
+        }
+    }
+    pub fn version(&self) -> u8 {
+        match self {
+            Packet::Ipv4(_) => 4,
+            Packet::Ipv6(_) => 6,
+        }
+    }
+    pub fn src_addr(&self) -> Address {
+        match self {
+            Packet::Ipv4(packet) => packet.src_addr().into(),
+            Packet::Ipv6(packet) => packet.src_addr().into(),
+        }
+    }
+    pub fn dst_addr(&self) -> Address {
+        match self {
+            Packet::Ipv4(packet) => packet.dst_addr().into(),
+            Packet::Ipv6(packet) => packet.dst_addr().into(),
+        }
+    }
+    // TTL of IPv4, hop limit of IPv6
+    pub fn hop_limit(&self) -> u8 {
+        match self {
+            Packet::Ipv4(packet) => packet.ttl(),
+            Packet::Ipv6(packet) => packet.hop_limit(),
+        }
+    }
+    // protocol field of IPv4, first next header of IPv6
+    pub fn next_header(&self) -> Protocol {
+        match self {
+            Packet::Ipv4(packet) => packet.protocol(),
+            Packet::Ipv6(packet) => packet.next_header(),
+        }
+    }
+    pub fn total_len(&self) -> usize {
+        match self {
+            Packet::Ipv4(packet) => packet.total_len() as usize,
+            Packet::Ipv6(packet) => packet.total_len(),
+        }
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    // upper-layer protocol and data; IPv6 extension headers are skipped,
+    // malformed chains give None
+    pub fn upper_layer(&self) -> Option<(Protocol, &'a [u8])> {
+        match self {
+            Packet::Ipv4(packet) => Some((packet.protocol(), packet.payload())),
+            Packet::Ipv6(packet) => packet.upper_layer().ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_address_parse_cmp() {
+        let v4: Address = "192.0.2.1".parse().unwrap();
+        let v6: Address = "2001:db8::1".parse().unwrap();
+        assert_eq!(v4, Address::Ipv4(ipv4::Address::new(192, 0, 2, 1)));
+        assert!(v6.is_ipv6() && v6.version() == 6);
+        assert!(v4 < v6);
+        assert!("192.0.2.1".parse::<Address>().unwrap() < "192.0.2.2".parse().unwrap());
+        assert!("::2".parse::<Address>().unwrap() > "::1".parse().unwrap());
+        assert_eq!(v4.to_string(), "192.0.2.1");
+        assert!("192.0.2".parse::<Address>().is_err());
+        assert!("::1::".parse::<Address>().is_err());
+        assert!("ff02::1".parse::<Address>().unwrap().is_multicast());
+        let std_v4: std::net::IpAddr = "192.0.2.1".parse().unwrap();
+        assert_eq!(Address::from(std_v4), v4);
+        assert_eq!(std::net::IpAddr::from(v6), "2001:db8::1".parse::<std::net::IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn any_cidr_contains() {
+        let v4: Cidr = "10.0.0.0/8".parse().unwrap();
+        let v6: Cidr = "fe80::/10".parse().unwrap();
+        assert!(v4.contains("10.1.2.3".parse().unwrap()));
+        assert!(!v4.contains("::a01:203".parse().unwrap()));
+        assert!(v6.contains("febf::1".parse().unwrap()));
+        assert!(!v6.contains("fec0::1".parse().unwrap()));
+        assert!(v6.contains_cidr("fe80::/64".parse().unwrap()));
+        assert!(!v6.contains_cidr(v4));
+        assert_eq!(Cidr::from(ip::Subnet::LINK_LOCAL_UNICAST), v6);
+        assert_eq!(Cidr::from_address("10.1.2.3".parse().unwrap(), 16).unwrap().to_string(), "10.1.0.0/16");
+        assert!("fe80::1/10".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("::/129".parse::<Cidr>().is_err());
+        assert!("::/0".parse::<Cidr>().unwrap().contains("2001:db8::1".parse().unwrap()));
+        let mut cidrs = [v6, "10.0.0.0/16".parse().unwrap(), v4];
+        cidrs.sort();
+        assert_eq!(cidrs.iter().map(|cidr| cidr.prefix()).collect::<Vec<_>>(), [8, 16, 10]);
+    }
+
+    #[test]
+    fn any_packet_dispatch() {
+        let v4 = [
+            0x45, 0x00, 0x00, 0x18, 0x00, 0x00, 0x40, 0x00, 0x40, 0x01, 0x00, 0x00,
+            0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7, 0x08, 0x00, 0xf7, 0xff,
+        ];
+        let packet = Packet::new_checked(&v4[..]).unwrap();
+        assert_eq!(packet.version(), 4);
+        assert_eq!(packet.src_addr(), "192.168.0.1".parse().unwrap());
+        assert_eq!(packet.upper_layer(), Some((Protocol::Icmp, &v4[20..])));
+        let mut v6 = [0u8; 48];
+        let mut ip = ip::Packet::new(&mut v6[..]);
+        ip.set_version(6);
+        ip.set_payload_len(8);
+        ip.set_next_header(Protocol::Icmpv6);
+        ip.set_dst_addr("ff02::1".parse().unwrap());
+        let packet = Packet::new_checked(&v6[..]).unwrap();
+        assert_eq!(packet.version(), 6);
+        assert!(packet.dst_addr().is_multicast());
+        assert_eq!(packet.upper_layer(), Some((Protocol::Icmpv6, &v6[40..])));
+        assert!(Packet::new_checked(&v6[..39]).is_none());
+        assert!(Packet::new_checked(&[0x50u8; 40][..]).is_none());
+    }
+}
@@ -0,0 +1,165 @@
+// Internet checksum, RFC 1071, with incremental update of RFC 1624
+use super::checksum_simd;
+use super::ip::{Address, Protocol};
+
+pub use super::checksum_simd::Implementation;
+
+// running one's complement sum, data may be added in chunks of any length
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Checksum {
+    sum: u64,
+    // previous chunk ended in the middle of a 16-bit word
+    odd: bool,
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add(&mut self, mut data: &[u8]) {
+        if data.is_empty() {
+            return
+        }
+        if self.odd {
+            self.sum += data[0] as u64;
+            data = &data[1..];
+            self.odd = false;
+        }
+        self.sum += checksum_simd::sum(data) as u64;
+        self.odd = !data.len().is_multiple_of(2);
+    }
+    // word-aligned values, such as header fields
+    pub fn add_u16(&mut self, value: u16) {
+        self.add(&value.to_be_bytes())
+    }
+    pub fn add_u32(&mut self, value: u32) {
+        self.add(&value.to_be_bytes())
+    }
+    // IPv6 pseudo-header, Section 8.1 of RFC 8200
+    pub fn add_pseudo_header(&mut self, src_addr: Address, dst_addr: Address, upper_len: u32, next_header: Protocol) {
+        self.add(&src_addr.octets());
+        self.add(&dst_addr.octets());
+        self.add_u32(upper_len);
+        self.add_u32(u8::from(next_header) as u32);
+    }
+    pub fn merge(&mut self, other: Checksum) {
+        let mut sum = other.sum;
+        // the other sum was taken at odd offset, so its bytes are swapped
+        if self.odd {
+            sum = fold(sum).swap_bytes() as u64;
+        }
+        self.sum += sum;
+        self.odd ^= other.odd;
+    }
+    // folded sum, 0xffff when the data includes a valid checksum
+    pub fn sum(&self) -> u16 {
+        fold(self.sum)
+    }
+    // value for the checksum field
+    pub fn finish(&self) -> u16 {
+        !self.sum()
+    }
+}
+
+fn fold(mut sum: u64) -> u16 {
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+// checksum field value for data, with the field itself zeroed
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum = Checksum::new();
+    sum.add(data);
+    sum.finish()
+}
+
+pub fn pseudo_header(src_addr: Address, dst_addr: Address, upper_len: u32, next_header: Protocol) -> Checksum {
+    let mut sum = Checksum::new();
+    sum.add_pseudo_header(src_addr, dst_addr, upper_len, next_header);
+    sum
+}
+
+// checksum after a 16-bit word changed from old to new, equation 3 of RFC 1624
+pub fn update(checksum: u16, old: u16, new: u16) -> u16 {
+    let sum = (!checksum) as u64 + (!old) as u64 + new as u64;
+    !fold(sum)
+}
+
+// checksum after a word-aligned field changed, e.g. an address rewrite;
+// old and new must have the same even length
+pub fn update_slice(checksum: u16, old: &[u8], new: &[u8]) -> u16 {
+    assert!(old.len() == new.len() && old.len().is_multiple_of(2));
+    let mut sum = (!checksum) as u64;
+    for (old, new) in old.chunks_exact(2).zip(new.chunks_exact(2)) {
+        sum += (!u16::from_be_bytes([old[0], old[1]])) as u64;
+        sum += u16::from_be_bytes([new[0], new[1]]) as u64;
+    }
+    !fold(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_rfc1071_example() {
+        // Section 3 of RFC 1071
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        let mut sum = Checksum::new();
+        sum.add(&data);
+        assert_eq!(sum.sum(), 0xddf2);
+        assert_eq!(checksum(&data), !0xddf2);
+        assert_eq!(checksum(&[]), 0xffff);
+        assert_eq!(checksum(&[0xab]), !0xab00);
+    }
+
+    #[test]
+    fn checksum_chunked() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1001).map(|b| b.wrapping_mul(31)).collect();
+        let whole = checksum(&data);
+        for split in &[1, 2, 3, 5, 7, 64, 999] {
+            let mut sum = Checksum::new();
+            for chunk in data.chunks(*split) {
+                sum.add(chunk);
+            }
+            assert_eq!(sum.finish(), whole, "chunks of {}", split);
+        }
+        for split in 0..data.len() {
+            let (mut head, mut tail) = (Checksum::new(), Checksum::new());
+            head.add(&data[..split]);
+            tail.add(&data[split..]);
+            head.merge(tail);
+            assert_eq!(head.finish(), whole, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn checksum_incremental() {
+        let mut data: Vec<u8> = (0..40u8).map(|b| b.wrapping_mul(97)).collect();
+        let before = checksum(&data);
+        let old = u16::from_be_bytes([data[8], data[9]]);
+        data[8..10].copy_from_slice(&[0x12, 0x34]);
+        assert_eq!(update(before, old, 0x1234), checksum(&data));
+
+        let old: Address = "fe80::1".parse().unwrap();
+        let new: Address = "2001:db8::abcd".parse().unwrap();
+        let mut packet = old.octets().to_vec();
+        packet.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let before = checksum(&packet);
+        packet[..16].copy_from_slice(&new.octets());
+        assert_eq!(update_slice(before, &old.octets(), &new.octets()), checksum(&packet));
+    }
+
+    #[test]
+    fn checksum_pseudo_header() {
+        let src: Address = "fe80::1".parse().unwrap();
+        let dst: Address = "fe80::2".parse().unwrap();
+        let mut layout = Vec::new();
+        layout.extend_from_slice(&src.octets());
+        layout.extend_from_slice(&dst.octets());
+        layout.extend_from_slice(&[0, 0, 0, 8, 0, 0, 0, 58]);
+        assert_eq!(pseudo_header(src, dst, 8, Protocol::Icmpv6).finish(), checksum(&layout));
+    }
+}
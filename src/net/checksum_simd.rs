@@ -0,0 +1,201 @@
+// Vectorised one's complement sums for net::checksum
+//
+// All paths read words little-endian and swap the folded result, which gives
+// the same sum as big-endian words, Section 2 (B) of RFC 1071.
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Implementation {
+    // 16-bit words, the reference
+    Scalar,
+    // 64-bit words with end-around carry, any target
+    Portable,
+    Sse2,
+    Avx2,
+}
+
+impl Implementation {
+    pub const ALL: [Implementation; 4] =
+        [Implementation::Scalar, Implementation::Portable, Implementation::Sse2, Implementation::Avx2];
+
+    // fastest one this CPU supports, detected once
+    pub fn detect() -> Implementation {
+        static DETECTED: OnceLock<Implementation> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            if Implementation::Avx2.is_available() {
+                Implementation::Avx2
+            } else if Implementation::Sse2.is_available() {
+                Implementation::Sse2
+            } else {
+                Implementation::Portable
+            }
+        })
+    }
+    pub fn is_available(self) -> bool {
+        match self {
+            Implementation::Scalar | Implementation::Portable => true,
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(not(target_arch = "x86_64"))]
+            Implementation::Sse2 | Implementation::Avx2 => false,
+        }
+    }
+    // folded sum of data as big-endian words, a trailing odd byte is padded with zero;
+    // panics if the implementation is not available
+    pub fn sum(self, data: &[u8]) -> u16 {
+        assert!(self.is_available(), "{:?} not supported by this CPU", self);
+        match self {
+            Implementation::Scalar => sum_scalar(data),
+            Implementation::Portable => fold(sum_portable(data)).swap_bytes(),
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Sse2 => fold(unsafe { sum_sse2(data) }).swap_bytes(),
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Avx2 => fold(unsafe { sum_avx2(data) }).swap_bytes(),
+            #[cfg(not(target_arch = "x86_64"))]
+            Implementation::Sse2 | Implementation::Avx2 => unreachable!(),
+        }
+    }
+}
+
+// with the implementation detected for this CPU
+pub fn sum(data: &[u8]) -> u16 {
+    Implementation::detect().sum(data)
+}
+
+fn fold(mut sum: u64) -> u16 {
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+fn add_carry(a: u64, b: u64) -> u64 {
+    let (sum, carry) = a.overflowing_add(b);
+    sum + carry as u64
+}
+
+fn sum_scalar(data: &[u8]) -> u16 {
+    let mut sum = 0u64;
+    for chunk in data.chunks(2) {
+        sum += match chunk {
+            [hi, lo] => u16::from_be_bytes([*hi, *lo]) as u64,
+            [hi] => (*hi as u64) << 8,
+            _ => unreachable!(),
+        };
+    }
+    fold(sum)
+}
+
+fn sum_portable(data: &[u8]) -> u64 {
+    let mut sum = 0u64;
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        sum = add_carry(sum, u64::from_le_bytes(word));
+    }
+    let mut word = [0u8; 8];
+    word[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    add_carry(sum, u64::from_le_bytes(word))
+}
+
+// 32-bit words widened into 64-bit lanes, which cannot overflow for any slice
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn sum_sse2(data: &[u8]) -> u64 {
+    let zero = _mm_setzero_si128();
+    let mut acc = _mm_setzero_si128();
+    let mut chunks = data.chunks_exact(16);
+    for chunk in &mut chunks {
+        let v = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+        acc = _mm_add_epi64(acc, _mm_unpacklo_epi32(v, zero));
+        acc = _mm_add_epi64(acc, _mm_unpackhi_epi32(v, zero));
+    }
+    let mut lanes = [0u64; 2];
+    _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, acc);
+    add_carry(add_carry(lanes[0], lanes[1]), sum_portable(chunks.remainder()))
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn sum_avx2(data: &[u8]) -> u64 {
+    let zero = _mm256_setzero_si256();
+    let (mut acc0, mut acc1) = (_mm256_setzero_si256(), _mm256_setzero_si256());
+    let mut chunks = data.chunks_exact(64);
+    // two independent accumulators keep both adders busy
+    for chunk in &mut chunks {
+        let v0 = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+        let v1 = _mm256_loadu_si256(chunk.as_ptr().add(32) as *const __m256i);
+        acc0 = _mm256_add_epi64(acc0, _mm256_unpacklo_epi32(v0, zero));
+        acc1 = _mm256_add_epi64(acc1, _mm256_unpackhi_epi32(v0, zero));
+        acc0 = _mm256_add_epi64(acc0, _mm256_unpacklo_epi32(v1, zero));
+        acc1 = _mm256_add_epi64(acc1, _mm256_unpackhi_epi32(v1, zero));
+    }
+    let mut lanes = [0u64; 8];
+    _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, acc0);
+    _mm256_storeu_si256(lanes.as_mut_ptr().add(4) as *mut __m256i, acc1);
+    let sum = lanes.iter().fold(0, |sum, &lane| add_carry(sum, lane));
+    add_carry(sum, sum_sse2(chunks.remainder()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift64*, so failures are reproducible without extra crates
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn available() -> Vec<Implementation> {
+        Implementation::ALL.iter().copied().filter(|imp| imp.is_available()).collect()
+    }
+
+    #[test]
+    fn checksum_simd_matches_scalar() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let mut buffer = vec![0u8; 4096 + 64];
+        for round in 0..2000 {
+            // every few rounds saturate the buffer to stress end-around carries
+            let fill = match round % 4 {
+                0 => Some(0xff),
+                1 => Some(0x00),
+                _ => None,
+            };
+            for byte in buffer.iter_mut() {
+                *byte = fill.unwrap_or(rng.next() as u8);
+            }
+            let offset = rng.below(64);
+            let len = if round % 2 == 0 { rng.below(256) } else { rng.below(4096) };
+            let data = &buffer[offset..offset + len];
+            let expected = Implementation::Scalar.sum(data);
+            for imp in available() {
+                assert_eq!(imp.sum(data), expected, "{:?}, offset {}, len {}", imp, offset, len);
+            }
+        }
+    }
+
+    #[test]
+    fn checksum_simd_large() {
+        let data = vec![0xffu8; (1 << 20) + 3];
+        for imp in available() {
+            assert_eq!(imp.sum(&data), Implementation::Scalar.sum(&data), "{:?}", imp);
+        }
+        assert!(Implementation::detect().is_available());
+        assert_eq!(sum(&[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7]), 0xddf2);
+    }
+}
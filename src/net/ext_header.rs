@@ -0,0 +1,432 @@
+// IPv6 extension headers: See https://tools.ietf.org/html/rfc8200#section-4
+use super::ip::Protocol;
+use byteorder::{ByteOrder, NetworkEndian};
+
+// longest chain walked before giving up; RFC 8200 recommends at most one of each
+// header (destination options twice), so anything longer is suspicious
+pub const MAX_EXT_HEADERS: usize = 8;
+
+// Hop-by-Hop Options and Destination Options headers, Section 4.3 and 4.6
+#[derive(Debug, Clone, Copy)]
+pub struct Options<T> {
+    inner: T
+}
+
+impl<T> Options<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Options<T> {
+    pub fn next_header(&self) -> Protocol {
+        self.inner.as_ref()[0].into()
+    }
+    // whole header length in bytes
+    pub fn header_len(&self) -> usize {
+        (self.inner.as_ref()[1] as usize + 1) * 8
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Options<&'a T> {
+    pub fn options(&self) -> OptionIter<'a> {
+        OptionIter { data: &self.inner.as_ref()[2..] }
+    }
+}
+
+impl<T: AsMut<[u8]>> Options<T> {
+    pub fn set_next_header(&mut self, protocol: Protocol) {
+        self.inner.as_mut()[0] = protocol.into()
+    }
+    // header length in bytes, must be a multiple of 8
+    pub fn set_header_len(&mut self, len: usize) {
+        debug_assert!(len >= 8 && len.is_multiple_of(8) && len <= 2048);
+        self.inner.as_mut()[1] = (len / 8 - 1) as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opt<'a> {
+    pub option_type: OptionType,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct OptionIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = Opt<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let option_type = OptionType::from(*self.data.first()?);
+        if option_type == OptionType::Pad1 {
+            self.data = &self.data[1..];
+            return Some(Opt { option_type, data: &[] })
+        }
+        let len = *self.data.get(1)? as usize;
+        if self.data.len() < 2 + len {
+            self.data = &[];
+            return None
+        }
+        let data = &self.data[2..2 + len];
+        self.data = &self.data[2 + len..];
+        Some(Opt { option_type, data })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionType {
+    Pad1, // 0x00
+    PadN, // 0x01
+    RouterAlert, // 0x05
+    JumboPayload, // 0xC2
+    Unknown(u8),
+}
+
+impl From<u8> for OptionType {
+    fn from(src: u8) -> Self {
+        match src {
+            0x00 => OptionType::Pad1,
+            0x01 => OptionType::PadN,
+            0x05 => OptionType::RouterAlert,
+            0xC2 => OptionType::JumboPayload,
+            others => OptionType::Unknown(others),
+        }
+    }
+}
+
+impl From<OptionType> for u8 {
+    fn from(src: OptionType) -> u8 {
+        match src {
+            OptionType::Pad1 => 0x00,
+            OptionType::PadN => 0x01,
+            OptionType::RouterAlert => 0x05,
+            OptionType::JumboPayload => 0xC2,
+            OptionType::Unknown(others) => others
+        }
+    }
+}
+
+// Routing header, Section 4.4; type specific data is left to the caller
+#[derive(Debug, Clone, Copy)]
+pub struct Routing<T> {
+    inner: T
+}
+
+impl<T> Routing<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Routing<T> {
+    pub fn next_header(&self) -> Protocol {
+        self.inner.as_ref()[0].into()
+    }
+    pub fn header_len(&self) -> usize {
+        (self.inner.as_ref()[1] as usize + 1) * 8
+    }
+    pub fn routing_type(&self) -> u8 {
+        self.inner.as_ref()[2]
+    }
+    pub fn segments_left(&self) -> u8 {
+        self.inner.as_ref()[3]
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Routing<&'a T> {
+    pub fn data(&self) -> &'a [u8] {
+        &self.inner.as_ref()[4..]
+    }
+}
+
+impl<T: AsMut<[u8]>> Routing<T> {
+    pub fn set_next_header(&mut self, protocol: Protocol) {
+        self.inner.as_mut()[0] = protocol.into()
+    }
+    pub fn set_header_len(&mut self, len: usize) {
+        debug_assert!(len >= 8 && len.is_multiple_of(8) && len <= 2048);
+        self.inner.as_mut()[1] = (len / 8 - 1) as u8
+    }
+    pub fn set_routing_type(&mut self, routing_type: u8) {
+        self.inner.as_mut()[2] = routing_type
+    }
+    pub fn set_segments_left(&mut self, segments_left: u8) {
+        self.inner.as_mut()[3] = segments_left
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Routing<&mut T> {
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.as_mut()[4..]
+    }
+}
+
+// Fragment header, Section 4.5
+#[derive(Debug, Clone, Copy)]
+pub struct Fragment<T> {
+    inner: T
+}
+
+impl<T> Fragment<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    pub const LEN: usize = 8;
+}
+
+impl<T: AsRef<[u8]>> Fragment<T> {
+    pub fn next_header(&self) -> Protocol {
+        self.inner.as_ref()[0].into()
+    }
+    // offset of fragment data in bytes, always a multiple of 8
+    pub fn frag_offset(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[2..4]) & !0x07
+    }
+    pub fn more_fragments(&self) -> bool {
+        self.inner.as_ref()[3] & 0x01 != 0
+    }
+    pub fn identification(&self) -> u32 {
+        NetworkEndian::read_u32(&self.inner.as_ref()[4..8])
+    }
+}
+
+impl<T: AsMut<[u8]>> Fragment<T> {
+    pub fn set_next_header(&mut self, protocol: Protocol) {
+        let data = self.inner.as_mut();
+        data[0] = protocol.into();
+        data[1] = 0; // reserved
+    }
+    pub fn set_frag_offset(&mut self, offset: u16) {
+        debug_assert!(offset.is_multiple_of(8));
+        let data = &mut self.inner.as_mut()[2..4];
+        let value = (NetworkEndian::read_u16(data) & 0x07) | (offset & !0x07);
+        NetworkEndian::write_u16(data, value)
+    }
+    pub fn set_more_fragments(&mut self, more_fragments: bool) {
+        let data = self.inner.as_mut();
+        data[3] = (data[3] & !0x07) | more_fragments as u8
+    }
+    pub fn set_identification(&mut self, identification: u32) {
+        NetworkEndian::write_u32(&mut self.inner.as_mut()[4..8], identification)
+    }
+}
+
+// Authentication Header: See https://tools.ietf.org/html/rfc4302#section-2
+#[derive(Debug, Clone, Copy)]
+pub struct Ah<T> {
+    inner: T
+}
+
+impl<T> Ah<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Ah<T> {
+    pub fn next_header(&self) -> Protocol {
+        self.inner.as_ref()[0].into()
+    }
+    // payload length field counts 4-octet units minus 2
+    pub fn header_len(&self) -> usize {
+        (self.inner.as_ref()[1] as usize + 2) * 4
+    }
+    pub fn spi(&self) -> u32 {
+        NetworkEndian::read_u32(&self.inner.as_ref()[4..8])
+    }
+    pub fn sequence_number(&self) -> u32 {
+        NetworkEndian::read_u32(&self.inner.as_ref()[8..12])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Ah<&'a T> {
+    // integrity check value
+    pub fn icv(&self) -> &'a [u8] {
+        let data = self.inner.as_ref();
+        &data[12..self.header_len().min(data.len())]
+    }
+}
+
+// one header in the chain, each trimmed to its own length
+#[derive(Debug, Clone, Copy)]
+pub enum ExtHeader<'a> {
+    HopByHop(Options<&'a [u8]>),
+    Routing(Routing<&'a [u8]>),
+    Fragment(Fragment<&'a [u8]>),
+    DestinationOptions(Options<&'a [u8]>),
+    Ah(Ah<&'a [u8]>),
+    // nothing follows; data after it is to be ignored
+    NoNextHeader(&'a [u8]),
+}
+
+impl<'a> ExtHeader<'a> {
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            ExtHeader::HopByHop(_) => Protocol::HopByHop,
+            ExtHeader::Routing(_) => Protocol::Routing,
+            ExtHeader::Fragment(_) => Protocol::Fragment,
+            ExtHeader::DestinationOptions(_) => Protocol::DestinationOptions,
+            ExtHeader::Ah(_) => Protocol::Ah,
+            ExtHeader::NoNextHeader(_) => Protocol::NoNextHeader,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtHeaderError {
+    // header longer than remaining payload
+    Truncated,
+    // more than MAX_EXT_HEADERS headers
+    TooManyHeaders,
+    // Hop-by-Hop Options must immediately follow the IPv6 header
+    HopByHopNotFirst,
+}
+
+// walks the header chain starting after the fixed IPv6 header;
+// stops at the first upper-layer protocol or error
+#[derive(Debug, Clone)]
+pub struct ExtHeaderIter<'a> {
+    next_header: Protocol,
+    data: &'a [u8],
+    count: usize,
+    error: Option<ExtHeaderError>,
+    finished: bool,
+}
+
+impl<'a> ExtHeaderIter<'a> {
+    pub fn new(next_header: Protocol, data: &'a [u8]) -> Self {
+        Self { next_header, data, count: 0, error: None, finished: false }
+    }
+    // protocol of the data not walked yet
+    pub fn next_header(&self) -> Protocol {
+        self.next_header
+    }
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+    pub fn error(&self) -> Option<ExtHeaderError> {
+        self.error
+    }
+    fn fail(&mut self, error: ExtHeaderError) -> Option<Result<ExtHeader<'a>, ExtHeaderError>> {
+        self.error = Some(error);
+        Some(Err(error))
+    }
+}
+
+impl<'a> Iterator for ExtHeaderIter<'a> {
+    type Item = Result<ExtHeader<'a>, ExtHeaderError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.error.is_some() || !self.next_header.is_extension_header() {
+            return None
+        }
+        if self.next_header == Protocol::NoNextHeader {
+            self.finished = true;
+            return Some(Ok(ExtHeader::NoNextHeader(core::mem::take(&mut self.data))))
+        }
+        if self.count >= MAX_EXT_HEADERS {
+            return self.fail(ExtHeaderError::TooManyHeaders)
+        }
+        if self.next_header == Protocol::HopByHop && self.count != 0 {
+            return self.fail(ExtHeaderError::HopByHopNotFirst)
+        }
+        if self.data.len() < 8 {
+            return self.fail(ExtHeaderError::Truncated)
+        }
+        let len = match self.next_header {
+            Protocol::Fragment => Fragment::<()>::LEN,
+            Protocol::Ah => Ah::new(self.data).header_len().max(12),
+            _ => (self.data[1] as usize + 1) * 8,
+        };
+        if len > self.data.len() {
+            return self.fail(ExtHeaderError::Truncated)
+        }
+        let (data, rest) = self.data.split_at(len);
+        let header = match self.next_header {
+            Protocol::HopByHop => ExtHeader::HopByHop(Options::new(data)),
+            Protocol::Routing => ExtHeader::Routing(Routing::new(data)),
+            Protocol::Fragment => ExtHeader::Fragment(Fragment::new(data)),
+            Protocol::DestinationOptions => ExtHeader::DestinationOptions(Options::new(data)),
+            Protocol::Ah => ExtHeader::Ah(Ah::new(data)),
+            _ => unreachable!(),
+        };
+        self.next_header = data[0].into();
+        self.data = rest;
+        self.count += 1;
+        Some(Ok(header))
+    }
+}
+
+// skip all extension headers, returns the upper-layer protocol and its data
+pub fn upper_layer(next_header: Protocol, data: &[u8]) -> Result<(Protocol, &[u8]), ExtHeaderError> {
+    let mut iter = ExtHeaderIter::new(next_header, data);
+    for header in &mut iter {
+        header?;
+    }
+    Ok((iter.next_header(), iter.remaining()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hop-by-Hop (router alert), Fragment, then ICMPv6 echo request
+    const CHAIN: [u8; 24] = [
+        0x2c, 0x00, 0x05, 0x02, 0x00, 0x00, 0x01, 0x00,
+        0x3a, 0x00, 0x05, 0xa9, 0x12, 0x34, 0x56, 0x78,
+        0x80, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn ext_header_chain() {
+        let mut iter = ExtHeaderIter::new(Protocol::HopByHop, &CHAIN);
+        let hop_by_hop = match iter.next() {
+            Some(Ok(ExtHeader::HopByHop(header))) => header,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(hop_by_hop.next_header(), Protocol::Fragment);
+        let options: Vec<_> = hop_by_hop.options().map(|opt| opt.option_type).collect();
+        assert_eq!(options, [OptionType::RouterAlert, OptionType::PadN]);
+        let fragment = match iter.next() {
+            Some(Ok(ExtHeader::Fragment(header))) => header,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(fragment.frag_offset(), 0x05a8);
+        assert!(fragment.more_fragments());
+        assert_eq!(fragment.identification(), 0x12345678);
+        assert!(iter.next().is_none());
+        assert_eq!(upper_layer(Protocol::HopByHop, &CHAIN), Ok((Protocol::Icmpv6, &CHAIN[16..])));
+    }
+
+    #[test]
+    fn ext_header_errors() {
+        assert_eq!(upper_layer(Protocol::HopByHop, &CHAIN[..12]), Err(ExtHeaderError::Truncated));
+        // Hop-by-Hop pointing to another Hop-by-Hop
+        let mut data = CHAIN;
+        data[0] = 0x00;
+        assert_eq!(upper_layer(Protocol::HopByHop, &data), Err(ExtHeaderError::HopByHopNotFirst));
+        // endless chain of empty destination options
+        let data: Vec<u8> = [0x3c, 0, 1, 4, 0, 0, 0, 0].repeat(MAX_EXT_HEADERS + 1);
+        assert_eq!(upper_layer(Protocol::DestinationOptions, &data), Err(ExtHeaderError::TooManyHeaders));
+        let mut data = data[8..].to_vec();
+        data[8 * (MAX_EXT_HEADERS - 1)] = 0x3b;
+        data.extend(&[0xff; 4]);
+        assert_eq!(upper_layer(Protocol::DestinationOptions, &data), Ok((Protocol::NoNextHeader, &[][..])));
+        let mut iter = ExtHeaderIter::new(Protocol::DestinationOptions, &data);
+        assert_eq!(iter.by_ref().count(), MAX_EXT_HEADERS + 1);
+        assert!(iter.error().is_none());
+    }
+}
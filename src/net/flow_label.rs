@@ -0,0 +1,142 @@
+// Flow label generation from the 5-tuple, RFC 6437 Appendix A and RFC 6438
+use super::ip::{Address, Packet, Protocol};
+use crate::raw_socket_sys::getrandom;
+use std::io;
+
+// SipHash-2-4 of data under a 128-bit key
+fn siphash24(key: [u64; 2], data: &[u8]) -> u64 {
+    let mut v = [
+        key[0] ^ 0x736f_6d65_7073_6575,
+        key[1] ^ 0x646f_7261_6e64_6f6d,
+        key[0] ^ 0x6c79_6765_6e65_7261,
+        key[1] ^ 0x7465_6462_7974_6573,
+    ];
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    };
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        compress(u64::from_le_bytes(word));
+    }
+    // last word carries the message length in its top byte
+    let mut last = [0u8; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    compress(u64::from_le_bytes(last));
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+// stateless flow labels: packets of one flow share a label, labels of
+// different flows look uniformly random to anyone without the key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowLabeler {
+    key: [u64; 2],
+}
+
+impl FlowLabeler {
+    pub fn new(key: [u8; 16]) -> FlowLabeler {
+        let mut halves = [[0u8; 8]; 2];
+        halves[0].copy_from_slice(&key[..8]);
+        halves[1].copy_from_slice(&key[8..]);
+        FlowLabeler { key: [u64::from_le_bytes(halves[0]), u64::from_le_bytes(halves[1])] }
+    }
+    // with a key from the kernel random source
+    pub fn random() -> io::Result<FlowLabeler> {
+        let mut key = [0u8; 16];
+        getrandom(&mut key)?;
+        Ok(FlowLabeler::new(key))
+    }
+    // 20-bit label, never 0 since that means unlabeled
+    pub fn label(&self, src_addr: Address, dst_addr: Address, protocol: Protocol, src_port: u16, dst_port: u16) -> u32 {
+        let mut tuple = [0u8; 37];
+        tuple[..16].copy_from_slice(&src_addr.octets());
+        tuple[16..32].copy_from_slice(&dst_addr.octets());
+        tuple[32] = protocol.into();
+        tuple[33..35].copy_from_slice(&src_port.to_be_bytes());
+        tuple[35..37].copy_from_slice(&dst_port.to_be_bytes());
+        let hash = siphash24(self.key, &tuple);
+        // fold all bits into the label
+        let label = (hash ^ (hash >> 20) ^ (hash >> 40)) as u32 & 0x000f_ffff;
+        if label == 0 { 1 } else { label }
+    }
+    // label of a packet, ports taken from a TCP, UDP or SCTP header after any extension headers
+    pub fn packet_label<T: AsRef<[u8]> + ?Sized>(&self, packet: &Packet<&T>) -> u32 {
+        let (protocol, ports) = match packet.upper_layer() {
+            Ok((protocol @ (Protocol::Tcp | Protocol::Udp | Protocol::Unknown(132)), data)) if data.len() >= 4 => {
+                (protocol, (u16::from_be_bytes([data[0], data[1]]), u16::from_be_bytes([data[2], data[3]])))
+            },
+            Ok((protocol, _)) => (protocol, (0, 0)),
+            Err(_) => (packet.next_header(), (0, 0)),
+        };
+        self.label(packet.src_addr(), packet.dst_addr(), protocol, ports.0, ports.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flow_label_siphash_vector() {
+        // Appendix A of the SipHash paper
+        let key = [0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908];
+        let data: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash24(key, &data), 0xa129_ca61_49be_45e5);
+        assert_eq!(siphash24(key, &[]), 0x726f_db47_dd0e_0e31);
+    }
+
+    #[test]
+    fn flow_label_spread() {
+        let labeler = FlowLabeler::new(*b"0123456789abcdef");
+        let src: Address = "2001:db8::1".parse().unwrap();
+        let dst: Address = "2001:db8::2".parse().unwrap();
+        let label = labeler.label(src, dst, Protocol::Udp, 5000, 53);
+        assert!(label != 0 && label <= 0x000f_ffff);
+        assert_eq!(labeler.label(src, dst, Protocol::Udp, 5000, 53), label);
+        assert_ne!(FlowLabeler::new([0; 16]).label(src, dst, Protocol::Udp, 5000, 53), label);
+        // sequential source ports should spread evenly over ECMP paths
+        let mut paths = [0u32; 4];
+        for port in 0..4000 {
+            paths[labeler.label(src, dst, Protocol::Udp, port, 53) as usize % 4] += 1;
+        }
+        assert!(paths.iter().all(|&count| count > 900 && count < 1100), "{:?}", paths);
+        assert!(FlowLabeler::random().is_ok());
+    }
+
+    #[test]
+    fn flow_label_packet() {
+        let labeler = FlowLabeler::new([7; 16]);
+        let mut buf = [0u8; 48];
+        let mut packet = Packet::new(&mut buf[..]);
+        packet.set_version(6);
+        packet.set_payload_len(8);
+        packet.set_next_header(Protocol::Udp);
+        packet.set_src_addr("fe80::1".parse().unwrap());
+        packet.set_dst_addr("fe80::2".parse().unwrap());
+        buf[40..44].copy_from_slice(&[0x13, 0x88, 0x00, 0x35]);
+        let packet = Packet::new(&buf[..]);
+        let expected = labeler.label(packet.src_addr(), packet.dst_addr(), Protocol::Udp, 5000, 53);
+        assert_eq!(labeler.packet_label(&packet), expected);
+    }
+}
@@ -0,0 +1,207 @@
+// IPv6 source fragmentation: See https://tools.ietf.org/html/rfc8200#section-4.5
+use super::ext_header::Fragment;
+use super::ip::{Address, Packet, Protocol};
+use crate::packet_write::PacketWriter;
+use crate::raw_socket_sys::getrandom;
+use std::io;
+
+const IP_HEADER_LEN: usize = 40;
+
+// minimum link MTU of IPv6, Section 5
+pub const MIN_MTU: usize = 1280;
+
+// identification should be unpredictable, see https://tools.ietf.org/html/rfc7739
+pub fn random_identification() -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    getrandom(&mut bytes)?;
+    Ok(u32::from_ne_bytes(bytes))
+}
+
+// splits the packet produced by a payload writer into fragments fitting the MTU;
+// a packet that already fits is emitted as is, without a Fragment header
+#[derive(Debug, Clone)]
+pub struct Fragmenter<W> {
+    version_tc_flow: (u8, u8, u32),
+    next_header: Protocol,
+    hop_limit: u8,
+    src_addr: Address,
+    dst_addr: Address,
+    payload: W,
+    payload_len: usize,
+    mtu: usize,
+    identification: u32,
+    offset: usize,
+    finished: bool,
+}
+
+impl<W: PacketWriter> Fragmenter<W> {
+    // header fields except payload length are copied from template; next header
+    // of template is the protocol of payload; None if the MTU is below the IPv6
+    // minimum or the payload does not fit the 16-bit payload length
+    pub fn new<T: AsRef<[u8]>>(template: &Packet<T>, payload: W, mtu: usize, identification: u32) -> Option<Self> {
+        if mtu < MIN_MTU || payload.packet_len() > 0xffff {
+            return None
+        }
+        Some(Fragmenter {
+            version_tc_flow: (template.version(), template.traffic_class(), template.flow_label()),
+            next_header: template.next_header(),
+            hop_limit: template.hop_limit(),
+            src_addr: template.src_addr(),
+            dst_addr: template.dst_addr(),
+            payload_len: payload.packet_len(),
+            payload,
+            mtu,
+            identification,
+            offset: 0,
+            finished: false,
+        })
+    }
+    pub fn needs_fragmentation(&self) -> bool {
+        IP_HEADER_LEN + self.payload_len > self.mtu
+    }
+    // payload bytes per fragment except the last, multiple of 8
+    fn max_fragment_data(&self) -> usize {
+        (self.mtu - IP_HEADER_LEN - Fragment::<()>::LEN) & !0x07
+    }
+    pub fn fragment_count(&self) -> usize {
+        if self.needs_fragmentation() {
+            self.payload_len.div_ceil(self.max_fragment_data())
+        } else {
+            1
+        }
+    }
+    pub fn identification(&self) -> u32 {
+        self.identification
+    }
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    // write next IPv6 packet, returns its length or 0 when all are written;
+    // a buffer shorter than min(mtu, whole packet) also gets 0 and nothing is consumed
+    #[must_use]
+    pub fn write_next(&mut self, buffer: &mut [u8]) -> usize {
+        if self.finished {
+            return 0
+        }
+        let fragmented = self.needs_fragmentation();
+        let (header_len, data_len) = if fragmented {
+            let data_len = usize::min(self.max_fragment_data(), self.payload_len - self.offset);
+            (IP_HEADER_LEN + Fragment::<()>::LEN, data_len)
+        } else {
+            (IP_HEADER_LEN, self.payload_len)
+        };
+        let total_len = header_len + data_len;
+        if buffer.len() < total_len {
+            return 0
+        }
+        let mut packet = Packet::new(&mut buffer[..total_len]);
+        let (version, traffic_class, flow_label) = self.version_tc_flow;
+        packet.set_version(version);
+        packet.set_traffic_class(traffic_class);
+        packet.set_flow_label(flow_label);
+        packet.set_payload_len((total_len - IP_HEADER_LEN) as u16);
+        packet.set_hop_limit(self.hop_limit);
+        packet.set_src_addr(self.src_addr);
+        packet.set_dst_addr(self.dst_addr);
+        if fragmented {
+            packet.set_next_header(Protocol::Fragment);
+            let mut fragment = Fragment::new(&mut packet.payload_mut()[..Fragment::<()>::LEN]);
+            fragment.set_next_header(self.next_header);
+            fragment.set_frag_offset(self.offset as u16);
+            fragment.set_more_fragments(self.offset + data_len < self.payload_len);
+            fragment.set_identification(self.identification);
+        } else {
+            packet.set_next_header(self.next_header);
+        }
+        let data = &mut buffer[header_len..total_len];
+        let mut written = 0;
+        while written < data_len {
+            let len = self.payload.write_packet(&mut data[written..]);
+            if len == 0 {
+                break
+            }
+            written += len;
+        }
+        debug_assert_eq!(written, data_len);
+        self.offset += data_len;
+        self.finished = self.offset >= self.payload_len;
+        total_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::IcmpWriter;
+
+    fn template() -> [u8; 40] {
+        let mut buf = [0u8; 40];
+        let mut packet = Packet::new(&mut buf[..]);
+        packet.set_version(6);
+        packet.set_flow_label(0x12345);
+        packet.set_next_header(Protocol::Icmpv6);
+        packet.set_hop_limit(64);
+        packet.set_src_addr("fe80::1".parse().unwrap());
+        packet.set_dst_addr("fe80::2".parse().unwrap());
+        buf
+    }
+
+    #[test]
+    fn fragment_large_payload() {
+        let data = (0..3000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut whole = vec![0u8; 3008];
+        let _ = IcmpWriter::echo_request(1, 1, &data).write(&mut whole);
+        let template = template();
+        let mut fragmenter = Fragmenter::new(&Packet::new(&template), IcmpWriter::echo_request(1, 1, &data), MIN_MTU, 7).unwrap();
+        assert!(fragmenter.needs_fragmentation());
+        assert_eq!(fragmenter.fragment_count(), 3);
+        let mut reassembled = Vec::new();
+        let mut buf = [0u8; MIN_MTU];
+        let mut lens = Vec::new();
+        loop {
+            let len = fragmenter.write_next(&mut buf);
+            if len == 0 {
+                break
+            }
+            lens.push(len);
+            let packet = Packet::new(&buf[..len]);
+            assert_eq!(packet.next_header(), Protocol::Fragment);
+            assert_eq!(packet.flow_label(), 0x12345);
+            assert_eq!(packet.total_len(), len);
+            let fragment = Fragment::new(packet.payload());
+            assert_eq!(fragment.next_header(), Protocol::Icmpv6);
+            assert_eq!(fragment.identification(), 7);
+            assert_eq!(fragment.frag_offset() as usize, reassembled.len());
+            assert_eq!(fragment.more_fragments(), reassembled.len() + len - 48 < whole.len());
+            reassembled.extend_from_slice(&packet.payload()[8..]);
+        }
+        assert_eq!(lens, [1280, 1280, 48 + 3008 - 2 * 1232]);
+        assert_eq!(reassembled, whole);
+        assert!(fragmenter.is_finished());
+    }
+
+    #[test]
+    fn fragment_small_payload() {
+        let template = template();
+        let mut fragmenter = Fragmenter::new(&Packet::new(&template), IcmpWriter::echo_request(1, 1, [0u8; 8]), 1500, 0).unwrap();
+        assert!(!fragmenter.needs_fragmentation());
+        let mut buf = [0u8; 1500];
+        assert_eq!(fragmenter.write_next(&mut buf), 56);
+        assert_eq!(Packet::new(&buf[..]).next_header(), Protocol::Icmpv6);
+        assert_eq!(Packet::new(&buf[..]).payload_len(), 16);
+        assert_eq!(fragmenter.write_next(&mut buf), 0);
+    }
+
+    #[test]
+    fn fragment_invalid() {
+        let template = Packet::new(template());
+        assert!(Fragmenter::new(&template, IcmpWriter::echo_request(1, 1, [0u8; 8]), MIN_MTU - 1, 0).is_none());
+        let mut fragmenter = Fragmenter::new(&template, IcmpWriter::echo_request(1, 1, [0u8; 8]), MIN_MTU, 0).unwrap();
+        // a short buffer is refused without losing the packet
+        assert_eq!(fragmenter.write_next(&mut [0u8; 55]), 0);
+        assert!(!fragmenter.is_finished());
+        assert_eq!(fragmenter.write_next(&mut [0u8; 56]), 56);
+        assert!(Fragmenter::new(&template, IcmpWriter::echo_request(1, 1, vec![0u8; 0xffff - 8]), 1500, 0).is_some());
+        assert!(Fragmenter::new(&template, IcmpWriter::echo_request(1, 1, vec![0u8; 0xffff - 7]), 1500, 0).is_none());
+    }
+}
@@ -1,7 +1,9 @@
 // ICMPv6
 use byteorder::{ByteOrder, NetworkEndian};
 use core::ops::Range;
-use crate::packet_write::PacketWrite;
+use crate::packet_write::{PacketWrite, PacketWriter};
+use super::checksum::{self, Checksum};
+use super::ip::{Address, Protocol};
 
 // ICMPv6 packet
 #[derive(Debug, Clone, Copy)]
@@ -30,6 +32,13 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn checksum(&self) -> u16 {
         NetworkEndian::read_u16(&self.inner.as_ref()[Self::CHECKSUM])
     }
+    // the buffer must hold exactly the ICMPv6 message
+    pub fn verify_checksum(&self, src_addr: Address, dst_addr: Address) -> bool {
+        let data = self.inner.as_ref();
+        let mut sum = checksum::pseudo_header(src_addr, dst_addr, data.len() as u32, Protocol::Icmpv6);
+        sum.add(data);
+        sum.sum() == 0xffff
+    }
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
@@ -50,7 +59,18 @@ impl<T: AsMut<[u8]>> Packet<T> {
     }
 }
 
-impl<'a, T: AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    // compute and store checksum; the buffer must hold exactly the ICMPv6 message
+    pub fn fill_checksum(&mut self, src_addr: Address, dst_addr: Address) {
+        self.set_checksum(0);
+        let data = self.inner.as_ref();
+        let mut sum = checksum::pseudo_header(src_addr, dst_addr, data.len() as u32, Protocol::Icmpv6);
+        sum.add(data);
+        self.set_checksum(sum.finish())
+    }
+}
+
+impl<T: AsMut<[u8]> + ?Sized> Packet<&mut T> {
     pub fn payload_mut(&mut self) -> &mut [u8] {
         &mut self.inner.as_mut()[Self::CHECKSUM.end..]
     }
@@ -83,6 +103,7 @@ impl From<Type> for u8 {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Writer<T> {
     write_type: WriteType<T>,
+    checksum: u16,
     byte_idx: usize,
 }
 
@@ -95,6 +116,7 @@ impl<T> Writer<T> {
     pub fn echo_request(identifier: u16, sequence_number: u16, data: T) -> Writer<T> {
         Writer { 
             write_type: WriteType::EchoRequest { identifier, sequence_number, data },
+            checksum: 0,
             byte_idx: 0,
         }
     }
@@ -106,6 +128,30 @@ impl<T: AsRef<[u8]>> Writer<T> {
             WriteType::EchoRequest { data, .. } => data.as_ref().len() + 8,
         }
     }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    // addresses of the enclosing IPv6 header, needed for the checksum;
+    // without them the checksum field is written as 0
+    pub fn addresses(self, src_addr: Address, dst_addr: Address) -> Self {
+        let mut sum = checksum::pseudo_header(src_addr, dst_addr, self.len() as u32, Protocol::Icmpv6);
+        sum.merge(self.message_sum());
+        Self { checksum: sum.finish(), ..self }
+    }
+    // sum of the message with checksum field 0
+    fn message_sum(&self) -> Checksum {
+        let mut sum = Checksum::new();
+        match &self.write_type {
+            WriteType::EchoRequest { identifier, sequence_number, data } => {
+                sum.add(&[Type::EchoRequest.into(), 0]);
+                sum.add_u16(0);
+                sum.add_u16(*identifier);
+                sum.add_u16(*sequence_number);
+                sum.add(data.as_ref());
+            },
+        }
+        sum
+    }
     #[must_use]
     pub fn write(&mut self, buffer: &mut [u8]) -> usize {
         let mut write = PacketWrite::new(buffer, self.byte_idx);
@@ -114,8 +160,7 @@ impl<T: AsRef<[u8]>> Writer<T> {
         };
         write.write_u8_at(0..1, typ.into());
         write.write_u8_at(1..2, code);
-        let checksum = 0xa688; // todo
-        write.write_u16_at(2..4, checksum);
+        write.write_u16_at(2..4, self.checksum);
         match &self.write_type {
             WriteType::EchoRequest { identifier, sequence_number, data } => {
                 write.write_u16_at(4..6, *identifier);
@@ -128,6 +173,15 @@ impl<T: AsRef<[u8]>> Writer<T> {
     }
 }
 
+impl<T: AsRef<[u8]>> PacketWriter for Writer<T> {
+    fn packet_len(&self) -> usize {
+        self.len()
+    }
+    fn write_packet(&mut self, buffer: &mut [u8]) -> usize {
+        self.write(buffer)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct EchoRequest<T> {
     inner: T
@@ -165,7 +219,7 @@ impl<T: AsMut<[u8]>> EchoRequest<T> {
     }
 }
 
-impl<'a, T: AsMut<[u8]> + ?Sized> EchoRequest<&'a mut T> {
+impl<T: AsMut<[u8]> + ?Sized> EchoRequest<&mut T> {
     pub fn data_mut(&mut self) -> &mut [u8] {
         &mut self.inner.as_mut()[Self::SEQUENCE_NUMBER.end..]
     }
@@ -189,4 +243,25 @@ mod tests {
         let len = echo.write(&mut out);
         eprintln!("{:x?}", &out[..len]);
     }
+
+    #[test]
+    fn icmp_checksum() {
+        let src: Address = "fe80::215:5dff:feb7:9fa9".parse().unwrap();
+        let dst: Address = "fe80::d19d:d7:769c:3a86".parse().unwrap();
+        let data = [0x34u8, 0x33, 0x79, 0x61, 0, 0, 0, 0, 0x48, 0x1e, 0x04];
+        let mut echo = Writer::echo_request(0x343b, 1, &data[..]).addresses(src, dst);
+        let mut out = vec![0u8; echo.len()];
+        let mut written = 0;
+        // resumable writes in odd-sized pieces still carry the checksum
+        while written < out.len() {
+            let end = usize::min(written + 3, out.len());
+            written += echo.write(&mut out[written..end]);
+        }
+        assert!(Packet::new(&out[..]).verify_checksum(src, dst));
+        assert!(!Packet::new(&out[..]).verify_checksum(dst, dst));
+        let checksum = Packet::new(&out[..]).checksum();
+        Packet::new(&mut out[..]).set_checksum(0);
+        Packet::new(&mut out[..]).fill_checksum(src, dst);
+        assert_eq!(Packet::new(&out[..]).checksum(), checksum);
+    }
 }
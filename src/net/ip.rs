@@ -1,12 +1,20 @@
-// Ip address; only IPv6 is supported
+// IPv6 address and header; IPv4 lives in ipv4.rs
 
+use super::ext_header::{self, ExtHeader, ExtHeaderError, ExtHeaderIter};
+use super::srh::Srh;
+use super::registry;
+use super::traffic_class::{Dscp, Ecn};
+use crate::raw_socket_sys;
 use byteorder::{ByteOrder, NetworkEndian};
+use core::iter::FromIterator;
 use core::ops::Range;
 use core::fmt;
 use core::str::FromStr;
+use std::io;
+use std::net::{Ipv6Addr, SocketAddrV6};
 
 // Ipv6 address
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Address {
     repr: u128,
 }
@@ -23,7 +31,7 @@ impl Address {
         Self { repr }
     }
     pub fn from_bytes(src: &[u8]) -> Address {
-        let repr = NetworkEndian::read_u128(&src);
+        let repr = NetworkEndian::read_u128(src);
         Address { repr }
     }
     pub const fn segments(self) -> [u16; 8] {
@@ -38,6 +46,173 @@ impl Address {
     pub const fn octets(self) -> [u8; 16] {
         self.repr.to_be_bytes()
     }
+    pub const fn to_bits(self) -> u128 {
+        self.repr
+    }
+    pub const fn from_bits(repr: u128) -> Address {
+        Address { repr }
+    }
+    // ::
+    pub const fn is_unspecified(self) -> bool {
+        Subnet::UNSPECIFIED.contains(self)
+    }
+    // ::1
+    pub const fn is_loopback(self) -> bool {
+        Subnet::LOOPBACK.contains(self)
+    }
+    // FF00::/8
+    pub const fn is_multicast(self) -> bool {
+        Subnet::MULTICAST.contains(self)
+    }
+    pub const fn is_unicast(self) -> bool {
+        !self.is_multicast() && !self.is_unspecified()
+    }
+    // FE80::/10
+    pub const fn is_link_local(self) -> bool {
+        Subnet::LINK_LOCAL_UNICAST.contains(self)
+    }
+    // FC00::/7, RFC 4193
+    pub const fn is_unique_local(self) -> bool {
+        registry::UNIQUE_LOCAL.contains(self)
+    }
+    // FEC0::/10, deprecated by RFC 3879
+    pub const fn is_site_local(self) -> bool {
+        SITE_LOCAL.contains(self)
+    }
+    // 2001:DB8::/32 and 3FFF::/20, RFC 3849 and RFC 9637
+    pub const fn is_documentation(self) -> bool {
+        registry::DOCUMENTATION.contains(self) || registry::DOCUMENTATION_3FFF.contains(self)
+    }
+    // 2001:2::/48, RFC 5180
+    pub const fn is_benchmarking(self) -> bool {
+        registry::BENCHMARKING.contains(self)
+    }
+    // ::FFFF:0:0/96
+    pub const fn is_ipv4_mapped(self) -> bool {
+        registry::IPV4_MAPPED.contains(self)
+    }
+    // 64:FF9B::/96, well-known prefix of RFC 6052
+    pub const fn is_ipv4_translated(self) -> bool {
+        registry::IPV4_TRANSLATED.contains(self)
+    }
+    // embedded IPv4 address of mapped and translated addresses
+    pub fn to_ipv4(self) -> Option<super::ipv4::Address> {
+        if self.is_ipv4_mapped() || self.is_ipv4_translated() {
+            Some(super::ipv4::Address::from_bits(self.repr as u32))
+        } else {
+            None
+        }
+    }
+    pub fn to_ipv4_mapped(address: super::ipv4::Address) -> Address {
+        Address { repr: registry::IPV4_MAPPED.network.repr | address.to_bits() as u128 }
+    }
+    // FF02::1:FF00:0/104, Section 2.7.1 of RFC 4291
+    pub const fn is_solicited_node(self) -> bool {
+        SOLICITED_NODE.contains(self)
+    }
+    // solicited-node multicast address of a unicast address
+    pub const fn solicited_node(self) -> Address {
+        Address { repr: SOLICITED_NODE.network.repr | (self.repr & !mask(SOLICITED_NODE.prefix)) }
+    }
+    pub fn multicast_scope(self) -> Option<MulticastScope> {
+        if !self.is_multicast() {
+            return None
+        }
+        Some(MulticastScope::from(self.octets()[1] & 0x0f))
+    }
+    pub fn multicast_flags(self) -> Option<MulticastFlags> {
+        if !self.is_multicast() {
+            return None
+        }
+        Some(MulticastFlags(self.octets()[1] >> 4))
+    }
+    // entry of IANA special-purpose registry covering this address
+    pub fn special_purpose(self) -> Option<&'static registry::Entry> {
+        registry::lookup(self)
+    }
+    // reachable from the whole Internet, by special-purpose registry
+    // or by scope for multicast addresses
+    pub fn is_global(self) -> bool {
+        if let Some(scope) = self.multicast_scope() {
+            return scope == MulticastScope::Global
+        }
+        match self.special_purpose() {
+            Some(entry) => entry.globally_reachable == Some(true),
+            None => GLOBAL_UNICAST.contains(self),
+        }
+    }
+}
+
+// blocks outside the special-purpose registry
+const SITE_LOCAL: Subnet = Subnet::new(Address::from_segments([0xfec0, 0, 0, 0, 0, 0, 0, 0]), 10);
+const SOLICITED_NODE: Subnet = Subnet::new(Address::from_segments([0xff02, 0, 0, 0, 0, 1, 0xff00, 0]), 104);
+// 2000::/3 is the only global unicast space allocated by IANA
+const GLOBAL_UNICAST: Subnet = Subnet::new(Address::from_segments([0x2000, 0, 0, 0, 0, 0, 0, 0]), 3);
+
+// Section 2.7 of RFC 4291 and RFC 7346
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MulticastScope {
+    InterfaceLocal, // 1
+    LinkLocal, // 2
+    RealmLocal, // 3
+    AdminLocal, // 4
+    SiteLocal, // 5
+    OrganizationLocal, // 8
+    Global, // 0xE
+    // reserved or unassigned values
+    Unknown(u8),
+}
+
+impl From<u8> for MulticastScope {
+    fn from(src: u8) -> Self {
+        match src {
+            0x1 => MulticastScope::InterfaceLocal,
+            0x2 => MulticastScope::LinkLocal,
+            0x3 => MulticastScope::RealmLocal,
+            0x4 => MulticastScope::AdminLocal,
+            0x5 => MulticastScope::SiteLocal,
+            0x8 => MulticastScope::OrganizationLocal,
+            0xE => MulticastScope::Global,
+            others => MulticastScope::Unknown(others),
+        }
+    }
+}
+
+impl From<MulticastScope> for u8 {
+    fn from(src: MulticastScope) -> u8 {
+        match src {
+            MulticastScope::InterfaceLocal => 0x1,
+            MulticastScope::LinkLocal => 0x2,
+            MulticastScope::RealmLocal => 0x3,
+            MulticastScope::AdminLocal => 0x4,
+            MulticastScope::SiteLocal => 0x5,
+            MulticastScope::OrganizationLocal => 0x8,
+            MulticastScope::Global => 0xE,
+            MulticastScope::Unknown(others) => others
+        }
+    }
+}
+
+// 4-bit flags field 0RPT of multicast addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MulticastFlags(u8);
+
+impl MulticastFlags {
+    // T: not a permanently assigned well-known address
+    pub fn transient(self) -> bool {
+        self.0 & 0x1 != 0
+    }
+    // P: assigned based on a unicast prefix, RFC 3306
+    pub fn prefix_based(self) -> bool {
+        self.0 & 0x2 != 0
+    }
+    // R: rendezvous point address embedded, RFC 3956
+    pub fn rp_embedded(self) -> bool {
+        self.0 & 0x4 != 0
+    }
+    pub fn bits(self) -> u8 {
+        self.0
+    }
 }
 
 impl From<[u8; 16]> for Address {
@@ -52,142 +227,132 @@ impl From<[u16; 8]> for Address {
     }
 }
 
-impl fmt::Display for Address {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl From<Ipv6Addr> for Address {
+    #[inline] fn from(address: Ipv6Addr) -> Address {
+        Address::from_bits(address.into())
+    }
+}
+
+impl From<Address> for Ipv6Addr {
+    #[inline] fn from(address: Address) -> Ipv6Addr {
+        address.to_bits().into()
+    }
+}
+
+impl Address {
+    // RFC 5952: the first longest run of two or more zero groups becomes "::",
+    // mapped and translated addresses end in dotted IPv4
+    fn write_text(&self, f: &mut fmt::Formatter<'_>, uppercase: bool) -> fmt::Result {
         let segments = self.segments();
-        let (longest_idx, longest_len) = {
-            let (mut longest_idx, mut longest_len) = (0, 0);
-            let mut cur_len = 0;
-            for idx in 0..8 {
-                if segments[idx] == 0 {
-                    cur_len += 1;
-                    if cur_len > longest_len {
-                        longest_len = cur_len;
-                        longest_idx = idx + 1 - cur_len;
-                    }
-                } else {
-                    cur_len = 0;
+        let embedded = self.to_ipv4();
+        let groups = if embedded.is_some() { &segments[..6] } else { &segments[..] };
+        let (mut longest_idx, mut longest_len) = (0, 0);
+        let mut cur_len = 0;
+        for (idx, &segment) in groups.iter().enumerate() {
+            if segment == 0 {
+                cur_len += 1;
+                if cur_len > longest_len {
+                    longest_len = cur_len;
+                    longest_idx = idx + 1 - cur_len;
                 }
+            } else {
+                cur_len = 0;
             }
-            (longest_idx, longest_len)
-        };
+        }
+        if longest_len < 2 {
+            longest_idx = groups.len();
+            longest_len = 0;
+        }
         let mut idx = 0;
-        while idx < 8 {
+        while idx < groups.len() {
             if idx == longest_idx && longest_len != 0 {
                 write!(f, "::")?;
                 idx += longest_len;
+                continue
+            }
+            if idx != 0 && idx != longest_idx + longest_len {
+                write!(f, ":")?;
+            }
+            if uppercase {
+                write!(f, "{:X}", groups[idx])?;
             } else {
-                write!(f, "{:X}", segments[idx])?;
-                idx += 1;
-                if idx != 8 && idx != longest_idx {
-                    write!(f, ":")?;
-                }
+                write!(f, "{:x}", groups[idx])?;
             }
+            idx += 1;
+        }
+        match embedded {
+            Some(ipv4) if longest_idx + longest_len == 6 && longest_len != 0 => write!(f, "{}", ipv4),
+            Some(ipv4) => write!(f, ":{}", ipv4),
+            None => Ok(()),
+        }
+    }
+}
+
+// canonical lowercase form, e.g. 2001:db8::1 or ::ffff:192.0.2.1
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_text(f, false)
+    }
+}
+
+// legacy uppercase form, e.g. 2001:DB8::1, via "{:X}"
+impl fmt::UpperHex for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_text(f, true)
+    }
+}
+
+// one to four hex digits
+fn parse_group(s: &str) -> Option<u16> {
+    if s.is_empty() || s.len() > 4 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None
+    }
+    u16::from_str_radix(s, 16).ok()
+}
+
+// groups of one side of "::", the last one may be dotted IPv4 when it ends the address
+fn parse_groups(s: &str, allow_ipv4: bool, groups: &mut Vec<u16>) -> Option<()> {
+    if s.is_empty() {
+        return Some(())
+    }
+    let mut parts = s.split(':').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() && allow_ipv4 && part.contains('.') {
+            let ipv4: super::ipv4::Address = part.parse().ok()?;
+            let bits = ipv4.to_bits();
+            groups.push((bits >> 16) as u16);
+            groups.push(bits as u16);
+        } else {
+            groups.push(parse_group(part)?);
         }
-        Ok(())
     }
+    Some(())
 }
 
+// every text form of RFC 4291 section 2.2, including embedded IPv4
 impl FromStr for Address {
     type Err = ParseAddressError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut segments = [0u16; 8];
-        let mut it = s.bytes().peekable();
-        let mut cur_num = 0;
-        let mut cur_num_len = 0;
-        let mut is_start = true;
-        let mut segment_idx = 0;
-        loop {
-            match it.next() {
-                Some(byte @ (b'0'..=b'9' | b'a' ..= b'f' | b'A' ..= b'F')) => {
-                    if cur_num_len >= 4 {
-                        return Err(ParseAddressError(()))
-                    }
-                    let digit = match byte {
-                        b'0' ..= b'9' => byte - b'0',
-                        b'a' ..= b'f' => byte - b'a' + 10,
-                        b'A' ..= b'F' => byte - b'A' + 10,
-                        _ => unreachable!(),
-                    } as u16;
-                    cur_num_len += 1;
-                    cur_num <<= 4;
-                    cur_num |= digit;
-                    is_start = false;
-                }
-                Some(b':') => {
-                    let nxt = it.peek();
-                    if (nxt != Some(&b':') && is_start) || nxt == None || segment_idx > 8 {
-                        return Err(ParseAddressError(()))
-                    }
-                    if !is_start {
-                        segments[segment_idx] = cur_num;
-                        segment_idx += 1;
-                    }
-                    cur_num_len = 0;
-                    cur_num = 0;
-                    if nxt == Some(&b':') {
-                        it.next(); // consume next character
-                        break // first part finished
-                    } 
-                    is_start = false;
-                },
-                None if segment_idx == 7 => {
-                    segments[segment_idx] = cur_num;
-                    return Ok(Address::from_segments(segments))
-                },
-                _ => return Err(ParseAddressError(()))
-            }
-        }
-        let omitted_idx_start = segment_idx;
-        is_start = true;
-        cur_num_len = 0;
-        cur_num = 0;
-        loop {
-            match it.next() {
-                Some(byte @ (b'0'..=b'9' | b'a' ..= b'f' | b'A' ..= b'F')) => {
-                    if cur_num_len >= 4 {
-                        return Err(ParseAddressError(()))
-                    }
-                    let digit = match byte {
-                        b'0' ..= b'9' => byte - b'0',
-                        b'a' ..= b'f' => byte - b'a' + 10,
-                        b'A' ..= b'F' => byte - b'A' + 10,
-                        _ => unreachable!(),
-                    } as u16;
-                    cur_num_len += 1;
-                    cur_num <<= 4;
-                    cur_num |= digit;
-                    is_start = false;
-                }
-                Some(b':') => {
-                    let nxt = it.peek();
-                    // cannot omit twice
-                    if nxt == Some(&b':') || nxt == None || is_start || segment_idx > 8 {
-                        return Err(ParseAddressError(()))
-                    }
-                    segments[segment_idx] = cur_num;
-                    segment_idx += 1;
-                    cur_num_len = 0;
-                    cur_num = 0;
-                    is_start = false;
-                },
-                None => {
-                    if !is_start {
-                        segments[segment_idx] = cur_num;
-                        segment_idx += 1;
-                    }
-                    break
-                },
-                _ => return Err(ParseAddressError(()))
-            }
-        }
-        let n_omitted_segments = 8 - segment_idx;
-        for idx in (omitted_idx_start + n_omitted_segments .. 8).rev() {
-            segments[idx] = segments[idx - n_omitted_segments]
-        }
-        for idx in omitted_idx_start..omitted_idx_start + n_omitted_segments {
-            segments[idx] = 0;
+        let mut head = Vec::with_capacity(8);
+        let mut tail = Vec::with_capacity(8);
+        let compressed = match s.find("::") {
+            Some(idx) => {
+                parse_groups(&s[..idx], false, &mut head).ok_or(ParseAddressError(()))?;
+                parse_groups(&s[idx + 2..], true, &mut tail).ok_or(ParseAddressError(()))?;
+                true
+            },
+            None => {
+                parse_groups(s, true, &mut head).ok_or(ParseAddressError(()))?;
+                false
+            },
+        };
+        let len = head.len() + tail.len();
+        if (compressed && len > 7) || (!compressed && len != 8) {
+            return Err(ParseAddressError(()))
         }
+        let mut segments = [0u16; 8];
+        segments[..head.len()].copy_from_slice(&head);
+        segments[8 - tail.len()..].copy_from_slice(&tail);
         Ok(Address::from_segments(segments))
     }
 }
@@ -196,6 +361,112 @@ impl FromStr for Address {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ParseAddressError(());
 
+// zone of a scoped address, RFC 4007 section 11
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Zone {
+    Index(u32),
+    Name(String),
+}
+
+impl Zone {
+    // interface index, looking names up through the kernel
+    pub fn index(&self) -> io::Result<u32> {
+        match self {
+            Zone::Index(index) => Ok(*index),
+            Zone::Name(name) => raw_socket_sys::interface_index(name),
+        }
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Index(index) => write!(f, "{}", index),
+            Zone::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+// for example: fe80::1%eth0 or fe80::1%2
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopedAddress {
+    address: Address,
+    zone: Option<Zone>,
+}
+
+impl ScopedAddress {
+    pub fn new(address: Address, zone: Option<Zone>) -> ScopedAddress {
+        ScopedAddress { address, zone }
+    }
+    pub fn address(&self) -> Address {
+        self.address
+    }
+    pub fn zone(&self) -> Option<&Zone> {
+        self.zone.as_ref()
+    }
+    // 0 when there is no zone, as in sin6_scope_id
+    pub fn scope_id(&self) -> io::Result<u32> {
+        self.zone.as_ref().map_or(Ok(0), Zone::index)
+    }
+    // replace an interface name by its index
+    pub fn resolve(&self) -> io::Result<ScopedAddress> {
+        let zone = match self.scope_id()? {
+            0 => None,
+            index => Some(Zone::Index(index)),
+        };
+        Ok(ScopedAddress { address: self.address, zone })
+    }
+    pub fn to_socket_addr(&self, port: u16) -> io::Result<SocketAddrV6> {
+        Ok(SocketAddrV6::new(self.address.into(), port, 0, self.scope_id()?))
+    }
+}
+
+impl From<Address> for ScopedAddress {
+    fn from(address: Address) -> ScopedAddress {
+        ScopedAddress { address, zone: None }
+    }
+}
+
+impl From<SocketAddrV6> for ScopedAddress {
+    fn from(socket_addr: SocketAddrV6) -> ScopedAddress {
+        let zone = match socket_addr.scope_id() {
+            0 => None,
+            index => Some(Zone::Index(index)),
+        };
+        ScopedAddress { address: (*socket_addr.ip()).into(), zone }
+    }
+}
+
+impl fmt::Display for ScopedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.zone {
+            Some(zone) => write!(f, "{}%{}", self.address, zone),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+// an all-digit zone is an index, anything else an interface name
+impl FromStr for ScopedAddress {
+    type Err = ParseAddressError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, zone) = match s.find('%') {
+            Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+            None => (s, None),
+        };
+        let zone = match zone {
+            None => None,
+            Some(zone) if zone.is_empty() || zone.contains('%') => return Err(ParseAddressError(())),
+            Some(zone) if zone.bytes().all(|b| b.is_ascii_digit()) => {
+                Some(Zone::Index(zone.parse().map_err(|_| ParseAddressError(()))?))
+            },
+            Some(zone) => Some(Zone::Name(zone.to_string())),
+        };
+        Ok(ScopedAddress { address: address.parse()?, zone })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct Packet<T> {
     inner: T,
 }
@@ -237,6 +508,12 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn traffic_class(&self) -> u8 {
         ((NetworkEndian::read_u16(&self.inner.as_ref()[0..2]) & 0x0ff0) >> 4) as u8
     }
+    pub fn dscp(&self) -> Dscp {
+        (self.traffic_class() >> 2).into()
+    }
+    pub fn ecn(&self) -> Ecn {
+        self.traffic_class().into()
+    }
     pub fn flow_label(&self) -> u32 {
         NetworkEndian::read_u24(&self.inner.as_ref()[1..4]) & 0x000fffff
     }
@@ -265,6 +542,25 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
     pub fn payload(&self) -> &'a [u8] {
         &self.inner.as_ref()[Self::IP_HEADER_END..]
     }
+    // payload cut to payload length, without link layer padding
+    fn payload_checked(&self) -> &'a [u8] {
+        let payload = self.payload();
+        &payload[..payload.len().min(self.payload_len() as usize)]
+    }
+    pub fn ext_headers(&self) -> ExtHeaderIter<'a> {
+        ExtHeaderIter::new(self.next_header(), self.payload_checked())
+    }
+    // first Segment Routing Header in the chain
+    pub fn srh(&self) -> Option<Srh<&'a [u8]>> {
+        self.ext_headers().find_map(|header| match header {
+            Ok(ExtHeader::Routing(routing)) => Srh::new_checked(routing.into_inner()),
+            _ => None,
+        })
+    }
+    // protocol and data after all extension headers
+    pub fn upper_layer(&self) -> Result<(Protocol, &'a [u8]), ExtHeaderError> {
+        ext_header::upper_layer(self.next_header(), self.payload_checked())
+    }
 }
 
 impl<T: AsMut<[u8]>> Packet<T> {
@@ -278,6 +574,18 @@ impl<T: AsMut<[u8]>> Packet<T> {
         data[0] = (data[0] & !0x0f) | ((traffic_class & 0xf0) >> 4);
         data[1] = (data[1] & !0xf0) | ((traffic_class & 0x0f) << 4);
     }
+    pub fn set_dscp(&mut self, dscp: Dscp) {
+        let traffic_class = self.traffic_class_bits();
+        self.set_traffic_class((traffic_class & 0x03) | (u8::from(dscp) << 2))
+    }
+    pub fn set_ecn(&mut self, ecn: Ecn) {
+        let traffic_class = self.traffic_class_bits();
+        self.set_traffic_class((traffic_class & !0x03) | u8::from(ecn))
+    }
+    fn traffic_class_bits(&mut self) -> u8 {
+        let data = &self.inner.as_mut()[Self::VER_TC_FLOW];
+        (data[0] << 4) | (data[1] >> 4)
+    }
     pub fn set_flow_label(&mut self, flow_label: u32) {
         debug_assert!(flow_label <= 0x000fffff);
         let data = &mut self.inner.as_mut()[Self::VER_TC_FLOW];
@@ -302,7 +610,7 @@ impl<T: AsMut<[u8]>> Packet<T> {
     }
 }
 
-impl<'a, T: AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
+impl<T: AsMut<[u8]> + ?Sized> Packet<&mut T> {
     pub fn payload_mut(&mut self) -> &mut [u8] {
         &mut self.inner.as_mut()[Self::IP_HEADER_END..]
     }
@@ -312,14 +620,48 @@ impl<'a, T: AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Protocol {
+    HopByHop, // 0x00, IPv6 Hop-by-Hop Options
+    Icmp, // 0x01, ICMP for IPv4
+    Igmp, // 0x02
+    Ipv4, // 0x04, IPv4 encapsulation
+    Tcp, // 0x06
+    Udp, // 0x11
+    Ipv6, // 0x29, IPv6 encapsulation
+    Routing, // 0x2B, Routing Header for IPv6
+    Fragment, // 0x2C, Fragment Header for IPv6
+    Esp, // 0x32, Encapsulating Security Payload
+    Ah, // 0x33, Authentication Header
     Icmpv6, // 0x3A, ICMP v6
+    NoNextHeader, // 0x3B, No Next Header for IPv6
+    DestinationOptions, // 0x3C, Destination Options for IPv6
     Unknown(u8),
 }
 
+impl Protocol {
+    // headers walked by the extension header chain; ESP is opaque and ends the chain
+    pub fn is_extension_header(self) -> bool {
+        matches!(self, Protocol::HopByHop | Protocol::Routing | Protocol::Fragment |
+            Protocol::Ah | Protocol::NoNextHeader | Protocol::DestinationOptions)
+    }
+}
+
 impl From<u8> for Protocol {
     fn from(src: u8) -> Self {
         match src {
+            0x00 => Protocol::HopByHop,
+            0x01 => Protocol::Icmp,
+            0x02 => Protocol::Igmp,
+            0x04 => Protocol::Ipv4,
+            0x06 => Protocol::Tcp,
+            0x11 => Protocol::Udp,
+            0x29 => Protocol::Ipv6,
+            0x2B => Protocol::Routing,
+            0x2C => Protocol::Fragment,
+            0x32 => Protocol::Esp,
+            0x33 => Protocol::Ah,
             0x3A => Protocol::Icmpv6,
+            0x3B => Protocol::NoNextHeader,
+            0x3C => Protocol::DestinationOptions,
             others => Protocol::Unknown(others),
         }
     }
@@ -328,14 +670,27 @@ impl From<u8> for Protocol {
 impl From<Protocol> for u8 {
     fn from(src: Protocol) -> u8 {
         match src {
+            Protocol::HopByHop => 0x00,
+            Protocol::Icmp => 0x01,
+            Protocol::Igmp => 0x02,
+            Protocol::Ipv4 => 0x04,
+            Protocol::Tcp => 0x06,
+            Protocol::Udp => 0x11,
+            Protocol::Ipv6 => 0x29,
+            Protocol::Routing => 0x2B,
+            Protocol::Fragment => 0x2C,
+            Protocol::Esp => 0x32,
+            Protocol::Ah => 0x33,
             Protocol::Icmpv6 => 0x3A,
+            Protocol::NoNextHeader => 0x3B,
+            Protocol::DestinationOptions => 0x3C,
             Protocol::Unknown(others) => others
         }
     }
 }
 
 // for example: FE80::/10
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Subnet {
     network: Address, // any bits beyond the prefix should be 0
     prefix: u8,
@@ -348,8 +703,10 @@ impl Subnet {
         assert!(network.repr.trailing_zeros() >= 128 - prefix as u32);
         unsafe { Self::new_unchecked(network, prefix) }
     }
-    // variable 'network' must have last '128 - prefix' bits set to zero
-    // this is not checked here so it's unsafe
+    /// # Safety
+    ///
+    /// variable 'network' must have last '128 - prefix' bits set to zero
+    /// this is not checked here so it's unsafe
     pub const unsafe fn new_unchecked(network: Address, prefix: u8) -> Self {
         Self { network, prefix }
     }
@@ -370,6 +727,86 @@ impl Subnet {
     pub fn prefix(self) -> u8 {
         self.prefix
     }
+    // None if prefix is longer than 128 or host bits are set
+    pub const fn new_checked(network: Address, prefix: u8) -> Option<Subnet> {
+        if prefix > 128 || network.repr & !mask(prefix) != 0 {
+            return None
+        }
+        Some(Subnet { network, prefix })
+    }
+    // subnet containing address, host bits cleared
+    pub const fn from_address(address: Address, prefix: u8) -> Subnet {
+        assert!(prefix <= 128);
+        Subnet { network: Address { repr: address.repr & mask(prefix) }, prefix }
+    }
+    // prefix bits set, for example FFFF:FFFF:: for /32
+    pub fn netmask(self) -> Address {
+        Address { repr: mask(self.prefix) }
+    }
+    // host bits set, for example ::FFFF:FFFF for /96
+    pub fn hostmask(self) -> Address {
+        Address { repr: !mask(self.prefix) }
+    }
+    // host part of an address in this subnet
+    pub fn host_part(self, address: Address) -> Address {
+        Address { repr: address.repr & !mask(self.prefix) }
+    }
+    // address with given host part, None if host does not fit
+    pub fn host(self, host: u128) -> Option<Address> {
+        if host & mask(self.prefix) != 0 {
+            return None
+        }
+        Some(Address { repr: self.network.repr | host })
+    }
+    pub fn first(self) -> Address {
+        self.network
+    }
+    pub fn last(self) -> Address {
+        Address { repr: self.network.repr | !mask(self.prefix) }
+    }
+    pub const fn contains(self, address: Address) -> bool {
+        address.repr & mask(self.prefix) == self.network.repr
+    }
+    pub fn contains_subnet(self, other: Subnet) -> bool {
+        self.prefix <= other.prefix && self.contains(other.network)
+    }
+    // subnets sharing any address are nested in one another
+    pub fn overlaps(self, other: Subnet) -> bool {
+        self.contains_subnet(other) || other.contains_subnet(self)
+    }
+    // split into subnets of longer prefix, None if prefix is shorter or over 128
+    pub fn subnets(self, prefix: u8) -> Option<Subnets> {
+        if prefix < self.prefix || prefix > 128 {
+            return None
+        }
+        let step = 128 - prefix as u32;
+        // ::/0 into /0 is itself, and a shift by 128 would overflow
+        let count = if step == 128 { 0 } else { (self.last().repr >> step) - (self.network.repr >> step) };
+        Some(Subnets { next: Some(self.network.repr), step, remaining: count, prefix })
+    }
+    // enclosing subnet of shorter prefix, None if prefix is longer
+    pub fn supernet(self, prefix: u8) -> Option<Subnet> {
+        if prefix > self.prefix {
+            return None
+        }
+        Some(Subnet::from_address(self.network, prefix))
+    }
+    // smallest subnet containing both
+    pub fn common_supernet(self, other: Subnet) -> Subnet {
+        let common = (self.network.repr ^ other.network.repr).leading_zeros() as u8;
+        Subnet::from_address(self.network, common.min(self.prefix).min(other.prefix))
+    }
+    // number of addresses, saturating at u128::MAX for ::/0
+    pub fn size(self) -> u128 {
+        (!mask(self.prefix)).saturating_add(1)
+    }
+    pub fn addresses(self) -> Addresses {
+        Addresses { next: Some(self.network.repr), last: self.last().repr }
+    }
+}
+
+const fn mask(prefix: u8) -> u128 {
+    if prefix == 0 { 0 } else { u128::MAX << (128 - prefix as u32) }
 }
 
 impl fmt::Display for Subnet {
@@ -378,48 +815,276 @@ impl fmt::Display for Subnet {
     }
 }
 
+impl FromStr for Subnet {
+    type Err = ParseSubnetError;
+    // for example "2001:db8::/32"; host bits must be zero
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (network, prefix) = s.split_once('/').ok_or(ParseSubnetError(()))?;
+        let network: Address = network.parse().map_err(|_| ParseSubnetError(()))?;
+        let prefix = super::ipv4::parse_prefix(prefix, 128).ok_or(ParseSubnetError(()))?;
+        Subnet::new_checked(network, prefix).ok_or(ParseSubnetError(()))
+    }
+}
+
+/// IPv6 subnet parse error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseSubnetError(());
+
+// subnets of equal prefix length in address order
+#[derive(Debug, Clone)]
+pub struct Subnets {
+    next: Option<u128>,
+    step: u32,
+    // subnets after next
+    remaining: u128,
+    prefix: u8,
+}
+
+impl Iterator for Subnets {
+    type Item = Subnet;
+    fn next(&mut self) -> Option<Subnet> {
+        let repr = self.next?;
+        self.next = if self.remaining == 0 { None } else {
+            self.remaining -= 1;
+            Some(repr + (1 << self.step))
+        };
+        Some(Subnet { network: Address { repr }, prefix: self.prefix })
+    }
+}
+
+// addresses of a subnet in order
+#[derive(Debug, Clone)]
+pub struct Addresses {
+    next: Option<u128>,
+    last: u128,
+}
+
+impl Iterator for Addresses {
+    type Item = Address;
+    fn next(&mut self) -> Option<Address> {
+        let repr = self.next?;
+        self.next = if repr == self.last { None } else { Some(repr + 1) };
+        Some(Address { repr })
+    }
+}
+
+// set of addresses kept as sorted, non-adjacent inclusive ranges;
+// iterates as the fewest subnets covering exactly the set
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SubnetSet {
+    ranges: Vec<(u128, u128)>,
+}
+
+impl SubnetSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+    pub fn insert(&mut self, subnet: Subnet) {
+        let (mut start, mut end) = (subnet.network.repr, subnet.last().repr);
+        // ranges overlapping or adjacent to new one are merged into it
+        self.ranges.retain(|&(lo, hi)| {
+            let touches = lo <= end.saturating_add(1) && start <= hi.saturating_add(1);
+            if touches {
+                start = start.min(lo);
+                end = end.max(hi);
+            }
+            !touches
+        });
+        let idx = self.ranges.partition_point(|&(lo, _)| lo < start);
+        self.ranges.insert(idx, (start, end));
+    }
+    pub fn remove(&mut self, subnet: Subnet) {
+        let (start, end) = (subnet.network.repr, subnet.last().repr);
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 1);
+        for &(lo, hi) in &self.ranges {
+            if hi < start || lo > end {
+                ranges.push((lo, hi));
+                continue
+            }
+            if lo < start {
+                ranges.push((lo, start - 1));
+            }
+            if hi > end {
+                ranges.push((end + 1, hi));
+            }
+        }
+        self.ranges = ranges;
+    }
+    pub fn contains(&self, address: Address) -> bool {
+        let idx = self.ranges.partition_point(|&(_, hi)| hi < address.repr);
+        matches!(self.ranges.get(idx), Some(&(lo, _)) if lo <= address.repr)
+    }
+    pub fn contains_subnet(&self, subnet: Subnet) -> bool {
+        let idx = self.ranges.partition_point(|&(_, hi)| hi < subnet.network.repr);
+        matches!(self.ranges.get(idx), Some(&(lo, hi)) if lo <= subnet.network.repr && subnet.last().repr <= hi)
+    }
+    pub fn union(&self, other: &SubnetSet) -> SubnetSet {
+        let mut set = self.clone();
+        other.iter().for_each(|subnet| set.insert(subnet));
+        set
+    }
+    pub fn difference(&self, other: &SubnetSet) -> SubnetSet {
+        let mut set = self.clone();
+        other.iter().for_each(|subnet| set.remove(subnet));
+        set
+    }
+    pub fn iter(&self) -> impl Iterator<Item = Subnet> + '_ {
+        self.ranges.iter().flat_map(|&(start, end)| RangeSubnets { next: Some(start), end })
+    }
+}
+
+impl FromIterator<Subnet> for SubnetSet {
+    fn from_iter<I: IntoIterator<Item = Subnet>>(iter: I) -> Self {
+        let mut set = SubnetSet::new();
+        iter.into_iter().for_each(|subnet| set.insert(subnet));
+        set
+    }
+}
+
+impl Extend<Subnet> for SubnetSet {
+    fn extend<I: IntoIterator<Item = Subnet>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|subnet| self.insert(subnet));
+    }
+}
+
+// largest aligned blocks covering an inclusive range
+struct RangeSubnets {
+    next: Option<u128>,
+    end: u128,
+}
+
+impl Iterator for RangeSubnets {
+    type Item = Subnet;
+    fn next(&mut self) -> Option<Subnet> {
+        let start = self.next?;
+        let align = start.trailing_zeros();
+        let span = match (self.end - start).checked_add(1) {
+            Some(len) => 127 - len.leading_zeros(),
+            None => 128,
+        };
+        let bits = align.min(span);
+        let last = if bits == 128 { u128::MAX } else { start + ((1u128 << bits) - 1) };
+        self.next = if last >= self.end { None } else { Some(last + 1) };
+        Some(Subnet { network: Address { repr: start }, prefix: 128 - bits as u8 })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Address, Subnet};
+    use super::{Address, MulticastScope, Packet, Protocol, ScopedAddress, Subnet, SubnetSet, Zone};
+    use crate::net::{Dscp, Ecn};
+    use std::net::{Ipv6Addr, SocketAddrV6};
     #[test]
     fn ip_address_print() {
         assert_eq!("::", Address::from([0, 0, 0, 0, 0, 0, 0, 0]).to_string());
         assert_eq!("::1", Address::from([0, 0, 0, 0, 0, 0, 0, 1]).to_string());
-        assert_eq!("FE80::1234:5678", Address::from([0xfe80, 0, 0, 0, 0, 0, 0x1234, 0x5678]).to_string());
-        assert_eq!("FF01::101", Address::from([0xff01, 0, 0, 0, 0, 0, 0, 0x101]).to_string());
-        assert_eq!("2001:DB8::8:800:200C:417A", Address::from([0x2001, 0xdb8, 0, 0, 8, 0x800, 0x200c, 0x417a]).to_string());
-        assert_eq!("2001:DB8:0:CD30::", Address::from([0x2001, 0xdb8, 0, 0xcd30, 0, 0, 0, 0]).to_string());
+        assert_eq!("fe80::1234:5678", Address::from([0xfe80, 0, 0, 0, 0, 0, 0x1234, 0x5678]).to_string());
+        assert_eq!("ff01::101", Address::from([0xff01, 0, 0, 0, 0, 0, 0, 0x101]).to_string());
+        assert_eq!("2001:db8::8:800:200c:417a", Address::from([0x2001, 0xdb8, 0, 0, 8, 0x800, 0x200c, 0x417a]).to_string());
+        assert_eq!("2001:db8:0:cd30::", Address::from([0x2001, 0xdb8, 0, 0xcd30, 0, 0, 0, 0]).to_string());
+        // a single zero group is not compressed, the first of equal runs is
+        assert_eq!("2001:db8:0:1:1:1:1:1", Address::from([0x2001, 0xdb8, 0, 1, 1, 1, 1, 1]).to_string());
+        assert_eq!("2001:0:0:1::1", Address::from([0x2001, 0, 0, 1, 0, 0, 0, 1]).to_string());
+        assert_eq!("2001:db8::1:0:0:1", Address::from([0x2001, 0xdb8, 0, 0, 1, 0, 0, 1]).to_string());
+        assert_eq!("::ffff:192.0.2.1", Address::from([0, 0, 0, 0, 0, 0xffff, 0xc000, 0x201]).to_string());
+        assert_eq!("64:ff9b::198.51.100.7", Address::from([0x64, 0xff9b, 0, 0, 0, 0, 0xc633, 0x6407]).to_string());
+        assert_eq!("::c000:201", Address::from([0, 0, 0, 0, 0, 0, 0xc000, 0x201]).to_string());
+        assert_eq!("FE80::1234:ABCD", format!("{:X}", Address::from([0xfe80, 0, 0, 0, 0, 0, 0x1234, 0xabcd])));
+        assert_eq!("::FFFF:192.0.2.1", format!("{:X}", Address::from([0, 0, 0, 0, 0, 0xffff, 0xc000, 0x201])));
     }
     #[test]
     fn ip_address_parse() {
         let addrs = [
             "::",
             "::1",
-            "FE80::1234:5678", 
-            "FF01::101", 
-            "2001:DB8::8:800:200C:417A", 
-            "2001:DB8:0:CD30::",
-            "FD12:3456:7890:ABCD:1122:3344:5566:7788", 
-            "FD12:3456:7890:ABCD:1122:3344:5566::", 
-            "FD12:3456:7890:ABCD:1122:3344::", 
-            "::3456:7890:ABCD:1122:3344:5566:7788", 
-            "::7890:ABCD:1122:3344:5566:7788", 
+            "fe80::1234:5678",
+            "ff01::101",
+            "2001:db8::8:800:200c:417a",
+            "2001:db8:0:cd30::",
+            "fd12:3456:7890:abcd:1122:3344:5566:7788",
+            "fd12:3456:7890:abcd:1122:3344:5566:0",
+            "fd12:3456:7890:abcd:1122:3344::",
+            "0:3456:7890:abcd:1122:3344:5566:7788",
+            "::7890:abcd:1122:3344:5566:7788",
+            "::ffff:192.0.2.1",
+            "64:ff9b::198.51.100.7",
         ];
         for addr_str in addrs {
             let addr = addr_str.parse::<Address>().unwrap();
             assert_eq!(addr_str, addr.to_string());
         }
+        let equivalent = [
+            ("FE80::1234:5678", "fe80::1234:5678"),
+            ("2001:0db8:0000:0000:0000:0000:0000:0001", "2001:db8::1"),
+            ("2001:db8:0:0:1:0:0:1", "2001:db8::1:0:0:1"),
+            ("2001:db8::0:1", "2001:db8::1"),
+            ("::ffff:c000:201", "::ffff:192.0.2.1"),
+            ("0:0:0:0:0:ffff:192.0.2.1", "::ffff:192.0.2.1"),
+            ("::192.0.2.1", "::c000:201"),
+            ("1:2:3:4:5:6:1.2.3.4", "1:2:3:4:5:6:102:304"),
+            ("1::6:1.2.3.4", "1::6:102:304"),
+        ];
+        for (addr_str, canonical) in equivalent {
+            assert_eq!(addr_str.parse::<Address>().unwrap().to_string(), canonical);
+        }
         let wrong_addrs = [
+            "",
+            ":",
+            ":::",
             "::1::",
-            "FF01:::101", 
+            "FF01:::101",
             "2001:DB8::8:800::200C:417A",
-            ":FD12:3456:7890:ABCD:1122:3344:5566:7788", 
-            "FD12:3456:7890:ABCD:1122:3344:5566:7788:", 
-            "FD12:3456:7890:ABCD:1122:3344:5566:7788:9", 
-            "FD12:3456:7890:ABCD:1122:3344:5566:EFGH", 
+            ":FD12:3456:7890:ABCD:1122:3344:5566:7788",
+            "FD12:3456:7890:ABCD:1122:3344:5566:7788:",
+            "FD12:3456:7890:ABCD:1122:3344:5566:7788:9",
+            "FD12:3456:7890:ABCD:1122:3344:5566:EFGH",
+            "1:2:3:4:5:6:7::8:9",
+            "1:2:3:4:5:6:7",
+            "12345::",
+            "::+1",
+            "::192.0.2.1:1",
+            "192.0.2.1::",
+            "::ffff:192.0.2",
+            "::ffff:192.0.2.256",
+            "::ffff:192.0.02.1",
+            "1:2:3:4:5:6:7:1.2.3.4",
+            "fe80::1%eth0",
         ];
         for addr_str in wrong_addrs {
-            assert!(addr_str.parse::<Address>().is_err());
+            assert!(addr_str.parse::<Address>().is_err(), "{}", addr_str);
+        }
+    }
+    #[test]
+    fn ip_address_round_trip() {
+        // every placement of zero runs, with and without embedded IPv4
+        for mask in 0u32..256 {
+            let mut segments = [0u16; 8];
+            for (idx, segment) in segments.iter_mut().enumerate() {
+                if mask & (1 << idx) != 0 {
+                    *segment = 0x1000 * idx as u16 + 0xa1;
+                }
+            }
+            let mapped = [0, 0, 0, 0, 0, 0xffff];
+            let translated = [0x64, 0xff9b, 0, 0, 0, 0];
+            for prefix in [None, Some(mapped), Some(translated)].iter() {
+                let mut segments = segments;
+                if let Some(prefix) = prefix {
+                    segments[..6].copy_from_slice(prefix);
+                }
+                let address = Address::from(segments);
+                let text = address.to_string();
+                assert_eq!(text.parse::<Address>(), Ok(address), "{}", text);
+                assert_eq!(format!("{:X}", address).parse::<Address>(), Ok(address));
+                assert_eq!(format!("{:X}", address).to_lowercase(), text);
+                assert!(!text.split(':').any(|group| group.len() > 1 && group.starts_with('0') && !group.contains('.')));
+                // std follows RFC 5952 too, but keeps hex groups for translated addresses
+                if !address.is_ipv4_translated() {
+                    assert_eq!(text, Ipv6Addr::from(address).to_string());
+                }
+            }
         }
     }
     #[test]
@@ -435,9 +1100,179 @@ mod tests {
     }
     #[test]
     fn ip_subnet_print() {
-        assert_eq!("FF00::/8", Subnet::MULTICAST.to_string());
-        assert_eq!("FE80::/10", Subnet::LINK_LOCAL_UNICAST.to_string());
+        assert_eq!("ff00::/8", Subnet::MULTICAST.to_string());
+        assert_eq!("fe80::/10", Subnet::LINK_LOCAL_UNICAST.to_string());
         assert_eq!("::1/128", Subnet::LOOPBACK.to_string());
         assert_eq!("::/128", Subnet::UNSPECIFIED.to_string());
     }
+    #[test]
+    fn ip_address_classify() {
+        let addr = |s: &str| s.parse::<Address>().unwrap();
+        assert!(addr("fe80::1").is_link_local() && !addr("fe80::1").is_global());
+        assert!(addr("fd12:3456::1").is_unique_local() && !addr("fd12:3456::1").is_global());
+        assert!(addr("2001:db8::1").is_documentation() && addr("3fff::1").is_documentation());
+        assert!(addr("2001:2::1").is_benchmarking());
+        assert!(addr("::ffff:c000:201").is_ipv4_mapped());
+        assert_eq!(addr("::ffff:c000:201").to_ipv4().unwrap().to_string(), "192.0.2.1");
+        assert_eq!(Address::to_ipv4_mapped("192.0.2.1".parse().unwrap()), addr("::ffff:c000:201"));
+        assert!(addr("64:ff9b::c633:6407").is_ipv4_translated());
+        assert!(addr("2001:db8::1").to_ipv4().is_none());
+        assert!(addr("2606:4700::1111").is_global());
+        assert!(addr("2001:1::1").is_global() && !addr("2001::1").is_global());
+        assert!(!addr("4000::1").is_global());
+        assert!(Address::LOOPBACK.is_loopback() && !Address::LOOPBACK.is_global());
+        assert!(Address::UNSPECIFIED.is_unspecified() && !Address::UNSPECIFIED.is_unicast());
+        let solicited = addr("fe80::215:5dff:feb7:9fa9").solicited_node();
+        assert_eq!(solicited, addr("ff02::1:ffb7:9fa9"));
+        assert!(solicited.is_solicited_node() && !addr("ff02::1").is_solicited_node());
+    }
+    #[test]
+    fn ip_address_multicast() {
+        let addr = |s: &str| s.parse::<Address>().unwrap();
+        assert_eq!(addr("ff01::1").multicast_scope(), Some(MulticastScope::InterfaceLocal));
+        assert_eq!(addr("ff02::1").multicast_scope(), Some(MulticastScope::LinkLocal));
+        assert_eq!(addr("ff05::2").multicast_scope(), Some(MulticastScope::SiteLocal));
+        assert_eq!(addr("ff0e::101").multicast_scope(), Some(MulticastScope::Global));
+        assert_eq!(addr("ff00::").multicast_scope(), Some(MulticastScope::Unknown(0)));
+        assert_eq!(addr("fe80::").multicast_scope(), None);
+        assert!(addr("ff0e::101").is_global() && !addr("ff05::2").is_global());
+        let flags = addr("ff3e:30:2001:db8::1").multicast_flags().unwrap();
+        assert!(flags.transient() && flags.prefix_based() && !flags.rp_embedded());
+        assert!(addr("ff7e:140:2001:db8::1").multicast_flags().unwrap().rp_embedded());
+        assert!(!addr("ff02::1").multicast_flags().unwrap().transient());
+    }
+    #[test]
+    fn ip_subnet_parse_contains() {
+        let subnet: Subnet = "2001:db8::/32".parse().unwrap();
+        assert_eq!(subnet, Subnet::new(Address::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 0]), 32));
+        assert!(subnet.contains("2001:db8:ffff::1".parse().unwrap()));
+        assert!(!subnet.contains("2001:db9::".parse().unwrap()));
+        assert!(subnet.contains_subnet("2001:db8:1::/48".parse().unwrap()));
+        assert!(!subnet.contains_subnet("2001:db8::/31".parse().unwrap()));
+        assert!("::/0".parse::<Subnet>().unwrap().contains(Address::LOOPBACK));
+        for subnet_str in ["2001:db8::1/32", "2001:db8::/129", "2001:db8::/032", "2001:db8::", "/32", "2001:db8::/+3"] {
+            assert!(subnet_str.parse::<Subnet>().is_err(), "{}", subnet_str);
+        }
+        assert!(Subnet::new_checked(Address::LOOPBACK, 127).is_none());
+        assert_eq!(Subnet::from_address(Address::LOOPBACK, 127), Subnet::UNSPECIFIED.supernet(127).unwrap());
+    }
+    #[test]
+    fn ip_subnet_masks() {
+        let subnet: Subnet = "fd00:1234::/96".parse().unwrap();
+        assert_eq!(subnet.netmask().to_string(), "ffff:ffff:ffff:ffff:ffff:ffff::");
+        assert_eq!(subnet.hostmask().to_string(), "::ffff:ffff");
+        assert_eq!(subnet.host_part("fd00:1234::c0a8:101".parse().unwrap()).to_string(), "::c0a8:101");
+        assert_eq!(subnet.host(0x0102_0304).unwrap().to_string(), "fd00:1234::102:304");
+        assert!(subnet.host(1 << 32).is_none());
+        assert_eq!(subnet.last().to_string(), "fd00:1234::ffff:ffff");
+        assert_eq!(subnet.size(), 1 << 32);
+        assert_eq!("::/0".parse::<Subnet>().unwrap().size(), u128::MAX);
+    }
+    #[test]
+    fn ip_subnet_split_aggregate() {
+        let subnet: Subnet = "2001:db8::/46".parse().unwrap();
+        let subnets: Vec<String> = subnet.subnets(48).unwrap().map(|subnet| subnet.to_string()).collect();
+        assert_eq!(subnets, ["2001:db8::/48", "2001:db8:1::/48", "2001:db8:2::/48", "2001:db8:3::/48"]);
+        assert!(subnet.subnets(45).is_none());
+        assert_eq!("::/0".parse::<Subnet>().unwrap().subnets(1).unwrap().count(), 2);
+        let all: Subnet = "::/0".parse().unwrap();
+        assert_eq!(all.subnets(0).unwrap().collect::<Vec<_>>(), [all]);
+        assert_eq!(subnet.supernet(32).unwrap().to_string(), "2001:db8::/32");
+        assert!(subnet.supernet(47).is_none());
+        let a: Subnet = "2001:db8:1::/48".parse().unwrap();
+        let b: Subnet = "2001:db8:2::/48".parse().unwrap();
+        assert_eq!(a.common_supernet(b).to_string(), "2001:db8::/46");
+        let addresses: Vec<Address> = "fe80::/126".parse::<Subnet>().unwrap().addresses().collect();
+        assert_eq!(addresses.len(), 4);
+        assert_eq!(addresses[3].to_string(), "fe80::3");
+        assert_eq!(Subnet::LOOPBACK.addresses().count(), 1);
+    }
+    #[test]
+    fn ip_subnet_set() {
+        let parse = |s: &str| s.parse::<Subnet>().unwrap();
+        let mut set: SubnetSet = ["2001:db8::/48", "2001:db8:1::/48", "2001:db8:2::/47"].iter().map(|s| parse(s)).collect();
+        assert_eq!(set.iter().collect::<Vec<_>>(), [parse("2001:db8::/46")]);
+        set.remove(parse("2001:db8:1::/64"));
+        let subnets: Vec<String> = set.iter().map(|subnet| subnet.to_string()).collect();
+        assert_eq!(subnets.first().unwrap(), "2001:db8::/48");
+        assert_eq!(subnets.last().unwrap(), "2001:db8:2::/47");
+        assert_eq!(subnets.len(), 2 + 16);
+        assert!(!set.contains("2001:db8:1::5".parse().unwrap()));
+        assert!(set.contains("2001:db8:1:1::5".parse().unwrap()));
+        assert!(set.contains_subnet(parse("2001:db8:2::/48")));
+        assert!(!set.contains_subnet(parse("2001:db8::/46")));
+        let rest = set.difference(&[parse("2001:db8::/47")].iter().copied().collect());
+        assert_eq!(rest.iter().collect::<Vec<_>>(), [parse("2001:db8:2::/47")]);
+        let all = rest.union(&[parse("::/1"), parse("8000::/1")].iter().copied().collect());
+        assert_eq!(all.iter().collect::<Vec<_>>(), [parse("::/0")]);
+        let mut empty = all.clone();
+        empty.remove(parse("::/0"));
+        assert!(empty.is_empty());
+    }
+    #[test]
+    fn ip_packet_upper_layer() {
+        let mut buf = [0u8; 40 + 16 + 4];
+        let mut packet = Packet::new(&mut buf[..]);
+        packet.set_version(6);
+        packet.set_next_header(Protocol::DestinationOptions);
+        packet.set_payload_len(16);
+        packet.payload_mut()[..10].copy_from_slice(&[0x3a, 0, 1, 4, 0, 0, 0, 0, 0x80, 0]);
+        // trailing bytes are link layer padding
+        let packet = Packet::new(&buf[..]);
+        assert_eq!(packet.ext_headers().count(), 1);
+        assert_eq!(packet.upper_layer(), Ok((Protocol::Icmpv6, &buf[48..56])));
+    }
+
+    #[test]
+    fn ip_address_std_scoped() {
+        let std_addr: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let address = Address::from(std_addr);
+        assert_eq!(address, "2001:db8::1".parse().unwrap());
+        assert_eq!(Ipv6Addr::from(address), std_addr);
+        assert!(Address::LOOPBACK < address && address < Address::from_bits(u128::MAX));
+
+        let scoped: ScopedAddress = "fe80::1%eth0".parse().unwrap();
+        assert_eq!(scoped.address(), "fe80::1".parse().unwrap());
+        assert_eq!(scoped.zone(), Some(&Zone::Name("eth0".to_string())));
+        assert_eq!(scoped.to_string(), "fe80::1%eth0");
+        let scoped: ScopedAddress = "fe80::1%3".parse().unwrap();
+        assert_eq!(scoped.zone(), Some(&Zone::Index(3)));
+        assert_eq!(scoped.scope_id().unwrap(), 3);
+        let socket_addr = scoped.to_socket_addr(546).unwrap();
+        assert_eq!(socket_addr.scope_id(), 3);
+        assert_eq!(ScopedAddress::from(socket_addr), scoped);
+        assert_eq!(ScopedAddress::from(SocketAddrV6::new(std_addr, 0, 0, 0)), address.into());
+        assert_eq!("::1".parse::<ScopedAddress>().unwrap().scope_id().unwrap(), 0);
+        assert!("fe80::1%".parse::<ScopedAddress>().is_err());
+        assert!("fe80::1%1%2".parse::<ScopedAddress>().is_err());
+        assert!("fe80::1%99999999999".parse::<ScopedAddress>().is_err());
+        assert!("%eth0".parse::<ScopedAddress>().is_err());
+    }
+
+    #[test]
+    #[ignore = "queries interfaces of the host"]
+    fn ip_address_scoped_resolve() {
+        let lo: ScopedAddress = "fe80::1%lo".parse().unwrap();
+        assert!(matches!(lo.resolve().unwrap().zone(), Some(&Zone::Index(_))));
+        assert!("fe80::1%nosuchif0".parse::<ScopedAddress>().unwrap().scope_id().is_err());
+    }
+
+    #[test]
+    fn ip_packet_traffic_class() {
+        let mut buf = [0u8; 40];
+        let mut packet = Packet::new(&mut buf[..]);
+        packet.set_version(6);
+        packet.set_flow_label(0xabcde);
+        packet.set_dscp(Dscp::Af21);
+        packet.set_ecn(Ecn::Ect0);
+        let packet = Packet::new(&buf[..]);
+        assert_eq!(packet.traffic_class(), 18 << 2 | 2);
+        assert_eq!((packet.dscp(), packet.ecn()), (Dscp::Af21, Ecn::Ect0));
+        let mut packet = Packet::new(&mut buf[..]);
+        packet.set_ecn(Ecn::Ce);
+        packet.set_dscp(Dscp::Ef);
+        let packet = Packet::new(&buf[..]);
+        assert_eq!((packet.dscp(), packet.ecn()), (Dscp::Ef, Ecn::Ce));
+        assert_eq!((packet.version(), packet.flow_label()), (6, 0xabcde));
+    }
 }
@@ -0,0 +1,726 @@
+// IPv4 address and header: See https://tools.ietf.org/html/rfc791
+
+use super::checksum;
+use super::ip::Protocol;
+use super::traffic_class::{Dscp, Ecn};
+use crate::packet_write::{PacketWrite, PacketWriter};
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+use core::ops::Range;
+use core::str::FromStr;
+use std::net::Ipv4Addr;
+
+// Ipv4 address
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Address {
+    repr: u32,
+}
+
+impl Address {
+    pub const UNSPECIFIED: Self = Address::new(0, 0, 0, 0);
+    pub const LOOPBACK: Self = Address::new(127, 0, 0, 1);
+    pub const BROADCAST: Self = Address::new(255, 255, 255, 255);
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Address {
+        Address { repr: u32::from_be_bytes([a, b, c, d]) }
+    }
+    pub fn from_bytes(src: &[u8]) -> Address {
+        Address { repr: NetworkEndian::read_u32(src) }
+    }
+    pub const fn octets(self) -> [u8; 4] {
+        self.repr.to_be_bytes()
+    }
+    pub const fn to_bits(self) -> u32 {
+        self.repr
+    }
+    pub const fn from_bits(repr: u32) -> Address {
+        Address { repr }
+    }
+    const fn in_block(self, network: u32, prefix: u32) -> bool {
+        (self.repr ^ network) >> (32 - prefix) == 0
+    }
+    // 0.0.0.0
+    pub const fn is_unspecified(self) -> bool {
+        self.repr == 0
+    }
+    // 127.0.0.0/8
+    pub const fn is_loopback(self) -> bool {
+        self.in_block(0x7f00_0000, 8)
+    }
+    // 10.0.0.0/8, 172.16.0.0/12 and 192.168.0.0/16, RFC 1918
+    pub const fn is_private(self) -> bool {
+        self.in_block(0x0a00_0000, 8) || self.in_block(0xac10_0000, 12) || self.in_block(0xc0a8_0000, 16)
+    }
+    // 100.64.0.0/10, carrier-grade NAT, RFC 6598
+    pub const fn is_shared(self) -> bool {
+        self.in_block(0x6440_0000, 10)
+    }
+    // 169.254.0.0/16, RFC 3927
+    pub const fn is_link_local(self) -> bool {
+        self.in_block(0xa9fe_0000, 16)
+    }
+    // 224.0.0.0/4
+    pub const fn is_multicast(self) -> bool {
+        self.in_block(0xe000_0000, 4)
+    }
+    // 255.255.255.255; directed broadcast depends on the subnet and is not detected
+    pub const fn is_broadcast(self) -> bool {
+        self.repr == u32::MAX
+    }
+    // 192.0.2.0/24, 198.51.100.0/24 and 203.0.113.0/24, RFC 5737
+    pub const fn is_documentation(self) -> bool {
+        self.in_block(0xc000_0200, 24) || self.in_block(0xc633_6400, 24) || self.in_block(0xcb00_7100, 24)
+    }
+    // 198.18.0.0/15, RFC 2544
+    pub const fn is_benchmarking(self) -> bool {
+        self.in_block(0xc612_0000, 15)
+    }
+    // 240.0.0.0/4 except broadcast
+    pub const fn is_reserved(self) -> bool {
+        self.in_block(0xf000_0000, 4) && !self.is_broadcast()
+    }
+    pub const fn is_unicast(self) -> bool {
+        !(self.is_multicast() || self.is_broadcast() || self.is_unspecified())
+    }
+    // routable on the public Internet
+    pub const fn is_global(self) -> bool {
+        // 0.0.0.0/8 is "this network"
+        !(self.in_block(0, 8) || self.is_loopback() || self.is_private() || self.is_shared() ||
+            self.is_link_local() || self.is_documentation() || self.is_benchmarking() ||
+            self.is_reserved() || self.is_broadcast() || self.in_block(0xc000_0000, 24))
+    }
+}
+
+impl From<[u8; 4]> for Address {
+    #[inline] fn from(octets: [u8; 4]) -> Address {
+        Address::from_bytes(&octets)
+    }
+}
+
+impl From<Address> for [u8; 4] {
+    #[inline] fn from(address: Address) -> [u8; 4] {
+        address.octets()
+    }
+}
+
+impl From<u32> for Address {
+    #[inline] fn from(repr: u32) -> Address {
+        Address { repr }
+    }
+}
+
+impl From<Address> for u32 {
+    #[inline] fn from(address: Address) -> u32 {
+        address.repr
+    }
+}
+
+impl From<Ipv4Addr> for Address {
+    #[inline] fn from(address: Ipv4Addr) -> Address {
+        Address { repr: address.into() }
+    }
+}
+
+impl From<Address> for Ipv4Addr {
+    #[inline] fn from(address: Address) -> Ipv4Addr {
+        address.repr.into()
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.octets();
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+impl FromStr for Address {
+    type Err = ParseAddressError;
+    // dotted decimal only; leading zeros are rejected since some parsers read them as octal
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut octets = [0u8; 4];
+        let mut parts = s.split('.');
+        for octet in &mut octets {
+            let part = parts.next().ok_or(ParseAddressError(()))?;
+            if part.is_empty() || part.len() > 3 || (part.len() > 1 && part.starts_with('0')) ||
+                !part.bytes().all(|byte| byte.is_ascii_digit())
+            {
+                return Err(ParseAddressError(()))
+            }
+            *octet = part.parse().map_err(|_| ParseAddressError(()))?;
+        }
+        if parts.next().is_some() {
+            return Err(ParseAddressError(()))
+        }
+        Ok(Address::from(octets))
+    }
+}
+
+/// IPv4 address parse error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseAddressError(());
+
+// decimal prefix length without sign or leading zeros
+pub(super) fn parse_prefix(s: &str, max: u8) -> Option<u8> {
+    if s.is_empty() || s.len() > 3 || (s.len() > 1 && s.starts_with('0')) ||
+        !s.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None
+    }
+    s.parse().ok().filter(|&prefix| prefix <= max)
+}
+
+// for example: 192.168.0.0/16
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Subnet {
+    network: Address, // any bits beyond the prefix should be 0
+    prefix: u8,
+}
+
+impl Subnet {
+    // check prefix mask
+    pub const fn new(network: Address, prefix: u8) -> Subnet {
+        assert!(prefix <= 32);
+        assert!(network.repr & !Self::mask(prefix) == 0);
+        Subnet { network, prefix }
+    }
+    // subnet of address with host bits cleared
+    pub const fn from_address(address: Address, prefix: u8) -> Subnet {
+        assert!(prefix <= 32);
+        Subnet { network: Address { repr: address.repr & Self::mask(prefix) }, prefix }
+    }
+    const fn mask(prefix: u8) -> u32 {
+        if prefix == 0 { 0 } else { u32::MAX << (32 - prefix as u32) }
+    }
+    pub fn network(self) -> Address {
+        self.network
+    }
+    pub fn prefix(self) -> u8 {
+        self.prefix
+    }
+    pub fn netmask(self) -> Address {
+        Address { repr: Self::mask(self.prefix) }
+    }
+    // last address of subnet
+    pub fn broadcast(self) -> Address {
+        Address { repr: self.network.repr | !Self::mask(self.prefix) }
+    }
+    pub fn contains(self, address: Address) -> bool {
+        address.repr & Self::mask(self.prefix) == self.network.repr
+    }
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = ParseAddressError;
+    // host bits must be zero
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (network, prefix) = s.split_once('/').ok_or(ParseAddressError(()))?;
+        let network: Address = network.parse()?;
+        let prefix = parse_prefix(prefix, 32).ok_or(ParseAddressError(()))?;
+        if network.repr & !Self::mask(prefix) != 0 {
+            return Err(ParseAddressError(()))
+        }
+        Ok(Subnet { network, prefix })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<T> {
+    inner: T,
+}
+
+impl<T> Packet<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    // 4-bit version and 4-bit header length in 32-bit words
+    const VER_IHL:     usize = 0;
+    // 6-bit DSCP and 2-bit ECN, formerly type of service
+    const DSCP_ECN:    usize = 1;
+    // length of header and data in bytes
+    const LENGTH:      Range<usize> = 2..4;
+    const IDENT:       Range<usize> = 4..6;
+    // 3-bit flags and 13-bit fragment offset in 8-byte units
+    const FLG_OFF:     Range<usize> = 6..8;
+    const TTL:         usize = 8;
+    const PROTOCOL:    usize = 9;
+    const CHECKSUM:    Range<usize> = 10..12;
+    const SRC_ADDR:    Range<usize> = 12..16;
+    const DST_ADDR:    Range<usize> = 16..20;
+    // end of header without options
+    pub const MIN_HEADER_LEN: usize = 20;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    // check version and that header and total length fit in data
+    pub fn new_checked(inner: T) -> Option<Self> {
+        let packet = Self { inner };
+        let len = packet.inner.as_ref().len();
+        if len < Self::MIN_HEADER_LEN || packet.version() != 4 {
+            return None
+        }
+        let header_len = packet.header_len();
+        if header_len < Self::MIN_HEADER_LEN || header_len > len ||
+            (packet.total_len() as usize) < header_len || packet.total_len() as usize > len
+        {
+            return None
+        }
+        Some(packet)
+    }
+    pub fn version(&self) -> u8 {
+        self.inner.as_ref()[Self::VER_IHL] >> 4
+    }
+    // header length in bytes
+    pub fn header_len(&self) -> usize {
+        (self.inner.as_ref()[Self::VER_IHL] & 0x0f) as usize * 4
+    }
+    pub fn dscp(&self) -> Dscp {
+        (self.inner.as_ref()[Self::DSCP_ECN] >> 2).into()
+    }
+    pub fn ecn(&self) -> Ecn {
+        self.inner.as_ref()[Self::DSCP_ECN].into()
+    }
+    pub fn total_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::LENGTH])
+    }
+    pub fn identification(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::IDENT])
+    }
+    pub fn dont_fragment(&self) -> bool {
+        self.inner.as_ref()[Self::FLG_OFF.start] & 0x40 != 0
+    }
+    pub fn more_fragments(&self) -> bool {
+        self.inner.as_ref()[Self::FLG_OFF.start] & 0x20 != 0
+    }
+    // fragment offset in bytes
+    pub fn frag_offset(&self) -> u16 {
+        (NetworkEndian::read_u16(&self.inner.as_ref()[Self::FLG_OFF]) & 0x1fff) << 3
+    }
+    pub fn ttl(&self) -> u8 {
+        self.inner.as_ref()[Self::TTL]
+    }
+    pub fn protocol(&self) -> Protocol {
+        self.inner.as_ref()[Self::PROTOCOL].into()
+    }
+    pub fn checksum(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::CHECKSUM])
+    }
+    pub fn src_addr(&self) -> Address {
+        Address::from_bytes(&self.inner.as_ref()[Self::SRC_ADDR])
+    }
+    pub fn dst_addr(&self) -> Address {
+        Address::from_bytes(&self.inner.as_ref()[Self::DST_ADDR])
+    }
+    pub fn verify_checksum(&self) -> bool {
+        checksum::checksum(&self.inner.as_ref()[..self.header_len()]) == 0
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    pub fn options(&self) -> OptionIter<'a> {
+        OptionIter { data: &self.inner.as_ref()[Self::MIN_HEADER_LEN..self.header_len()] }
+    }
+    // data after header, cut to total length
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.inner.as_ref();
+        &data[self.header_len()..(self.total_len() as usize).min(data.len())]
+    }
+}
+
+impl<T: AsMut<[u8]> + AsRef<[u8]>> Packet<T> {
+    // compute and store header checksum; header length must be set
+    pub fn fill_checksum(&mut self) {
+        self.set_checksum(0);
+        let header_len = self.header_len();
+        let checksum = checksum::checksum(&self.inner.as_ref()[..header_len]);
+        self.set_checksum(checksum)
+    }
+    // forwarding step, patches the checksum instead of recomputing it
+    pub fn decrement_ttl(&mut self) -> u8 {
+        let data = self.inner.as_ref();
+        let old = NetworkEndian::read_u16(&data[Self::TTL..Self::TTL + 2]);
+        let ttl = self.ttl().saturating_sub(1);
+        self.set_ttl(ttl);
+        let new = NetworkEndian::read_u16(&self.inner.as_ref()[Self::TTL..Self::TTL + 2]);
+        self.set_checksum(checksum::update(self.checksum(), old, new));
+        ttl
+    }
+}
+
+impl<T: AsMut<[u8]>> Packet<T> {
+    pub fn set_version(&mut self, version: u8) {
+        let data = self.inner.as_mut();
+        data[Self::VER_IHL] = (data[Self::VER_IHL] & 0x0f) | (version << 4);
+    }
+    // header length in bytes, multiple of 4
+    pub fn set_header_len(&mut self, len: usize) {
+        debug_assert!(len.is_multiple_of(4) && (20..=60).contains(&len));
+        let data = self.inner.as_mut();
+        data[Self::VER_IHL] = (data[Self::VER_IHL] & 0xf0) | (len / 4) as u8;
+    }
+    pub fn set_dscp(&mut self, dscp: Dscp) {
+        let data = self.inner.as_mut();
+        data[Self::DSCP_ECN] = (data[Self::DSCP_ECN] & 0x03) | (u8::from(dscp) << 2);
+    }
+    pub fn set_ecn(&mut self, ecn: Ecn) {
+        let data = self.inner.as_mut();
+        data[Self::DSCP_ECN] = (data[Self::DSCP_ECN] & !0x03) | u8::from(ecn);
+    }
+    pub fn set_total_len(&mut self, len: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::LENGTH], len)
+    }
+    pub fn set_identification(&mut self, identification: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::IDENT], identification)
+    }
+    pub fn set_dont_fragment(&mut self, value: bool) {
+        let data = self.inner.as_mut();
+        data[Self::FLG_OFF.start] = (data[Self::FLG_OFF.start] & !0x40) | ((value as u8) << 6);
+    }
+    pub fn set_more_fragments(&mut self, value: bool) {
+        let data = self.inner.as_mut();
+        data[Self::FLG_OFF.start] = (data[Self::FLG_OFF.start] & !0x20) | ((value as u8) << 5);
+    }
+    // fragment offset in bytes, multiple of 8
+    pub fn set_frag_offset(&mut self, offset: u16) {
+        debug_assert!(offset.is_multiple_of(8));
+        let data = &mut self.inner.as_mut()[Self::FLG_OFF];
+        let value = (NetworkEndian::read_u16(data) & 0xe000) | (offset >> 3);
+        NetworkEndian::write_u16(data, value)
+    }
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.inner.as_mut()[Self::TTL] = ttl
+    }
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.inner.as_mut()[Self::PROTOCOL] = protocol.into()
+    }
+    pub fn set_checksum(&mut self, checksum: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::CHECKSUM], checksum)
+    }
+    pub fn set_src_addr(&mut self, address: Address) {
+        self.inner.as_mut()[Self::SRC_ADDR].copy_from_slice(&address.octets())
+    }
+    pub fn set_dst_addr(&mut self, address: Address) {
+        self.inner.as_mut()[Self::DST_ADDR].copy_from_slice(&address.octets())
+    }
+}
+
+impl<T: AsMut<[u8]> + AsRef<[u8]> + ?Sized> Packet<&mut T> {
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let header_len = (self.inner.as_ref()[Self::VER_IHL] & 0x0f) as usize * 4;
+        &mut self.inner.as_mut()[header_len..]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opt<'a> {
+    pub option_type: OptionType,
+    pub data: &'a [u8],
+}
+
+// header options, Section 3.1
+#[derive(Debug, Clone)]
+pub struct OptionIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = Opt<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let option_type = OptionType::from(*self.data.first()?);
+        match option_type {
+            OptionType::End => {
+                self.data = &[];
+                return Some(Opt { option_type, data: &[] })
+            },
+            OptionType::Nop => {
+                self.data = &self.data[1..];
+                return Some(Opt { option_type, data: &[] })
+            },
+            _ => {},
+        }
+        // length includes type and length bytes
+        let len = *self.data.get(1)? as usize;
+        if len < 2 || len > self.data.len() {
+            self.data = &[];
+            return None
+        }
+        let data = &self.data[2..len];
+        self.data = &self.data[len..];
+        Some(Opt { option_type, data })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionType {
+    End, // 0
+    Nop, // 1
+    RecordRoute, // 7
+    Timestamp, // 68
+    LooseSourceRoute, // 131
+    StrictSourceRoute, // 137
+    RouterAlert, // 148
+    Unknown(u8),
+}
+
+impl From<u8> for OptionType {
+    fn from(src: u8) -> Self {
+        match src {
+            0 => OptionType::End,
+            1 => OptionType::Nop,
+            7 => OptionType::RecordRoute,
+            68 => OptionType::Timestamp,
+            131 => OptionType::LooseSourceRoute,
+            137 => OptionType::StrictSourceRoute,
+            148 => OptionType::RouterAlert,
+            others => OptionType::Unknown(others),
+        }
+    }
+}
+
+impl From<OptionType> for u8 {
+    fn from(src: OptionType) -> u8 {
+        match src {
+            OptionType::End => 0,
+            OptionType::Nop => 1,
+            OptionType::RecordRoute => 7,
+            OptionType::Timestamp => 68,
+            OptionType::LooseSourceRoute => 131,
+            OptionType::StrictSourceRoute => 137,
+            OptionType::RouterAlert => 148,
+            OptionType::Unknown(others) => others
+        }
+    }
+}
+
+// IPv4 header followed by packet of a payload writer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Writer<W> {
+    src_addr: Address,
+    dst_addr: Address,
+    protocol: Protocol,
+    ttl: u8,
+    dscp: Dscp,
+    ecn: Ecn,
+    identification: u16,
+    dont_fragment: bool,
+    options: Vec<u8>,
+    payload: W,
+    byte_idx: usize,
+}
+
+impl<W> Writer<W> {
+    pub const DEFAULT_TTL: u8 = 64;
+
+    pub fn new(src_addr: Address, dst_addr: Address, protocol: Protocol, payload: W) -> Writer<W> {
+        Writer {
+            src_addr, dst_addr, protocol,
+            ttl: Self::DEFAULT_TTL,
+            dscp: Dscp::Cs0,
+            ecn: Ecn::NotEct,
+            identification: 0,
+            dont_fragment: false,
+            options: Vec::new(),
+            payload,
+            byte_idx: 0,
+        }
+    }
+    pub fn ttl(self, ttl: u8) -> Self {
+        Self { ttl, ..self }
+    }
+    pub fn dscp(self, dscp: Dscp) -> Self {
+        Self { dscp, ..self }
+    }
+    pub fn ecn(self, ecn: Ecn) -> Self {
+        Self { ecn, ..self }
+    }
+    pub fn identification(self, identification: u16) -> Self {
+        Self { identification, ..self }
+    }
+    pub fn dont_fragment(self, dont_fragment: bool) -> Self {
+        Self { dont_fragment, ..self }
+    }
+    // raw option bytes, padded with End of Option List to a multiple of 4
+    pub fn options(self, options: &[u8]) -> Self {
+        let mut options = options.to_vec();
+        options.resize(options.len().next_multiple_of(4), 0);
+        debug_assert!(options.len() <= 40);
+        Self { options, ..self }
+    }
+    pub fn into_payload(self) -> W {
+        self.payload
+    }
+    fn header_len(&self) -> usize {
+        Packet::<()>::MIN_HEADER_LEN + self.options.len()
+    }
+}
+
+impl<W: PacketWriter> Writer<W> {
+    pub fn len(&self) -> usize {
+        self.header_len() + self.payload.packet_len()
+    }
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![0u8; self.header_len()];
+        let mut packet = Packet::new(&mut header[..]);
+        packet.set_version(4);
+        packet.set_header_len(self.header_len());
+        packet.set_dscp(self.dscp);
+        packet.set_ecn(self.ecn);
+        debug_assert!(self.len() <= 0xffff);
+        packet.set_total_len(self.len() as u16);
+        packet.set_identification(self.identification);
+        packet.set_dont_fragment(self.dont_fragment);
+        packet.set_ttl(self.ttl);
+        packet.set_protocol(self.protocol);
+        packet.set_src_addr(self.src_addr);
+        packet.set_dst_addr(self.dst_addr);
+        header[Packet::<()>::MIN_HEADER_LEN..].copy_from_slice(&self.options);
+        Packet::new(&mut header[..]).fill_checksum();
+        header
+    }
+    #[must_use]
+    pub fn write(&mut self, buffer: &mut [u8]) -> usize {
+        let header_len = self.header_len();
+        let mut write = PacketWrite::new(&mut *buffer, self.byte_idx);
+        if self.byte_idx < header_len {
+            write.write_slice_at(0.., &self.header());
+        }
+        self.byte_idx = write.buffer_index();
+        let mut cur_idx = write.bytes_written();
+        if (header_len..self.len()).contains(&self.byte_idx) {
+            let write_len = self.payload.write_packet(&mut buffer[cur_idx..]);
+            self.byte_idx += write_len;
+            cur_idx += write_len;
+        }
+        cur_idx
+    }
+}
+
+impl<W: PacketWriter> PacketWriter for Writer<W> {
+    fn packet_len(&self) -> usize {
+        self.len()
+    }
+    fn write_packet(&mut self, buffer: &mut [u8]) -> usize {
+        self.write(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::wol::Writer as WolWriter;
+
+    #[test]
+    fn ipv4_address_parse_print() {
+        for addr_str in ["0.0.0.0", "127.0.0.1", "192.168.1.254", "255.255.255.255"] {
+            assert_eq!(addr_str.parse::<Address>().unwrap().to_string(), addr_str);
+        }
+        for addr_str in ["", "1.2.3", "1.2.3.4.5", "256.1.1.1", "01.2.3.4", "1.2.3.-4", "1..2.3", "a.b.c.d"] {
+            assert!(addr_str.parse::<Address>().is_err(), "{}", addr_str);
+        }
+        assert_eq!(Address::new(192, 0, 2, 1).octets(), [192, 0, 2, 1]);
+    }
+
+    #[test]
+    fn ipv4_subnet() {
+        let subnet: Subnet = "172.16.0.0/12".parse().unwrap();
+        assert_eq!(subnet.to_string(), "172.16.0.0/12");
+        assert_eq!(subnet.netmask(), Address::new(255, 240, 0, 0));
+        assert_eq!(subnet.broadcast(), Address::new(172, 31, 255, 255));
+        assert!(subnet.contains(Address::new(172, 20, 1, 1)));
+        assert!(!subnet.contains(Address::new(172, 32, 0, 0)));
+        assert!("0.0.0.0/0".parse::<Subnet>().unwrap().contains(Address::BROADCAST));
+        for subnet_str in ["172.16.0.1/12", "10.0.0.0/33", "10.0.0.0/08", "10.0.0.0", "10.0.0.0/"] {
+            assert!(subnet_str.parse::<Subnet>().is_err(), "{}", subnet_str);
+        }
+        assert_eq!(Subnet::from_address(Address::new(10, 1, 2, 3), 8), Subnet::new(Address::new(10, 0, 0, 0), 8));
+    }
+
+    #[test]
+    fn ipv4_address_classify() {
+        let addr = |s: &str| s.parse::<Address>().unwrap();
+        assert!(addr("10.1.2.3").is_private() && !addr("10.1.2.3").is_global());
+        assert!(addr("172.31.255.255").is_private() && !addr("172.32.0.0").is_private());
+        assert!(addr("169.254.1.1").is_link_local());
+        assert!(addr("100.127.0.1").is_shared() && !addr("100.128.0.1").is_shared());
+        assert!(addr("224.0.0.251").is_multicast() && !addr("224.0.0.251").is_unicast());
+        assert!(addr("203.0.113.9").is_documentation());
+        assert!(addr("240.0.0.1").is_reserved() && !Address::BROADCAST.is_reserved());
+        assert!(addr("8.8.8.8").is_global() && addr("8.8.8.8").is_unicast());
+        assert!(Address::LOOPBACK.is_loopback());
+    }
+
+    // header from https://en.wikipedia.org/wiki/Internet_checksum
+    const HEADER: [u8; 20] = [
+        0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11,
+        0xb8, 0x61, 0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+    ];
+
+    #[test]
+    fn ipv4_packet_parse() {
+        let packet = Packet::new(&HEADER[..]);
+        assert_eq!(packet.version(), 4);
+        assert_eq!(packet.header_len(), 20);
+        assert_eq!(packet.total_len(), 0x73);
+        assert!(packet.dont_fragment() && !packet.more_fragments());
+        assert_eq!(packet.ttl(), 64);
+        assert_eq!(packet.protocol(), Protocol::Udp);
+        assert_eq!(packet.src_addr(), Address::new(192, 168, 0, 1));
+        assert_eq!(packet.dst_addr(), Address::new(192, 168, 0, 199));
+        assert!(packet.verify_checksum());
+        // total length exceeds data
+        assert!(Packet::new_checked(&HEADER[..]).is_none());
+        let mut header = HEADER;
+        Packet::new(&mut header[..]).set_ttl(63);
+        assert!(!Packet::new(&header[..]).verify_checksum());
+        Packet::new(&mut header[..]).fill_checksum();
+        assert!(Packet::new(&header[..]).verify_checksum());
+        assert_eq!(Packet::new(&mut header[..]).decrement_ttl(), 62);
+        assert!(Packet::new(&header[..]).verify_checksum());
+    }
+
+    #[test]
+    fn ipv4_writer_options() {
+        let target = "00-15-5d-b7-9f-a9".parse().unwrap();
+        let writer = Writer::new(Address::new(10, 0, 0, 1), Address::BROADCAST, Protocol::Udp, WolWriter::new(target))
+            .ttl(1)
+            .identification(0x1234)
+            .options(&[148, 4, 0, 0, 1]);
+        let mut writer2 = writer.clone();
+        let mut whole = vec![0u8; writer.len()];
+        let len = writer.clone().write(&mut whole);
+        assert_eq!(len, 28 + 102);
+        // resumable in small pieces
+        let mut pieces = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let len = writer2.write(&mut buf);
+            if len == 0 {
+                break
+            }
+            pieces.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(pieces, whole);
+        let packet = Packet::new_checked(&whole[..]).unwrap();
+        assert!(packet.verify_checksum());
+        assert_eq!(packet.ttl(), 1);
+        assert_eq!(packet.identification(), 0x1234);
+        let options: Vec<_> = packet.options().collect();
+        assert_eq!(options, [
+            Opt { option_type: OptionType::RouterAlert, data: &[0, 0] },
+            Opt { option_type: OptionType::Nop, data: &[] },
+            Opt { option_type: OptionType::End, data: &[] },
+        ]);
+        assert_eq!(packet.payload().len(), 102);
+    }
+}
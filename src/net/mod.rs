@@ -1,8 +1,31 @@
 mod ip;
+pub mod ipv4;
+mod any;
 mod icmp;
+pub mod ext_header;
+mod fragment;
+pub mod registry;
+mod route;
+pub mod checksum;
+mod checksum_simd;
+mod traffic_class;
+mod flow_label;
+pub mod srh;
 pub use ip::{
-    Address as IpAddress, Packet as IpPacket, Protocol as IpProtocol,
+    Address as IpAddress, Packet as IpPacket, Protocol as IpProtocol, Subnet as IpSubnet,
+    SubnetSet as IpSubnetSet, MulticastScope as IpMulticastScope, MulticastFlags as IpMulticastFlags,
+    ScopedAddress as IpScopedAddress, Zone as IpZone,
 };
 pub use icmp::{
     Packet as IcmpPacket, EchoRequest, Type as IcmpType, Writer as IcmpWriter,
 };
+pub use ext_header::{ExtHeader as IpExtHeader, ExtHeaderError as IpExtHeaderError};
+pub use fragment::{Fragmenter as IpFragmenter, random_identification, MIN_MTU as IPV6_MIN_MTU};
+pub use ipv4::{
+    Address as Ipv4Address, Packet as Ipv4Packet, Subnet as Ipv4Subnet, Writer as Ipv4Writer,
+};
+pub use any::{Address as IpAddr, Cidr as IpCidr, Packet as IpAnyPacket};
+pub use route::{Route, RoutingTable, Iter as Routes};
+pub use traffic_class::{Dscp, Ecn};
+pub use flow_label::FlowLabeler;
+pub use srh::{Srh, Writer as SrhWriter};
@@ -0,0 +1,120 @@
+// IANA IPv6 Special-Purpose Address Registry, RFC 6890
+// https://www.iana.org/assignments/iana-ipv6-special-registry/
+use super::ip::{Address, Subnet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub subnet: Subnet,
+    pub name: &'static str,
+    pub rfc: u16,
+    // valid as source address
+    pub source: bool,
+    // valid as destination address
+    pub destination: bool,
+    // routers may forward packets with this address
+    pub forwardable: bool,
+    // None where the registry says N/A
+    pub globally_reachable: Option<bool>,
+    // special handling required by protocol
+    pub reserved_by_protocol: bool,
+}
+
+const fn block(segments: [u16; 8], prefix: u8) -> Subnet {
+    Subnet::new(Address::from_segments(segments), prefix)
+}
+
+// blocks with their own Address predicate, shared so the two cannot disagree
+pub const IPV4_MAPPED: Subnet = block([0, 0, 0, 0, 0, 0xffff, 0, 0], 96);
+pub const IPV4_TRANSLATED: Subnet = block([0x64, 0xff9b, 0, 0, 0, 0, 0, 0], 96);
+pub const BENCHMARKING: Subnet = block([0x2001, 2, 0, 0, 0, 0, 0, 0], 48);
+pub const DOCUMENTATION: Subnet = block([0x2001, 0xdb8, 0, 0, 0, 0, 0, 0], 32);
+pub const DOCUMENTATION_3FFF: Subnet = block([0x3fff, 0, 0, 0, 0, 0, 0, 0], 20);
+pub const UNIQUE_LOCAL: Subnet = block([0xfc00, 0, 0, 0, 0, 0, 0, 0], 7);
+
+const fn entry(
+    subnet: Subnet, name: &'static str, rfc: u16,
+    flags: [bool; 3], globally_reachable: Option<bool>, reserved_by_protocol: bool,
+) -> Entry {
+    Entry {
+        subnet,
+        name, rfc,
+        source: flags[0],
+        destination: flags[1],
+        forwardable: flags[2],
+        globally_reachable,
+        reserved_by_protocol,
+    }
+}
+
+const T: bool = true;
+const F: bool = false;
+
+// flags are source, destination, forwardable
+pub const SPECIAL_PURPOSE: &[Entry] = &[
+    entry(Subnet::LOOPBACK, "Loopback Address", 4291, [F, F, F], Some(F), T),
+    entry(Subnet::UNSPECIFIED, "Unspecified Address", 4291, [T, F, F], Some(F), T),
+    entry(IPV4_MAPPED, "IPv4-mapped Address", 4291, [F, F, F], Some(F), T),
+    entry(IPV4_TRANSLATED, "IPv4-IPv6 Translat.", 6052, [T, T, T], Some(T), F),
+    entry(block([0x64, 0xff9b, 1, 0, 0, 0, 0, 0], 48), "IPv4-IPv6 Translat.", 8215, [T, T, T], Some(F), F),
+    entry(block([0x100, 0, 0, 0, 0, 0, 0, 0], 64), "Discard-Only Address Block", 6666, [T, T, T], Some(F), F),
+    entry(block([0x2001, 0, 0, 0, 0, 0, 0, 0], 23), "IETF Protocol Assignments", 2928, [F, F, F], Some(F), F),
+    entry(block([0x2001, 0, 0, 0, 0, 0, 0, 0], 32), "TEREDO", 4380, [T, T, T], None, F),
+    entry(block([0x2001, 1, 0, 0, 0, 0, 0, 1], 128), "Port Control Protocol Anycast", 7723, [T, T, T], Some(T), F),
+    entry(block([0x2001, 1, 0, 0, 0, 0, 0, 2], 128), "Traversal Using Relays around NAT Anycast", 8155, [T, T, T], Some(T), F),
+    entry(BENCHMARKING, "Benchmarking", 5180, [T, T, T], Some(F), F),
+    entry(block([0x2001, 3, 0, 0, 0, 0, 0, 0], 32), "AMT", 7450, [T, T, T], Some(T), F),
+    entry(block([0x2001, 4, 0x112, 0, 0, 0, 0, 0], 48), "AS112-v6", 7535, [T, T, T], Some(T), F),
+    entry(block([0x2001, 0x20, 0, 0, 0, 0, 0, 0], 28), "ORCHIDv2", 7343, [T, T, T], Some(T), F),
+    entry(block([0x2001, 0x30, 0, 0, 0, 0, 0, 0], 28), "Drone Remote ID Protocol Entity Tags (DETs) Prefix", 9374, [T, T, T], Some(T), F),
+    entry(DOCUMENTATION, "Documentation", 3849, [F, F, F], Some(F), F),
+    entry(block([0x2002, 0, 0, 0, 0, 0, 0, 0], 16), "6to4", 3056, [T, T, T], None, F),
+    entry(block([0x2620, 0x4f, 0x8000, 0, 0, 0, 0, 0], 48), "Direct Delegation AS112 Service", 7534, [T, T, T], Some(T), F),
+    entry(DOCUMENTATION_3FFF, "Documentation", 9637, [F, F, F], Some(F), F),
+    entry(block([0x5f00, 0, 0, 0, 0, 0, 0, 0], 16), "Segment Routing (SRv6) SIDs", 9602, [T, T, T], Some(F), F),
+    entry(UNIQUE_LOCAL, "Unique-Local", 4193, [T, T, T], Some(F), F),
+    entry(Subnet::LINK_LOCAL_UNICAST, "Link-Local Unicast", 4291, [T, T, F], Some(F), T),
+];
+
+// most specific registry entry containing address
+pub fn lookup(address: Address) -> Option<&'static Entry> {
+    SPECIAL_PURPOSE.iter()
+        .filter(|entry| entry.subnet.contains(address))
+        .max_by_key(|entry| entry.subnet.prefix())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_lookup() {
+        let name = |s: &str| lookup(s.parse().unwrap()).map(|entry| entry.name);
+        assert_eq!(name("2001::1"), Some("TEREDO"));
+        assert_eq!(name("2001:1::1"), Some("Port Control Protocol Anycast"));
+        assert_eq!(name("2001:1::3"), Some("IETF Protocol Assignments"));
+        assert_eq!(name("fd00::1"), Some("Unique-Local"));
+        assert_eq!(name("2400:cb00::1"), None);
+        assert_eq!(lookup(Address::UNSPECIFIED).unwrap().rfc, 4291);
+    }
+
+    #[test]
+    fn registry_predicates() {
+        // every entry with an Address predicate answers it, and only its own
+        let predicates = [
+            ("Loopback Address", Address::is_loopback as fn(Address) -> bool),
+            ("Unspecified Address", Address::is_unspecified),
+            ("IPv4-mapped Address", Address::is_ipv4_mapped),
+            ("Benchmarking", Address::is_benchmarking),
+            ("Documentation", Address::is_documentation),
+            ("Unique-Local", Address::is_unique_local),
+            ("Link-Local Unicast", Address::is_link_local),
+        ];
+        for entry in SPECIAL_PURPOSE {
+            for &(name, predicate) in &predicates {
+                assert_eq!(predicate(entry.subnet.first()), entry.name == name, "{} {}", entry.name, name);
+                assert_eq!(predicate(entry.subnet.last()), entry.name == name, "{} {}", entry.name, name);
+            }
+        }
+        assert!(Address::is_ipv4_translated(IPV4_TRANSLATED.last()));
+    }
+}
@@ -0,0 +1,324 @@
+// IPv6 routing table, longest-prefix match over a path-compressed binary trie
+use super::ip::{Address, Subnet};
+use core::mem;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Route {
+    pub next_hop: Address,
+    pub interface: String,
+    // lower is preferred
+    pub metric: u32,
+}
+
+impl Route {
+    pub fn new(next_hop: Address, interface: &str, metric: u32) -> Route {
+        Route { next_hop, interface: interface.to_string(), metric }
+    }
+}
+
+// a node without routes only joins two subtrees
+#[derive(Debug, Clone)]
+struct Node {
+    subnet: Subnet,
+    // sorted by metric, equal metrics are an ECMP set
+    routes: Vec<Route>,
+    children: [Option<Box<Node>>; 2],
+}
+
+impl Node {
+    fn new(subnet: Subnet) -> Box<Node> {
+        Box::new(Node { subnet, routes: Vec::new(), children: [None, None] })
+    }
+    // routes sharing the best metric
+    fn best(&self) -> &[Route] {
+        let metric = self.routes[0].metric;
+        let len = self.routes.iter().take_while(|route| route.metric == metric).count();
+        &self.routes[..len]
+    }
+}
+
+// bit idx of address, counted from the most significant
+fn bit(address: Address, idx: u8) -> usize {
+    (address.to_bits() >> (127 - idx)) as usize & 1
+}
+
+fn insert(slot: &mut Option<Box<Node>>, subnet: Subnet) -> &mut Node {
+    let current = match slot {
+        None => return slot.insert(Node::new(subnet)),
+        Some(node) => node.subnet,
+    };
+    if current == subnet {
+        return slot.as_mut().unwrap()
+    }
+    if current.contains_subnet(subnet) {
+        let idx = bit(subnet.network(), current.prefix());
+        return insert(&mut slot.as_mut().unwrap().children[idx], subnet)
+    }
+    // new node goes above the current one, with a join node if they diverge
+    let parent = if subnet.contains_subnet(current) {
+        subnet
+    } else {
+        current.common_supernet(subnet)
+    };
+    let old = slot.take().unwrap();
+    let mut new = Node::new(parent);
+    let old_idx = bit(old.subnet.network(), parent.prefix());
+    new.children[old_idx] = Some(old);
+    let new = slot.insert(new);
+    if parent == subnet {
+        new
+    } else {
+        new.children[1 - old_idx].insert(Node::new(subnet))
+    }
+}
+
+// apply f to the routes of subnet, then compact the path back to the root
+fn update<R, F: FnOnce(&mut Vec<Route>) -> R>(slot: &mut Option<Box<Node>>, subnet: Subnet, f: F) -> Option<R> {
+    let current = slot.as_ref()?.subnet;
+    let result = if current == subnet {
+        f(&mut slot.as_mut().unwrap().routes)
+    } else if current.prefix() < subnet.prefix() && current.contains_subnet(subnet) {
+        let idx = bit(subnet.network(), current.prefix());
+        update(&mut slot.as_mut().unwrap().children[idx], subnet, f)?
+    } else {
+        return None
+    };
+    compact(slot);
+    Some(result)
+}
+
+// drop a node left without routes if it no longer joins two subtrees
+fn compact(slot: &mut Option<Box<Node>>) {
+    let node = match slot {
+        Some(node) if node.routes.is_empty() => node,
+        _ => return,
+    };
+    match mem::take(&mut node.children) {
+        [Some(left), Some(right)] => node.children = [Some(left), Some(right)],
+        [Some(child), None] | [None, Some(child)] => *slot = Some(child),
+        [None, None] => *slot = None,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RoutingTable {
+    root: Option<Box<Node>>,
+    // prefixes with at least one route
+    len: usize,
+}
+
+impl RoutingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // number of prefixes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    // add a route, replacing one with the same next hop and interface;
+    // returns the replaced route
+    pub fn insert(&mut self, subnet: Subnet, route: Route) -> Option<Route> {
+        let node = insert(&mut self.root, subnet);
+        if node.routes.is_empty() {
+            self.len += 1;
+        }
+        let old = node.routes.iter()
+            .position(|old| old.next_hop == route.next_hop && old.interface == route.interface)
+            .map(|idx| node.routes.remove(idx));
+        let idx = node.routes.iter().take_while(|old| old.metric <= route.metric).count();
+        node.routes.insert(idx, route);
+        old
+    }
+    pub fn remove(&mut self, subnet: Subnet, next_hop: Address, interface: &str) -> Option<Route> {
+        let route = update(&mut self.root, subnet, |routes| {
+            let idx = routes.iter().position(|route| route.next_hop == next_hop && route.interface == interface)?;
+            Some((routes.remove(idx), routes.is_empty()))
+        })?;
+        let (route, emptied) = route?;
+        if emptied {
+            self.len -= 1;
+        }
+        Some(route)
+    }
+    // remove every route of a prefix
+    pub fn remove_prefix(&mut self, subnet: Subnet) -> Vec<Route> {
+        let routes = update(&mut self.root, subnet, mem::take).unwrap_or_default();
+        if !routes.is_empty() {
+            self.len -= 1;
+        }
+        routes
+    }
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+    // all routes of exactly this prefix, best first
+    pub fn get(&self, subnet: Subnet) -> Option<&[Route]> {
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            if node.subnet == subnet {
+                return if node.routes.is_empty() { None } else { Some(&node.routes) }
+            }
+            if node.subnet.prefix() >= subnet.prefix() || !node.subnet.contains_subnet(subnet) {
+                return None
+            }
+            cur = node.children[bit(subnet.network(), node.subnet.prefix())].as_deref();
+        }
+        None
+    }
+    // longest matching prefix and its ECMP set of best-metric routes
+    pub fn lookup(&self, address: Address) -> Option<(Subnet, &[Route])> {
+        let mut found = None;
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            if !node.subnet.contains(address) {
+                break
+            }
+            if !node.routes.is_empty() {
+                found = Some(node);
+            }
+            if node.subnet.prefix() == 128 {
+                break
+            }
+            cur = node.children[bit(address, node.subnet.prefix())].as_deref();
+        }
+        found.map(|node| (node.subnet, node.best()))
+    }
+    // pick one route of the ECMP set, packets of a flow should share flow_hash,
+    // such as the flow label of RFC 6438
+    pub fn select(&self, address: Address, flow_hash: u32) -> Option<&Route> {
+        let (_, routes) = self.lookup(address)?;
+        Some(&routes[flow_hash as usize % routes.len()])
+    }
+    // prefixes in order of network, then prefix length
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { stack: self.root.as_deref().into_iter().collect() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (Subnet, &'a [Route]);
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            self.stack.extend(node.children.iter().rev().filter_map(|child| child.as_deref()));
+            if !node.routes.is_empty() {
+                return Some((node.subnet, &node.routes))
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subnet(s: &str) -> Subnet {
+        s.parse().unwrap()
+    }
+    fn addr(s: &str) -> Address {
+        s.parse().unwrap()
+    }
+
+    fn count_nodes(node: &Node) -> usize {
+        1 + node.children.iter().filter_map(|child| child.as_deref()).map(count_nodes).sum::<usize>()
+    }
+
+    #[test]
+    fn route_lookup_ecmp() {
+        let mut table = RoutingTable::new();
+        let gateway = |n| Route::new(Address::from_segments([0xfe80, 0, 0, 0, 0, 0, 0, n]), "eth0", 10);
+        assert_eq!(table.insert(subnet("::/0"), gateway(1)), None);
+        table.insert(subnet("2001:db8::/32"), gateway(2));
+        table.insert(subnet("2001:db8::/32"), gateway(3));
+        table.insert(subnet("2001:db8::/32"), Route { metric: 20, ..gateway(4) });
+        table.insert(subnet("2001:db8:1::/48"), gateway(5));
+        table.insert(subnet("2001:db8:1::1/128"), gateway(6));
+        assert_eq!(table.len(), 4);
+
+        let (prefix, routes) = table.lookup(addr("2001:db8:2::1")).unwrap();
+        assert_eq!(prefix, subnet("2001:db8::/32"));
+        assert_eq!(routes, [gateway(2), gateway(3)]);
+        assert_eq!(table.lookup(addr("2001:db8:1::2")).unwrap().0, subnet("2001:db8:1::/48"));
+        assert_eq!(table.lookup(addr("2001:db8:1::1")).unwrap().0, subnet("2001:db8:1::1/128"));
+        assert_eq!(table.lookup(addr("2400::1")).unwrap().0, subnet("::/0"));
+        assert_eq!(table.select(addr("2001:db8::1"), 7).unwrap(), &gateway(3));
+
+        // replacing a route keeps the metric order
+        let old = table.insert(subnet("2001:db8::/32"), Route { metric: 5, ..gateway(3) });
+        assert_eq!(old, Some(gateway(3)));
+        assert_eq!(table.lookup(addr("2001:db8::1")).unwrap().1, [Route { metric: 5, ..gateway(3) }]);
+        assert_eq!(table.get(subnet("2001:db8::/32")).unwrap().len(), 3);
+        assert_eq!(table.get(subnet("2001:db8::/33")), None);
+
+        assert_eq!(table.remove(subnet("2001:db8::/32"), gateway(3).next_hop, "eth0").unwrap().metric, 5);
+        assert_eq!(table.remove(subnet("2001:db8::/32"), gateway(3).next_hop, "eth0"), None);
+        assert_eq!(table.remove_prefix(subnet("2001:db8::/32")).len(), 2);
+        assert_eq!(table.lookup(addr("2001:db8:2::1")).unwrap().0, subnet("::/0"));
+        assert_eq!(table.lookup(addr("2001:db8:1::1")).unwrap().0, subnet("2001:db8:1::1/128"));
+        table.remove_prefix(subnet("::/0"));
+        assert_eq!(table.lookup(addr("2400::1")), None);
+        assert_eq!(table.len(), 2);
+        assert_eq!(count_nodes(table.root.as_deref().unwrap()), 2);
+        table.clear();
+        assert!(table.is_empty() && table.iter().next().is_none());
+    }
+
+    #[test]
+    fn route_iter_order() {
+        let mut table = RoutingTable::new();
+        let prefixes = ["2001:db8:1::/48", "fe80::/10", "2001:db8::/32", "::/0", "2001:db8::/48", "::1/128"];
+        for prefix in prefixes.iter() {
+            table.insert(subnet(prefix), Route::new(Address::UNSPECIFIED, "lo", 0));
+        }
+        let mut sorted: Vec<Subnet> = prefixes.iter().map(|prefix| subnet(prefix)).collect();
+        sorted.sort();
+        assert_eq!(table.iter().map(|(prefix, _)| prefix).collect::<Vec<_>>(), sorted);
+    }
+
+    #[test]
+    fn route_matches_linear_scan() {
+        // xorshift, so the table shape is repeatable
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        // cluster under 2000::/8 so prefixes nest
+        let mut prefixes: Vec<Subnet> = (0..4000).map(|_| {
+            let bits = (0x20_u128 << 120) | ((random() as u128) << 56 >> 8);
+            Subnet::from_address(Address::from_bits(bits), 8 + (random() % 57) as u8)
+        }).collect();
+        prefixes.sort();
+        prefixes.dedup();
+        let mut table = RoutingTable::new();
+        for (idx, prefix) in prefixes.iter().enumerate() {
+            table.insert(*prefix, Route::new(Address::LOOPBACK, "lo", idx as u32));
+        }
+        for prefix in prefixes.iter().step_by(3) {
+            assert!(table.remove(*prefix, Address::LOOPBACK, "lo").is_some());
+        }
+        let prefixes: Vec<Subnet> = prefixes.iter().enumerate()
+            .filter(|(idx, _)| idx % 3 != 0)
+            .map(|(_, prefix)| *prefix)
+            .collect();
+        assert_eq!(table.len(), prefixes.len());
+        // join nodes never exceed route nodes in a compressed trie
+        assert!(table.root.as_deref().map_or(0, count_nodes) < 2 * prefixes.len());
+        assert_eq!(table.iter().map(|(prefix, _)| prefix).collect::<Vec<_>>(), prefixes);
+        for _ in 0..500 {
+            let address = Address::from_bits((0x20_u128 << 120) | ((random() as u128) << 56 >> 8));
+            let expected = prefixes.iter().filter(|prefix| prefix.contains(address)).max_by_key(|prefix| prefix.prefix());
+            assert_eq!(table.lookup(address).map(|(prefix, _)| prefix), expected.copied());
+        }
+    }
+}
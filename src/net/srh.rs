@@ -0,0 +1,520 @@
+// Segment Routing Header, RFC 8754, and the SRv6 End behaviour of RFC 8986
+use super::ext_header::{ExtHeader, ExtHeaderError, ExtHeaderIter};
+use super::ip::{Address, Packet, Protocol};
+use super::traffic_class::{Dscp, Ecn};
+use crate::packet_write::{PacketWrite, PacketWriter};
+use byteorder::{ByteOrder, NetworkEndian};
+use core::ops::Range;
+
+// Routing header of type 4; segment list[0] is the last segment of the path
+#[derive(Debug, Clone, Copy)]
+pub struct Srh<T> {
+    inner: T
+}
+
+impl<T> Srh<T> {
+    pub const ROUTING_TYPE: u8 = 4;
+    const LAST_ENTRY: usize = 4;
+    const FLAGS: usize = 5;
+    const TAG: Range<usize> = 6..8;
+    const SEGMENT_LIST: usize = 8;
+
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Srh<T> {
+    // None unless the buffer holds a routing header of type 4 with its whole segment list
+    pub fn new_checked(inner: T) -> Option<Self> {
+        let data = inner.as_ref();
+        if data.len() < Self::SEGMENT_LIST || data[2] != Self::ROUTING_TYPE {
+            return None
+        }
+        let srh = Srh { inner };
+        if srh.header_len() > srh.inner.as_ref().len() || srh.tlv_range().start > srh.header_len() {
+            return None
+        }
+        Some(srh)
+    }
+    pub fn next_header(&self) -> Protocol {
+        self.inner.as_ref()[0].into()
+    }
+    // whole header length in bytes
+    pub fn header_len(&self) -> usize {
+        (self.inner.as_ref()[1] as usize + 1) * 8
+    }
+    pub fn routing_type(&self) -> u8 {
+        self.inner.as_ref()[2]
+    }
+    pub fn segments_left(&self) -> u8 {
+        self.inner.as_ref()[3]
+    }
+    // index of the last element of the segment list
+    pub fn last_entry(&self) -> u8 {
+        self.inner.as_ref()[Self::LAST_ENTRY]
+    }
+    pub fn flags(&self) -> u8 {
+        self.inner.as_ref()[Self::FLAGS]
+    }
+    pub fn tag(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[Self::TAG])
+    }
+    pub fn segment_count(&self) -> usize {
+        self.last_entry() as usize + 1
+    }
+    pub fn segment(&self, idx: usize) -> Address {
+        debug_assert!(idx < self.segment_count());
+        let start = Self::SEGMENT_LIST + idx * 16;
+        Address::from_bytes(&self.inner.as_ref()[start..start + 16])
+    }
+    // segment list[segments left], the destination while this header is in flight
+    pub fn active_segment(&self) -> Option<Address> {
+        let idx = self.segments_left() as usize;
+        if idx < self.segment_count() {
+            Some(self.segment(idx))
+        } else {
+            None
+        }
+    }
+    fn tlv_range(&self) -> Range<usize> {
+        Self::SEGMENT_LIST + self.segment_count() * 16..self.header_len()
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Srh<&'a T> {
+    // in list order, last segment first
+    pub fn segments(&self) -> impl Iterator<Item = Address> + 'a {
+        let data = &self.inner.as_ref()[Self::SEGMENT_LIST..self.tlv_range().start];
+        data.chunks_exact(16).map(Address::from_bytes)
+    }
+    pub fn tlvs(&self) -> TlvIter<'a> {
+        TlvIter { data: &self.inner.as_ref()[self.tlv_range()] }
+    }
+}
+
+impl<T: AsMut<[u8]>> Srh<T> {
+    pub fn set_next_header(&mut self, protocol: Protocol) {
+        self.inner.as_mut()[0] = protocol.into()
+    }
+    // header length in bytes, must be a multiple of 8 between 8 and 2048
+    pub fn set_header_len(&mut self, len: usize) {
+        assert!(len >= 8 && len.is_multiple_of(8) && len <= 2048, "invalid routing header length {}", len);
+        self.inner.as_mut()[1] = (len / 8 - 1) as u8
+    }
+    pub fn set_routing_type(&mut self, routing_type: u8) {
+        self.inner.as_mut()[2] = routing_type
+    }
+    pub fn set_segments_left(&mut self, segments_left: u8) {
+        self.inner.as_mut()[3] = segments_left
+    }
+    pub fn set_last_entry(&mut self, last_entry: u8) {
+        self.inner.as_mut()[Self::LAST_ENTRY] = last_entry
+    }
+    pub fn set_flags(&mut self, flags: u8) {
+        self.inner.as_mut()[Self::FLAGS] = flags
+    }
+    pub fn set_tag(&mut self, tag: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[Self::TAG], tag)
+    }
+    pub fn set_segment(&mut self, idx: usize, segment: Address) {
+        let start = Self::SEGMENT_LIST + idx * 16;
+        self.inner.as_mut()[start..start + 16].copy_from_slice(&segment.octets())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tlv<'a> {
+    pub tlv_type: TlvType,
+    pub data: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    // Section 2.1.2 of RFC 8754
+    pub fn hmac(&self) -> Option<Hmac<'a>> {
+        if self.tlv_type != TlvType::Hmac || self.data.len() < 6 {
+            return None
+        }
+        Some(Hmac {
+            destination_check: self.data[0] & 0x80 != 0,
+            key_id: NetworkEndian::read_u32(&self.data[2..6]),
+            hmac: &self.data[6..],
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hmac<'a> {
+    // D flag, destination address must be checked as well
+    pub destination_check: bool,
+    pub key_id: u32,
+    pub hmac: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct TlvIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = Tlv<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let tlv_type = TlvType::from(*self.data.first()?);
+        if tlv_type == TlvType::Pad1 {
+            self.data = &self.data[1..];
+            return Some(Tlv { tlv_type, data: &[] })
+        }
+        let len = *self.data.get(1)? as usize;
+        if self.data.len() < 2 + len {
+            self.data = &[];
+            return None
+        }
+        let data = &self.data[2..2 + len];
+        self.data = &self.data[2 + len..];
+        Some(Tlv { tlv_type, data })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlvType {
+    Pad1, // 0x00
+    PadN, // 0x04
+    Hmac, // 0x05
+    Unknown(u8),
+}
+
+impl From<u8> for TlvType {
+    fn from(src: u8) -> Self {
+        match src {
+            0x00 => TlvType::Pad1,
+            0x04 => TlvType::PadN,
+            0x05 => TlvType::Hmac,
+            others => TlvType::Unknown(others),
+        }
+    }
+}
+
+impl From<TlvType> for u8 {
+    fn from(src: TlvType) -> u8 {
+        match src {
+            TlvType::Pad1 => 0x00,
+            TlvType::PadN => 0x04,
+            TlvType::Hmac => 0x05,
+            TlvType::Unknown(others) => others
+        }
+    }
+}
+
+// offset of the first Segment Routing Header in an IPv6 packet
+fn find(packet: &[u8]) -> Result<Option<usize>, ExtHeaderError> {
+    let packet = Packet::new(packet);
+    let payload_len = usize::min(packet.payload().len(), packet.payload_len() as usize);
+    let mut headers = ExtHeaderIter::new(packet.next_header(), &packet.payload()[..payload_len]);
+    loop {
+        let offset = 40 + payload_len - headers.remaining().len();
+        match headers.next() {
+            Some(Ok(ExtHeader::Routing(routing))) if routing.routing_type() == Srh::<()>::ROUTING_TYPE => {
+                return Ok(Some(offset))
+            },
+            Some(Ok(_)) => {},
+            Some(Err(err)) => return Err(err),
+            None => return Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndError {
+    // shorter than the IPv6 header
+    Truncated,
+    NoSrh,
+    Malformed(ExtHeaderError),
+    // path finished, the next header is for this node
+    SegmentsLeftZero,
+    // answer with ICMPv6 Time Exceeded
+    HopLimitExceeded,
+    // last entry or segments left out of range, answer with ICMPv6 Parameter Problem
+    ParameterProblem,
+}
+
+// End behaviour, Section 4.1 of RFC 8986: decrement hop limit and segments left,
+// then steer the packet to the next segment; returns the new destination
+pub fn end(packet: &mut [u8]) -> Result<Address, EndError> {
+    if packet.len() < 40 {
+        return Err(EndError::Truncated)
+    }
+    let offset = find(packet).map_err(EndError::Malformed)?.ok_or(EndError::NoSrh)?;
+    let srh = Srh::new_checked(&packet[offset..]).ok_or(EndError::ParameterProblem)?;
+    let segments_left = srh.segments_left();
+    if segments_left == 0 {
+        return Err(EndError::SegmentsLeftZero)
+    }
+    let hop_limit = Packet::new(&packet[..]).hop_limit();
+    if hop_limit <= 1 {
+        return Err(EndError::HopLimitExceeded)
+    }
+    // max_LE is (Hdr Ext Len / 2) - 1
+    let max_last_entry = (srh.header_len() / 8 - 1) / 2;
+    if srh.last_entry() as usize + 1 > max_last_entry || segments_left as usize > srh.segment_count() {
+        return Err(EndError::ParameterProblem)
+    }
+    let next = srh.segment(segments_left as usize - 1);
+    Srh::new(&mut packet[offset..]).set_segments_left(segments_left - 1);
+    let mut ip = Packet::new(&mut packet[..]);
+    ip.set_hop_limit(hop_limit - 1);
+    ip.set_dst_addr(next);
+    Ok(next)
+}
+
+// SRv6 encapsulation: an outer IPv6 header and Segment Routing Header in front of
+// the payload, H.Encaps of RFC 8986; segments are given in the order they are visited
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Writer<W> {
+    src_addr: Address,
+    segments: Vec<Address>,
+    next_header: Protocol,
+    hop_limit: u8,
+    dscp: Dscp,
+    ecn: Ecn,
+    flow_label: u32,
+    flags: u8,
+    tag: u16,
+    tlvs: Vec<u8>,
+    payload: W,
+    byte_idx: usize,
+}
+
+impl<W> Writer<W> {
+    pub const DEFAULT_HOP_LIMIT: u8 = 64;
+    // Hdr Ext Len of 255
+    const MAX_SRH_LEN: usize = 2048;
+
+    // next header is the protocol of payload, Ipv6 for an encapsulated IPv6 packet
+    pub fn new(src_addr: Address, segments: &[Address], next_header: Protocol, payload: W) -> Writer<W> {
+        assert!(!segments.is_empty() && segments.len() <= 127, "1 to 127 segments");
+        Writer {
+            src_addr,
+            segments: segments.to_vec(),
+            next_header,
+            hop_limit: Self::DEFAULT_HOP_LIMIT,
+            dscp: Dscp::Cs0,
+            ecn: Ecn::NotEct,
+            flow_label: 0,
+            flags: 0,
+            tag: 0,
+            tlvs: Vec::new(),
+            payload,
+            byte_idx: 0,
+        }
+    }
+    pub fn hop_limit(self, hop_limit: u8) -> Self {
+        Self { hop_limit, ..self }
+    }
+    pub fn dscp(self, dscp: Dscp) -> Self {
+        Self { dscp, ..self }
+    }
+    pub fn ecn(self, ecn: Ecn) -> Self {
+        Self { ecn, ..self }
+    }
+    pub fn flow_label(self, flow_label: u32) -> Self {
+        debug_assert!(flow_label <= 0x000fffff);
+        Self { flow_label, ..self }
+    }
+    pub fn flags(self, flags: u8) -> Self {
+        Self { flags, ..self }
+    }
+    pub fn tag(self, tag: u16) -> Self {
+        Self { tag, ..self }
+    }
+    // add the HMAC TLV, at most one per header; the caller computes the HMAC itself,
+    // a multiple of 8 bytes up to 32 by Section 2.1.2 of RFC 8754
+    pub fn hmac(self, key_id: u32, destination_check: bool, hmac: &[u8]) -> Self {
+        assert!(hmac.len() <= 32 && hmac.len().is_multiple_of(8), "HMAC must be 0 to 32 bytes in steps of 8");
+        assert!(self.tlvs.is_empty(), "only one HMAC TLV is allowed");
+        let mut tlvs = self.tlvs;
+        tlvs.extend_from_slice(&[TlvType::Hmac.into(), (hmac.len() + 6) as u8]);
+        tlvs.extend_from_slice(&[if destination_check { 0x80 } else { 0 }, 0]);
+        tlvs.extend_from_slice(&key_id.to_be_bytes());
+        tlvs.extend_from_slice(hmac);
+        let writer = Self { tlvs, ..self };
+        assert!(writer.srh_len() <= Self::MAX_SRH_LEN, "segments and TLVs exceed the routing header length");
+        writer
+    }
+    pub fn into_payload(self) -> W {
+        self.payload
+    }
+    // TLVs padded to a multiple of 8
+    fn srh_len(&self) -> usize {
+        (Srh::<()>::SEGMENT_LIST + self.segments.len() * 16 + self.tlvs.len()).next_multiple_of(8)
+    }
+    fn header_len(&self) -> usize {
+        40 + self.srh_len()
+    }
+}
+
+impl<W: PacketWriter> Writer<W> {
+    pub fn len(&self) -> usize {
+        self.header_len() + self.payload.packet_len()
+    }
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![0u8; self.header_len()];
+        let mut packet = Packet::new(&mut header[..]);
+        packet.set_version(6);
+        packet.set_dscp(self.dscp);
+        packet.set_ecn(self.ecn);
+        packet.set_flow_label(self.flow_label);
+        debug_assert!(self.len() - 40 <= 0xffff);
+        packet.set_payload_len((self.len() - 40) as u16);
+        packet.set_next_header(Protocol::Routing);
+        packet.set_hop_limit(self.hop_limit);
+        packet.set_src_addr(self.src_addr);
+        packet.set_dst_addr(self.segments[0]);
+        let last_entry = self.segments.len() - 1;
+        let mut srh = Srh::new(&mut header[40..]);
+        srh.set_next_header(self.next_header);
+        srh.set_header_len(self.srh_len());
+        srh.set_routing_type(Srh::<()>::ROUTING_TYPE);
+        srh.set_segments_left(last_entry as u8);
+        srh.set_last_entry(last_entry as u8);
+        srh.set_flags(self.flags);
+        srh.set_tag(self.tag);
+        for (idx, segment) in self.segments.iter().rev().enumerate() {
+            srh.set_segment(idx, *segment);
+        }
+        let tlv_start = 40 + Srh::<()>::SEGMENT_LIST + self.segments.len() * 16;
+        header[tlv_start..tlv_start + self.tlvs.len()].copy_from_slice(&self.tlvs);
+        let padding = &mut header[tlv_start + self.tlvs.len()..];
+        if padding.len() >= 2 {
+            padding[0] = TlvType::PadN.into();
+            padding[1] = (padding.len() - 2) as u8;
+        }
+        header
+    }
+    #[must_use]
+    pub fn write(&mut self, buffer: &mut [u8]) -> usize {
+        let header_len = self.header_len();
+        let mut write = PacketWrite::new(&mut *buffer, self.byte_idx);
+        if self.byte_idx < header_len {
+            write.write_slice_at(0.., &self.header());
+        }
+        self.byte_idx = write.buffer_index();
+        let mut cur_idx = write.bytes_written();
+        if (header_len..self.len()).contains(&self.byte_idx) {
+            let write_len = self.payload.write_packet(&mut buffer[cur_idx..]);
+            self.byte_idx += write_len;
+            cur_idx += write_len;
+        }
+        cur_idx
+    }
+}
+
+impl<W: PacketWriter> PacketWriter for Writer<W> {
+    fn packet_len(&self) -> usize {
+        self.len()
+    }
+    fn write_packet(&mut self, buffer: &mut [u8]) -> usize {
+        self.write(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::IcmpWriter;
+
+    fn addr(s: &str) -> Address {
+        s.parse().unwrap()
+    }
+
+    fn encap() -> Vec<u8> {
+        let segments = [addr("fc00:1::e"), addr("fc00:2::e"), addr("fc00:3::d")];
+        let payload = IcmpWriter::echo_request(1, 2, [0x45u8; 12]);
+        let mut writer = Writer::new(addr("fc00::1"), &segments, Protocol::Icmpv6, payload)
+            .tag(0x1234)
+            .hmac(7, true, &[0xaa; 32]);
+        let mut buf = vec![0u8; writer.len()];
+        let mut written = 0;
+        // resumable writes in small pieces
+        while written < buf.len() {
+            let end = usize::min(written + 13, buf.len());
+            written += writer.write(&mut buf[written..end]);
+        }
+        buf
+    }
+
+    #[test]
+    fn srh_parse_encap() {
+        let buf = encap();
+        let packet = Packet::new(&buf[..]);
+        assert_eq!(packet.dst_addr(), addr("fc00:1::e"));
+        assert_eq!(packet.total_len(), buf.len());
+        let srh = packet.srh().unwrap();
+        assert_eq!(srh.header_len(), 8 + 3 * 16 + 40);
+        assert_eq!((srh.segments_left(), srh.last_entry(), srh.tag()), (2, 2, 0x1234));
+        assert_eq!(srh.segments().collect::<Vec<_>>(), [addr("fc00:3::d"), addr("fc00:2::e"), addr("fc00:1::e")]);
+        assert_eq!(srh.active_segment(), Some(addr("fc00:1::e")));
+        let tlvs: Vec<Tlv> = srh.tlvs().collect();
+        // an HMAC TLV of 8n bytes needs no padding
+        assert_eq!(tlvs.len(), 1);
+        let hmac = tlvs[0].hmac().unwrap();
+        assert!(hmac.destination_check);
+        assert_eq!((hmac.key_id, hmac.hmac), (7, &[0xaa; 32][..]));
+        assert_eq!(packet.upper_layer(), Ok((Protocol::Icmpv6, &buf[buf.len() - 20..])));
+        assert_eq!(srh.next_header(), Protocol::Icmpv6);
+        // a routing header of another type is not an SRH
+        let mut other = buf.clone();
+        other[42] = 3;
+        assert!(Packet::new(&other[..]).srh().is_none());
+        assert!(Srh::new_checked(&buf[40..60]).is_none());
+    }
+
+    #[test]
+    fn srh_end_behaviour() {
+        let mut buf = encap();
+        assert_eq!(end(&mut buf), Ok(addr("fc00:2::e")));
+        assert_eq!(end(&mut buf), Ok(addr("fc00:3::d")));
+        let packet = Packet::new(&buf[..]);
+        assert_eq!((packet.dst_addr(), packet.hop_limit()), (addr("fc00:3::d"), 62));
+        assert_eq!(packet.srh().unwrap().segments_left(), 0);
+        assert_eq!(end(&mut buf), Err(EndError::SegmentsLeftZero));
+
+        let mut buf = encap();
+        Packet::new(&mut buf[..]).set_hop_limit(1);
+        assert_eq!(end(&mut buf), Err(EndError::HopLimitExceeded));
+        let mut buf = encap();
+        Srh::new(&mut buf[40..]).set_segments_left(4);
+        assert_eq!(end(&mut buf), Err(EndError::ParameterProblem));
+        let mut buf = encap();
+        Packet::new(&mut buf[..]).set_next_header(Protocol::NoNextHeader);
+        assert_eq!(end(&mut buf), Err(EndError::NoSrh));
+        let mut buf = encap();
+        Packet::new(&mut buf[..]).set_payload_len(20);
+        assert_eq!(end(&mut buf), Err(EndError::Malformed(ExtHeaderError::Truncated)));
+        for len in 0..40 {
+            assert_eq!(end(&mut encap()[..len]), Err(EndError::Truncated));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "only one HMAC TLV")]
+    fn srh_writer_second_hmac() {
+        let payload = IcmpWriter::echo_request(1, 2, [0u8; 0]);
+        let _ = Writer::new(addr("fc00::1"), &[addr("fc00::e")], Protocol::Icmpv6, payload)
+            .hmac(7, false, &[0; 32])
+            .hmac(8, false, &[0; 32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "routing header length")]
+    fn srh_writer_too_long() {
+        let segments = vec![addr("fc00::e"); 127];
+        let payload = IcmpWriter::echo_request(1, 2, [0u8; 0]);
+        let _ = Writer::new(addr("fc00::1"), &segments, Protocol::Icmpv6, payload).hmac(7, false, &[0; 32]);
+    }
+}
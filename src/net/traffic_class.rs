@@ -0,0 +1,154 @@
+// Differentiated Services and ECN fields, RFC 2474 and RFC 3168
+
+// per-hop behaviour codepoints, upper six bits of traffic class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dscp {
+    Cs0, // default forwarding
+    Cs1, Cs2, Cs3, Cs4, Cs5, Cs6, Cs7,
+    Af11, Af12, Af13,
+    Af21, Af22, Af23,
+    Af31, Af32, Af33,
+    Af41, Af42, Af43,
+    Ef,
+    VoiceAdmit, // RFC 5865
+    Le, // lower effort, RFC 8622
+    Unknown(u8),
+}
+
+impl Dscp {
+    // assured forwarding class 1-4 with drop precedence 1-3, RFC 2597
+    pub fn af(class: u8, drop_precedence: u8) -> Option<Dscp> {
+        if !(1..=4).contains(&class) || !(1..=3).contains(&drop_precedence) {
+            return None
+        }
+        Some(Dscp::from(class << 3 | drop_precedence << 1))
+    }
+    // class selector 0-7, RFC 2474
+    pub fn cs(class: u8) -> Option<Dscp> {
+        if class > 7 {
+            return None
+        }
+        Some(Dscp::from(class << 3))
+    }
+}
+
+impl From<u8> for Dscp {
+    fn from(src: u8) -> Self {
+        match src & 0x3f {
+            0 => Dscp::Cs0,
+            8 => Dscp::Cs1,
+            16 => Dscp::Cs2,
+            24 => Dscp::Cs3,
+            32 => Dscp::Cs4,
+            40 => Dscp::Cs5,
+            48 => Dscp::Cs6,
+            56 => Dscp::Cs7,
+            10 => Dscp::Af11,
+            12 => Dscp::Af12,
+            14 => Dscp::Af13,
+            18 => Dscp::Af21,
+            20 => Dscp::Af22,
+            22 => Dscp::Af23,
+            26 => Dscp::Af31,
+            28 => Dscp::Af32,
+            30 => Dscp::Af33,
+            34 => Dscp::Af41,
+            36 => Dscp::Af42,
+            38 => Dscp::Af43,
+            46 => Dscp::Ef,
+            44 => Dscp::VoiceAdmit,
+            1 => Dscp::Le,
+            others => Dscp::Unknown(others),
+        }
+    }
+}
+
+impl From<Dscp> for u8 {
+    fn from(src: Dscp) -> u8 {
+        match src {
+            Dscp::Cs0 => 0,
+            Dscp::Cs1 => 8,
+            Dscp::Cs2 => 16,
+            Dscp::Cs3 => 24,
+            Dscp::Cs4 => 32,
+            Dscp::Cs5 => 40,
+            Dscp::Cs6 => 48,
+            Dscp::Cs7 => 56,
+            Dscp::Af11 => 10,
+            Dscp::Af12 => 12,
+            Dscp::Af13 => 14,
+            Dscp::Af21 => 18,
+            Dscp::Af22 => 20,
+            Dscp::Af23 => 22,
+            Dscp::Af31 => 26,
+            Dscp::Af32 => 28,
+            Dscp::Af33 => 30,
+            Dscp::Af41 => 34,
+            Dscp::Af42 => 36,
+            Dscp::Af43 => 38,
+            Dscp::Ef => 46,
+            Dscp::VoiceAdmit => 44,
+            Dscp::Le => 1,
+            Dscp::Unknown(others) => others & 0x3f,
+        }
+    }
+}
+
+// lower two bits of traffic class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ecn {
+    NotEct, // 0b00
+    Ect1,   // 0b01
+    Ect0,   // 0b10
+    Ce,     // 0b11, congestion experienced
+}
+
+impl Ecn {
+    // ECN-capable transport
+    pub fn is_ect(self) -> bool {
+        self != Ecn::NotEct
+    }
+}
+
+impl From<u8> for Ecn {
+    fn from(src: u8) -> Self {
+        match src & 0x03 {
+            0 => Ecn::NotEct,
+            1 => Ecn::Ect1,
+            2 => Ecn::Ect0,
+            _ => Ecn::Ce,
+        }
+    }
+}
+
+impl From<Ecn> for u8 {
+    fn from(src: Ecn) -> u8 {
+        match src {
+            Ecn::NotEct => 0,
+            Ecn::Ect1 => 1,
+            Ecn::Ect0 => 2,
+            Ecn::Ce => 3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dscp_ecn_values() {
+        for value in 0..64u8 {
+            assert_eq!(u8::from(Dscp::from(value)), value);
+        }
+        assert_eq!(Dscp::from(46), Dscp::Ef);
+        assert_eq!(Dscp::af(3, 2), Some(Dscp::Af32));
+        assert_eq!(Dscp::af(5, 1), None);
+        assert_eq!(Dscp::cs(6), Some(Dscp::Cs6));
+        assert_eq!(Dscp::from(3), Dscp::Unknown(3));
+        for value in 0..4u8 {
+            assert_eq!(u8::from(Ecn::from(value)), value);
+        }
+        assert!(Ecn::Ce.is_ect() && !Ecn::NotEct.is_ect());
+    }
+}
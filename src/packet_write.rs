@@ -1,6 +1,16 @@
 use byteorder::{ByteOrder, NetworkEndian};
 use core::ops::{Range, RangeFrom};
 
+/// Resumable packet writer
+///
+/// Each call to `write_packet` continues where the previous call stopped, so a packet
+/// can be emitted into buffers of any size. Returns 0 when the packet is done.
+pub trait PacketWriter {
+    // total length of the packet
+    fn packet_len(&self) -> usize;
+    fn write_packet(&mut self, buffer: &mut [u8]) -> usize;
+}
+
 pub struct PacketWrite<T> {
     out_buf: T,
     byte_idx: usize,
@@ -51,11 +61,11 @@ impl<T: AsMut<[u8]>> PacketWrite<T> {
     }
     #[inline]
     pub fn write_slice_at(&mut self, range: RangeFrom<usize>, slice: &[u8]) {
-        if !range.contains(&self.byte_idx) {
+        if !range.contains(&self.byte_idx) || self.byte_idx >= range.start + slice.len() {
             return
         }
         let out_buf = self.out_buf.as_mut();
-        let write_len = usize::min(slice.len() + (range.start - self.byte_idx), out_buf.len() - self.cur_idx);
+        let write_len = usize::min(slice.len() - (self.byte_idx - range.start), out_buf.len() - self.cur_idx);
         let start_idx = self.byte_idx - range.start;
         out_buf[self.cur_idx..self.cur_idx + write_len].copy_from_slice(&slice[start_idx..start_idx + write_len]); 
         self.byte_idx += write_len;
@@ -2,11 +2,13 @@
 #![allow(unused)]
 use libc;
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::Duration;
 use std::{io, mem};
 
 // use super::{ifreq, ifreq_for, ifreq_ioctl, ETH_P_ALL, SIOCGIFINDEX, SIOCGIFMTU};
 const SIOCGIFMTU: libc::c_ulong = 0x8921;
 const SIOCGIFINDEX: libc::c_ulong = 0x8933;
+const SIOCGIFNAME: libc::c_ulong = 0x8910;
 const ETH_P_ALL: libc::c_short = 0x0003;
 // const IFF_TAP: libc::c_int = 0x0002;
 // const IFF_NO_PI: libc::c_int = 0x1000;
@@ -30,6 +32,24 @@ fn ifreq_for(name: &str) -> ifreq {
     ifreq
 }
 
+// fill buffer from the kernel random source
+pub fn getrandom(buffer: &mut [u8]) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let rest = &mut buffer[filled..];
+        let len = unsafe { libc::getrandom(rest.as_mut_ptr() as *mut libc::c_void, rest.len(), 0) };
+        if len == -1 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue
+            }
+            return Err(err)
+        }
+        filled += len as usize;
+    }
+    Ok(())
+}
+
 fn ifreq_ioctl(
     lower: libc::c_int,
     ifreq: &mut ifreq,
@@ -45,6 +65,36 @@ fn ifreq_ioctl(
     Ok(ifreq.ifr_data)
 }
 
+// run an interface ioctl on a throwaway socket, for callers without a RawSocketDesc
+fn ifreq_ioctl_once(ifreq: &mut ifreq, cmd: libc::c_ulong) -> io::Result<libc::c_int> {
+    let lower = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    if lower == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let res = ifreq_ioctl(lower, ifreq, cmd);
+    unsafe {
+        libc::close(lower);
+    }
+    res
+}
+
+// index of a network interface by name, e.g. the zone of "fe80::1%eth0"
+pub fn interface_index(name: &str) -> io::Result<u32> {
+    if name.is_empty() || name.len() >= libc::IF_NAMESIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name"));
+    }
+    ifreq_ioctl_once(&mut ifreq_for(name), SIOCGIFINDEX).map(|index| index as u32)
+}
+
+// name of a network interface by index
+pub fn interface_name(index: u32) -> io::Result<String> {
+    let mut ifreq = ifreq_for("");
+    ifreq.ifr_data = index as libc::c_int;
+    ifreq_ioctl_once(&mut ifreq, SIOCGIFNAME)?;
+    let name: Vec<u8> = ifreq.ifr_name.iter().take_while(|&&c| c != 0).map(|&c| c as u8).collect();
+    Ok(String::from_utf8_lossy(&name).into_owned())
+}
+
 #[derive(Debug)]
 pub struct RawSocketDesc {
     lower: libc::c_int,
@@ -72,7 +122,7 @@ impl RawSocketDesc {
         };
 
         Ok(RawSocketDesc {
-            lower: lower,
+            lower,
             ifreq: ifreq_for(name),
         })
     }
@@ -81,6 +131,10 @@ impl RawSocketDesc {
         ifreq_ioctl(self.lower, &mut self.ifreq, SIOCGIFMTU).map(|mtu| mtu as usize)
     }
 
+    pub fn interface_index(&mut self) -> io::Result<u32> {
+        ifreq_ioctl(self.lower, &mut self.ifreq, SIOCGIFINDEX).map(|index| index as u32)
+    }
+
     pub fn bind_interface(&mut self) -> io::Result<()> {
         let sockaddr = libc::sockaddr_ll {
             sll_family: libc::AF_PACKET as u16,
@@ -106,6 +160,22 @@ impl RawSocketDesc {
         Ok(())
     }
 
+    // block until a frame is ready or timeout passes, returns whether one is ready
+    pub fn wait(&mut self, timeout: Duration) -> io::Result<bool> {
+        let mut pollfd = libc::pollfd { fd: self.lower, events: libc::POLLIN, revents: 0 };
+        // round up so a sub-millisecond remainder does not turn into a busy loop
+        let millis = u128::min(timeout.as_micros().div_ceil(1000), libc::c_int::MAX as u128);
+        let res = unsafe { libc::poll(&mut pollfd, 1, millis as libc::c_int) };
+        if res == -1 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                return Ok(false)
+            }
+            return Err(err)
+        }
+        Ok(res > 0)
+    }
+
     pub fn recv(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
         unsafe {
             let len = libc::recv(